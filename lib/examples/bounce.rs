@@ -0,0 +1,32 @@
+/* Offline bounce example.
+Renders the default drum pattern without an audio device and writes it to a WAV file.
+Usage: cargo run --example bounce -- [output.wav] [bars]
+*/
+
+use libgooey::render::OfflineRenderer;
+use libgooey::stage::Stage;
+use libgooey::wav::{write_wav_file, WavFormat, WavSpec};
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().unwrap_or_else(|| "bounce.wav".to_string());
+    let bars: u32 = match args.next() {
+        Some(bars) => bars.parse()?,
+        None => 4,
+    };
+
+    // Configure the stage with the default groove
+    let mut stage = Stage::new(44100.0);
+    stage.sequencer_set_default_patterns();
+    stage.sequencer_play();
+
+    // Render faster than realtime
+    let mut renderer = OfflineRenderer::new(2);
+    let samples = renderer.render_bars(&mut stage, bars);
+
+    let spec = WavSpec::for_stage(&stage, renderer.channels() as u16, WavFormat::Int24);
+    write_wav_file(&path, &samples, spec)?;
+
+    println!("Rendered {} bars to {}", bars, path);
+    Ok(())
+}
@@ -5,18 +5,21 @@ Minimal code to start the audio engine and trigger kick drum hits.
 use std::io::{self, Write};
 
 // Import the platform abstraction and audio engine
-use libgooey::platform::{AudioEngine, AudioOutput, CpalOutput};
+use libgooey::platform::{AudioEngine, AudioOutput, CpalOutput, StageCommand};
 
 // CLI example for kick drum
 #[cfg(feature = "native")]
 fn main() -> anyhow::Result<()> {
     // Create the audio engine
-    let audio_engine = AudioEngine::new(44100.0);
+    let mut audio_engine = AudioEngine::new(44100.0);
 
     // Create and configure the CPAL output
     let mut cpal_output = CpalOutput::new();
     cpal_output.initialize(44100.0)?;
-    cpal_output.create_stream_with_stage(audio_engine.stage(), audio_engine.audio_state())?;
+    let processor = audio_engine
+        .take_processor()
+        .ok_or_else(|| anyhow::anyhow!("Stage processor already taken"))?;
+    cpal_output.create_stream(processor)?;
 
     // Start the audio stream
     cpal_output.start()?;
@@ -33,8 +36,7 @@ fn main() -> anyhow::Result<()> {
         match input.trim() {
             " " | "" => {
                 println!("Triggering kick drum!");
-                let mut stage = audio_engine.stage_mut();
-                stage.trigger_kick();
+                let _ = audio_engine.send(StageCommand::TriggerKick);
             }
             "q" => {
                 println!("Quitting...");
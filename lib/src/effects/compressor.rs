@@ -0,0 +1,255 @@
+//! Feed-forward dynamics compressor with an optional external key
+//! The gain computer works in dB with a soft knee, and the gain reduction itself is smoothed by
+//! attack and release. Keying from another signal instead of the input gives sidechain ducking,
+//! e.g. a kick pushing the hats down.
+
+/// Averaging time of the RMS detector, in seconds
+const RMS_WINDOW: f32 = 0.01;
+
+/// Level treated as silence by the detector
+const MIN_LEVEL_DB: f32 = -120.0;
+
+indexed_enum! {
+    /// How the compressor measures the level of its key signal
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum DetectorMode {
+        /// Instantaneous level; catches transients
+        #[default]
+        Peak,
+        /// Average power over a short window; follows loudness more than transients
+        Rms,
+    }
+}
+
+/// Compressor settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompressorConfig {
+    pub enabled: bool,
+    pub threshold_db: f32, // Level where compression starts, -60.0 to 0.0
+    pub ratio: f32,        // 1.0 (none) to 20.0; above threshold, output rises 1 dB per `ratio` dB
+    pub knee_db: f32,      // Width of the soft knee around the threshold, 0.0 for a hard knee
+    pub attack: f32,       // Seconds for gain reduction to build up
+    pub release: f32,      // Seconds for gain reduction to recover
+    pub makeup_db: f32,    // Gain added after compression
+    pub detector: DetectorMode,
+}
+
+impl CompressorConfig {
+    pub fn new(threshold_db: f32, ratio: f32, knee_db: f32, attack: f32, release: f32, makeup_db: f32) -> Self {
+        Self {
+            enabled: true,
+            threshold_db: threshold_db.clamp(-60.0, 0.0),
+            ratio: ratio.clamp(1.0, 20.0),
+            knee_db: knee_db.clamp(0.0, 24.0),
+            attack: attack.clamp(0.0001, 0.5),
+            release: release.clamp(0.005, 2.0),
+            makeup_db: makeup_db.clamp(0.0, 24.0),
+            detector: DetectorMode::Peak,
+        }
+    }
+
+    pub fn with_detector(mut self, detector: DetectorMode) -> Self {
+        self.detector = detector;
+        self
+    }
+
+    /// Deep, fast ducking for a sidechain key such as a kick
+    pub fn ducking() -> Self {
+        Self::new(-30.0, 8.0, 6.0, 0.002, 0.15, 0.0)
+    }
+
+    /// Moderate settings that are switched off
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::new(-18.0, 4.0, 6.0, 0.01, 0.1, 0.0)
+        }
+    }
+
+    /// Output level in dB for a detected level, following the soft-knee curve
+    pub fn curve_db(&self, level_db: f32) -> f32 {
+        let over = level_db - self.threshold_db;
+        let slope = 1.0 / self.ratio - 1.0;
+        if 2.0 * over <= -self.knee_db {
+            level_db
+        } else if self.knee_db > 0.0 && 2.0 * over.abs() <= self.knee_db {
+            let knee_over = over + self.knee_db * 0.5;
+            level_db + slope * knee_over * knee_over / (2.0 * self.knee_db)
+        } else {
+            self.threshold_db + over / self.ratio
+        }
+    }
+}
+
+impl Default for CompressorConfig {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Compressor {
+    pub sample_rate: f32,
+    config: CompressorConfig,
+    attack_coeff: f32,
+    release_coeff: f32,
+    rms_coeff: f32,
+    /// Running mean square of the key for the RMS detector
+    mean_square: f32,
+    /// Smoothed gain reduction in dB
+    reduction_db: f32,
+    /// Largest reduction since the meter was last read
+    meter_db: f32,
+}
+
+impl Compressor {
+    pub fn new(sample_rate: f32, config: CompressorConfig) -> Self {
+        let mut compressor = Self {
+            sample_rate,
+            config,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            rms_coeff: (-1.0 / (RMS_WINDOW * sample_rate)).exp(),
+            mean_square: 0.0,
+            reduction_db: 0.0,
+            meter_db: 0.0,
+        };
+        compressor.set_config(config);
+        compressor
+    }
+
+    pub fn config(&self) -> CompressorConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: CompressorConfig) {
+        if !config.enabled {
+            self.reset();
+        }
+        self.config = config;
+        self.attack_coeff = (-1.0 / (config.attack * self.sample_rate)).exp();
+        self.release_coeff = (-1.0 / (config.release * self.sample_rate)).exp();
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Gain reduction being applied right now, in dB
+    pub fn gain_reduction_db(&self) -> f32 {
+        self.reduction_db
+    }
+
+    /// Largest gain reduction since the last call, in dB, for driving a meter
+    pub fn take_peak_gain_reduction_db(&mut self) -> f32 {
+        let reduction = self.meter_db;
+        self.meter_db = self.reduction_db;
+        reduction
+    }
+
+    pub fn reset(&mut self) {
+        self.mean_square = 0.0;
+        self.reduction_db = 0.0;
+        self.meter_db = 0.0;
+    }
+
+    /// Gain to apply for one key sample of absolute level `level`
+    #[inline]
+    fn next_gain(&mut self, level: f32) -> f32 {
+        let level = match self.config.detector {
+            DetectorMode::Peak => level,
+            DetectorMode::Rms => {
+                self.mean_square = level * level + (self.mean_square - level * level) * self.rms_coeff;
+                self.mean_square.sqrt()
+            }
+        };
+        let level_db = (20.0 * level.log10()).max(MIN_LEVEL_DB);
+        let target = level_db - self.config.curve_db(level_db);
+
+        let coeff = if target > self.reduction_db { self.attack_coeff } else { self.release_coeff };
+        self.reduction_db = target + (self.reduction_db - target) * coeff;
+        self.meter_db = self.meter_db.max(self.reduction_db);
+        10.0f32.powf((self.config.makeup_db - self.reduction_db) / 20.0)
+    }
+
+    /// Compress a mono signal by its own level
+    pub fn process_block(&mut self, buffer: &mut [f32]) {
+        for sample in buffer.iter_mut() {
+            *sample *= self.next_gain(sample.abs());
+        }
+    }
+
+    /// Compress a mono signal by the level of `key`, which must be at least as long
+    pub fn process_block_keyed(&mut self, buffer: &mut [f32], key: &[f32]) {
+        debug_assert!(key.len() >= buffer.len());
+        for (sample, key) in buffer.iter_mut().zip(key.iter()) {
+            *sample *= self.next_gain(key.abs());
+        }
+    }
+
+    /// Compress a stereo pair by the louder channel, applying the same gain to both
+    pub fn process_block_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let gain = self.next_gain(l.abs().max(r.abs()));
+            *l *= gain;
+            *r *= gain;
+        }
+    }
+
+    /// Compress a stereo pair by the level of `key`, which must be at least as long
+    pub fn process_block_stereo_keyed(&mut self, left: &mut [f32], right: &mut [f32], key: &[f32]) {
+        debug_assert!(key.len() >= left.len());
+        for ((l, r), key) in left.iter_mut().zip(right.iter_mut()).zip(key.iter()) {
+            let gain = self.next_gain(key.abs());
+            *l *= gain;
+            *r *= gain;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-4, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn hard_knee_follows_ratio_above_threshold() {
+        let config = CompressorConfig::new(-20.0, 4.0, 0.0, 0.01, 0.1, 0.0);
+        assert_close(config.curve_db(-40.0), -40.0);
+        assert_close(config.curve_db(-20.0), -20.0);
+        assert_close(config.curve_db(-12.0), -18.0);
+        assert_close(config.curve_db(0.0), -15.0);
+    }
+
+    #[test]
+    fn soft_knee_blends_into_both_lines() {
+        let config = CompressorConfig::new(-20.0, 4.0, 10.0, 0.01, 0.1, 0.0);
+        // Untouched below the knee and on the ratio line above it
+        assert_close(config.curve_db(-25.0), -25.0);
+        assert_close(config.curve_db(-15.0), -18.75);
+        assert_close(config.curve_db(-5.0), -16.25);
+        // Inside the knee the curve already dips below the input at the threshold
+        assert_close(config.curve_db(-20.0), -20.0 - 0.75 * 25.0 / 20.0);
+
+        // The curve never rises faster than the input and never jumps
+        let mut previous = config.curve_db(-40.0);
+        for step in 1..=400 {
+            let level = -40.0 + step as f32 * 0.1;
+            let output = config.curve_db(level);
+            let rise = output - previous;
+            assert!((0.0..=0.1 + 1e-4).contains(&rise), "rise of {rise} at {level} dB");
+            previous = output;
+        }
+    }
+
+    #[test]
+    fn unity_ratio_leaves_level_alone() {
+        let config = CompressorConfig::new(-30.0, 1.0, 6.0, 0.01, 0.1, 0.0);
+        for level in [-60.0, -33.0, -30.0, -27.0, 0.0] {
+            assert_close(config.curve_db(level), level);
+        }
+    }
+}
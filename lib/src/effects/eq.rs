@@ -0,0 +1,368 @@
+//! Multi-band parametric equaliser built from cascaded biquads
+//! Each band is a bell, shelf or high/low cut. Band settings glide like any `Biquad`, and
+//! coefficients are only recalculated while a setting is moving. All state lives inline, so
+//! changing bands never allocates and the EQ can be adjusted from the audio thread.
+
+use crate::filters::{Biquad, BiquadType, MonoFilter};
+
+/// Number of bands in every `ParametricEq`
+pub const EQ_BANDS: usize = 6;
+
+/// Most biquads a band can cascade, for the 48 dB/octave cuts
+const MAX_STAGES: usize = 4;
+
+/// Left and right; a track EQ only uses the first
+const CHANNELS: usize = 2;
+
+/// Butterworth Q of each stage for 2, 4, 6 and 8 pole cuts
+const BUTTERWORTH_Q: [[f32; MAX_STAGES]; MAX_STAGES] = [
+    [std::f32::consts::FRAC_1_SQRT_2, 0.0, 0.0, 0.0],
+    [0.5412, 1.3066, 0.0, 0.0],
+    [0.5176, std::f32::consts::FRAC_1_SQRT_2, 1.9319, 0.0],
+    [0.5098, 0.6013, 0.9000, 2.5629],
+];
+
+indexed_enum! {
+    /// Shape of one EQ band
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum EqBandType {
+        /// Boost or cut around the frequency, as wide as the Q allows
+        #[default]
+        Bell,
+        LowShelf,
+        HighShelf,
+        /// Removes everything below the frequency at the band's slope
+        HighPass,
+        /// Removes everything above the frequency at the band's slope
+        LowPass,
+    }
+}
+
+impl EqBandType {
+    fn biquad_type(self) -> BiquadType {
+        match self {
+            EqBandType::Bell => BiquadType::Peak,
+            EqBandType::LowShelf => BiquadType::LowShelf,
+            EqBandType::HighShelf => BiquadType::HighShelf,
+            EqBandType::HighPass => BiquadType::HighPass,
+            EqBandType::LowPass => BiquadType::LowPass,
+        }
+    }
+
+    /// Whether the slope setting applies to this band
+    pub fn is_cut(self) -> bool {
+        matches!(self, EqBandType::HighPass | EqBandType::LowPass)
+    }
+}
+
+indexed_enum! {
+    /// Steepness of a high-pass or low-pass band
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum EqSlope {
+        /// One biquad, shaped by the band's Q
+        #[default]
+        Db12,
+        /// Butterworth cascades; the band's Q is ignored
+        Db24,
+        Db36,
+        Db48,
+    }
+}
+
+impl EqSlope {
+    /// Number of cascaded biquads
+    pub fn stages(self) -> usize {
+        self.index() + 1
+    }
+}
+
+/// Settings for one EQ band
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EqBand {
+    pub enabled: bool,
+    pub band_type: EqBandType,
+    pub frequency: f32, // Hz
+    pub gain_db: f32,   // Bells and shelves only
+    pub q: f32,         // Bandwidth of bells, steepness of shelves, resonance of 12 dB cuts
+    pub slope: EqSlope, // High-pass and low-pass only
+}
+
+impl EqBand {
+    pub fn new(band_type: EqBandType, frequency: f32, gain_db: f32, q: f32) -> Self {
+        Self {
+            enabled: true,
+            band_type,
+            frequency: frequency.clamp(10.0, 22000.0),
+            gain_db: gain_db.clamp(-24.0, 24.0),
+            q: q.clamp(0.1, 18.0),
+            slope: EqSlope::Db12,
+        }
+    }
+
+    pub fn bell(frequency: f32, gain_db: f32, q: f32) -> Self {
+        Self::new(EqBandType::Bell, frequency, gain_db, q)
+    }
+
+    pub fn low_shelf(frequency: f32, gain_db: f32) -> Self {
+        Self::new(EqBandType::LowShelf, frequency, gain_db, std::f32::consts::FRAC_1_SQRT_2)
+    }
+
+    pub fn high_shelf(frequency: f32, gain_db: f32) -> Self {
+        Self::new(EqBandType::HighShelf, frequency, gain_db, std::f32::consts::FRAC_1_SQRT_2)
+    }
+
+    pub fn high_pass(frequency: f32, slope: EqSlope) -> Self {
+        Self::new(EqBandType::HighPass, frequency, 0.0, std::f32::consts::FRAC_1_SQRT_2).with_slope(slope)
+    }
+
+    pub fn low_pass(frequency: f32, slope: EqSlope) -> Self {
+        Self::new(EqBandType::LowPass, frequency, 0.0, std::f32::consts::FRAC_1_SQRT_2).with_slope(slope)
+    }
+
+    pub fn with_slope(mut self, slope: EqSlope) -> Self {
+        self.slope = slope;
+        self
+    }
+
+    /// A flat bell that is switched off
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::bell(1000.0, 0.0, 1.0)
+        }
+    }
+
+    fn stages(&self) -> usize {
+        if self.band_type.is_cut() {
+            self.slope.stages()
+        } else {
+            1
+        }
+    }
+
+    fn stage_q(&self, stage: usize) -> f32 {
+        if self.band_type.is_cut() && self.slope != EqSlope::Db12 {
+            BUTTERWORTH_Q[self.slope.index()][stage]
+        } else {
+            self.q
+        }
+    }
+}
+
+impl Default for EqBand {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+/// A fixed set of EQ bands with filter state for up to two channels
+#[derive(Debug, Clone)]
+pub struct ParametricEq {
+    pub sample_rate: f32,
+    bands: [EqBand; EQ_BANDS],
+    filters: [[[Biquad; MAX_STAGES]; CHANNELS]; EQ_BANDS],
+}
+
+impl ParametricEq {
+    /// Create an EQ with every band switched off, so it passes audio through untouched
+    pub fn new(sample_rate: f32) -> Self {
+        let flat = Biquad::new(sample_rate, BiquadType::Peak, 1000.0, 1.0);
+        Self {
+            sample_rate,
+            bands: [EqBand::disabled(); EQ_BANDS],
+            filters: [[[flat; MAX_STAGES]; CHANNELS]; EQ_BANDS],
+        }
+    }
+
+    /// Rebuild the filters for a new sample rate, keeping the band settings
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate == self.sample_rate {
+            return;
+        }
+        let bands = self.bands;
+        *self = Self::new(sample_rate);
+        for (index, band) in bands.into_iter().enumerate() {
+            self.set_band(index, band);
+        }
+    }
+
+    pub fn bands(&self) -> &[EqBand; EQ_BANDS] {
+        &self.bands
+    }
+
+    pub fn band(&self, index: usize) -> Option<EqBand> {
+        self.bands.get(index).copied()
+    }
+
+    /// Whether any band is switched on
+    pub fn is_active(&self) -> bool {
+        self.bands.iter().any(|band| band.enabled)
+    }
+
+    /// Change one band; frequency, gain and Q glide, a new shape or slope applies at once
+    pub fn set_band(&mut self, index: usize, band: EqBand) {
+        let Some(previous) = self.bands.get(index).copied() else {
+            return;
+        };
+        self.bands[index] = band;
+
+        for channel in self.filters[index].iter_mut() {
+            for (stage, filter) in channel.iter_mut().enumerate().take(band.stages()) {
+                filter.set_type(band.band_type.biquad_type());
+                filter.set_frequency(band.frequency);
+                filter.set_q(band.stage_q(stage));
+                filter.set_gain_db(band.gain_db);
+
+                // A stage that wasn't running has stale memory and parameters
+                if !previous.enabled || stage >= previous.stages() {
+                    filter.reset();
+                }
+            }
+        }
+    }
+
+    /// Switch every band off
+    pub fn clear(&mut self) {
+        for index in 0..EQ_BANDS {
+            self.set_band(index, EqBand::disabled());
+        }
+    }
+
+    /// Clear the filters' memory, e.g. when playback jumps
+    pub fn reset(&mut self) {
+        for filter in self.filters.iter_mut().flatten().flatten() {
+            filter.reset();
+        }
+    }
+
+    /// Equalise a mono signal in place
+    pub fn process_block(&mut self, buffer: &mut [f32]) {
+        self.process_channel(0, buffer);
+    }
+
+    /// Equalise a stereo pair in place, each channel with its own filter state
+    pub fn process_block_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        self.process_channel(0, left);
+        self.process_channel(1, right);
+    }
+
+    fn process_channel(&mut self, channel: usize, buffer: &mut [f32]) {
+        for (band, filters) in self.bands.iter().zip(self.filters.iter_mut()) {
+            if !band.enabled {
+                continue;
+            }
+            for filter in filters[channel].iter_mut().take(band.stages()) {
+                filter.process_block(buffer);
+            }
+        }
+    }
+
+    /// Combined gain of every enabled band at `frequency` Hz, e.g. for drawing the EQ curve
+    pub fn magnitude(&self, frequency: f32) -> f32 {
+        self.bands
+            .iter()
+            .zip(self.filters.iter())
+            .filter(|(band, _)| band.enabled)
+            .flat_map(|(band, filters)| filters[0].iter().take(band.stages()))
+            .map(|filter| filter.magnitude(frequency))
+            .product()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    fn eq_with(bands: &[EqBand]) -> ParametricEq {
+        let mut eq = ParametricEq::new(SAMPLE_RATE);
+        for (index, band) in bands.iter().enumerate() {
+            eq.set_band(index, *band);
+        }
+        eq
+    }
+
+    fn magnitude_db(eq: &ParametricEq, frequency: f32) -> f32 {
+        20.0 * eq.magnitude(frequency).log10()
+    }
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!((actual - expected).abs() <= tolerance, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn bell_and_shelves_reach_their_gain() {
+        let bell = eq_with(&[EqBand::bell(1000.0, 6.0, 1.0)]);
+        assert_close(magnitude_db(&bell, 1000.0), 6.0, 0.01);
+        assert_close(magnitude_db(&bell, 20.0), 0.0, 0.1);
+        assert_close(magnitude_db(&bell, 20000.0), 0.0, 0.1);
+
+        let low = eq_with(&[EqBand::low_shelf(200.0, 6.0)]);
+        assert_close(magnitude_db(&low, 10.0), 6.0, 0.1);
+        assert_close(magnitude_db(&low, 200.0), 3.0, 0.01);
+        assert_close(magnitude_db(&low, 10000.0), 0.0, 0.1);
+
+        let high = eq_with(&[EqBand::high_shelf(4000.0, -6.0)]);
+        assert_close(magnitude_db(&high, 100.0), 0.0, 0.1);
+        assert_close(magnitude_db(&high, 4000.0), -3.0, 0.01);
+        assert_close(magnitude_db(&high, 23000.0), -6.0, 0.1);
+
+        // Enabled bands multiply, disabled ones are ignored
+        let mut all = eq_with(&[EqBand::bell(1000.0, 6.0, 1.0), EqBand::low_shelf(200.0, 6.0), EqBand::high_shelf(4000.0, -6.0)]);
+        all.set_band(3, EqBand { enabled: false, ..EqBand::bell(1000.0, 12.0, 1.0) });
+        for frequency in [50.0, 200.0, 1000.0, 4000.0, 12000.0] {
+            let sum = magnitude_db(&bell, frequency) + magnitude_db(&low, frequency) + magnitude_db(&high, frequency);
+            assert_close(magnitude_db(&all, frequency), sum, 1e-3);
+        }
+    }
+
+    #[test]
+    fn butterworth_cuts_are_3db_down_at_cutoff() {
+        // An n-pole Butterworth is 6n dB down an octave past the cutoff
+        for (slope, octave_db) in [(EqSlope::Db24, -24.08), (EqSlope::Db48, -48.16)] {
+            let low = eq_with(&[EqBand::low_pass(1000.0, slope)]);
+            assert_close(magnitude_db(&low, 1000.0), -3.01, 0.02);
+            assert_close(magnitude_db(&low, 2000.0), octave_db, 0.5);
+            assert_close(magnitude_db(&low, 20.0), 0.0, 0.01);
+
+            let high = eq_with(&[EqBand::high_pass(1000.0, slope)]);
+            assert_close(magnitude_db(&high, 1000.0), -3.01, 0.02);
+            assert_close(magnitude_db(&high, 500.0), octave_db, 0.5);
+            assert_close(magnitude_db(&high, 20000.0), 0.0, 0.01);
+        }
+    }
+
+    #[test]
+    fn slope_change_resets_only_new_stages() {
+        let signal: Vec<f32> = (0..512).map(|index| ((index * 37 % 101) as f32 / 50.0) - 1.0).collect();
+        let run = |eq: &mut ParametricEq| {
+            let (mut left, mut right) = (signal.clone(), signal.clone());
+            eq.process_block_stereo(&mut left, &mut right);
+        };
+        // Ringing of a stage fed silence; zero only if its memory is clear
+        let ringing = |eq: &ParametricEq, channel: usize, stage: usize| {
+            let mut filter = eq.filters[0][channel][stage];
+            filter.process(0.0).abs() + filter.process(0.0).abs()
+        };
+
+        // Run all four stages so every one has memory, then drop back to one
+        let mut eq = eq_with(&[EqBand::high_pass(200.0, EqSlope::Db48)]);
+        run(&mut eq);
+        eq.set_band(0, EqBand::high_pass(200.0, EqSlope::Db12));
+        run(&mut eq);
+        for channel in 0..CHANNELS {
+            for stage in 0..MAX_STAGES {
+                assert!(ringing(&eq, channel, stage) > 0.0);
+            }
+        }
+
+        // Stage 0 kept running; stage 1 was idle and starts clean, the rest stay unused
+        eq.set_band(0, EqBand::high_pass(200.0, EqSlope::Db24));
+        for channel in 0..CHANNELS {
+            assert!(ringing(&eq, channel, 0) > 0.0);
+            assert_eq!(ringing(&eq, channel, 1), 0.0);
+            assert!(ringing(&eq, channel, 2) > 0.0);
+        }
+    }
+}
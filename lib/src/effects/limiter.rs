@@ -18,4 +18,287 @@ impl BrickWallLimiter {
             input
         }
     }
-}
\ No newline at end of file
+
+    /// Apply brick wall limiting to a block of samples in place
+    pub fn process_block(&self, buffer: &mut [f32]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+}
+
+/// Longest lookahead a `LookaheadLimiter` supports, in seconds
+pub const MAX_LOOKAHEAD: f32 = 0.02;
+
+/// How the master limiter keeps the output under its threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LimiterMode {
+    /// Delays the signal and turns the gain down smoothly before each peak arrives
+    #[default]
+    Lookahead,
+    /// Clips at the threshold with no delay, as `BrickWallLimiter` does
+    HardClip,
+}
+
+/// A stereo-linked peak limiter that sees peaks coming through a short delay
+/// Both channels share one gain so the stereo image doesn't shift under limiting. An attack longer
+/// than the lookahead lets the start of a peak through, and a final clip catches whatever is left.
+#[derive(Debug, Clone)]
+pub struct LookaheadLimiter {
+    pub threshold: f32,
+    mode: LimiterMode,
+    sample_rate: f32,
+    attack: f32,
+    release: f32,
+    lookahead: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    /// Delay in samples, at most the capacity of the delay lines
+    delay: usize,
+    left_delay: Vec<f32>,
+    right_delay: Vec<f32>,
+    write_index: usize,
+    /// Monotonic queue of (frame, required gain) over the frames in the delay line, lowest gain
+    /// at the front, kept in a ring buffer as large as the delay lines
+    minima: Vec<(usize, f32)>,
+    minima_start: usize,
+    minima_len: usize,
+    /// Frames processed, to tell when a queued gain has left the delay line
+    frame: usize,
+    gain: f32,
+    /// Lowest gain reached since the meter was last read
+    meter_gain: f32,
+}
+
+impl LookaheadLimiter {
+    /// Create a limiter with 5 ms lookahead, 1 ms attack and 100 ms release
+    /// The delay lines are sized for `MAX_LOOKAHEAD` here, so changing settings later never allocates.
+    pub fn new(sample_rate: f32, threshold: f32) -> Self {
+        let capacity = (MAX_LOOKAHEAD * sample_rate).ceil() as usize + 1;
+        let mut limiter = Self {
+            threshold,
+            mode: LimiterMode::default(),
+            sample_rate,
+            attack: 0.001,
+            release: 0.1,
+            lookahead: 0.005,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            delay: 0,
+            left_delay: vec![0.0; capacity],
+            right_delay: vec![0.0; capacity],
+            write_index: 0,
+            minima: vec![(0, 1.0); capacity],
+            minima_start: 0,
+            minima_len: 0,
+            frame: 0,
+            gain: 1.0,
+            meter_gain: 1.0,
+        };
+        limiter.set_attack(limiter.attack);
+        limiter.set_release(limiter.release);
+        limiter.set_lookahead(limiter.lookahead);
+        limiter
+    }
+
+    pub fn mode(&self) -> LimiterMode {
+        self.mode
+    }
+
+    /// Switch between lookahead limiting and hard clipping; clears the delay line
+    pub fn set_mode(&mut self, mode: LimiterMode) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.reset();
+        }
+    }
+
+    pub fn attack(&self) -> f32 {
+        self.attack
+    }
+
+    /// Time in seconds for the gain to come down on a new peak
+    pub fn set_attack(&mut self, attack: f32) {
+        self.attack = attack.clamp(0.0001, 0.1);
+        self.attack_coeff = (-1.0 / (self.attack * self.sample_rate)).exp();
+    }
+
+    pub fn release(&self) -> f32 {
+        self.release
+    }
+
+    /// Time in seconds for the gain to recover once peaks have passed
+    pub fn set_release(&mut self, release: f32) {
+        self.release = release.clamp(0.001, 2.0);
+        self.release_coeff = (-1.0 / (self.release * self.sample_rate)).exp();
+    }
+
+    pub fn lookahead(&self) -> f32 {
+        self.lookahead
+    }
+
+    /// Delay in seconds the limiter looks ahead by, up to `MAX_LOOKAHEAD`; clears the delay line
+    pub fn set_lookahead(&mut self, lookahead: f32) {
+        self.lookahead = lookahead.clamp(0.0, MAX_LOOKAHEAD);
+        let delay = ((self.lookahead * self.sample_rate).round() as usize).min(self.left_delay.len() - 1);
+        if delay != self.delay {
+            self.delay = delay;
+            self.reset();
+        }
+    }
+
+    /// Samples the output lags the input by
+    pub fn latency_samples(&self) -> usize {
+        match self.mode {
+            LimiterMode::Lookahead => self.delay,
+            LimiterMode::HardClip => 0,
+        }
+    }
+
+    /// Gain reduction being applied right now, in dB (0.0 when not limiting)
+    pub fn gain_reduction_db(&self) -> f32 {
+        -20.0 * self.gain.log10()
+    }
+
+    /// Largest gain reduction since the last call, in dB, for driving a meter
+    pub fn take_peak_gain_reduction_db(&mut self) -> f32 {
+        let reduction = -20.0 * self.meter_gain.log10();
+        self.meter_gain = self.gain;
+        reduction
+    }
+
+    /// Clear the delay line and return to unity gain
+    pub fn reset(&mut self) {
+        self.left_delay.fill(0.0);
+        self.right_delay.fill(0.0);
+        self.write_index = 0;
+        self.minima_start = 0;
+        self.minima_len = 0;
+        self.frame = 0;
+        self.gain = 1.0;
+        self.meter_gain = 1.0;
+    }
+
+    /// Limit one stereo frame
+    #[inline]
+    pub fn process_stereo(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if self.mode == LimiterMode::HardClip {
+            let clipper = BrickWallLimiter::new(self.threshold);
+            return (clipper.process(left), clipper.process(right));
+        }
+        let threshold = self.threshold.max(0.0);
+
+        // Gain this frame needs, held for as long as the frame is in the delay line
+        let peak = left.abs().max(right.abs());
+        let required = if peak > threshold { threshold / peak } else { 1.0 };
+        let target = self.window_minimum(required);
+
+        let coeff = if target < self.gain { self.attack_coeff } else { self.release_coeff };
+        self.gain = target + (self.gain - target) * coeff;
+        self.meter_gain = self.meter_gain.min(self.gain);
+
+        let capacity = self.left_delay.len();
+        self.left_delay[self.write_index] = left;
+        self.right_delay[self.write_index] = right;
+        let read_index = (self.write_index + capacity - self.delay) % capacity;
+        self.write_index = (self.write_index + 1) % capacity;
+
+        let left = (self.left_delay[read_index] * self.gain).clamp(-threshold, threshold);
+        let right = (self.right_delay[read_index] * self.gain).clamp(-threshold, threshold);
+        (left, right)
+    }
+
+    /// Add this frame's required gain and return the lowest one still in the delay line
+    #[inline]
+    fn window_minimum(&mut self, required: f32) -> f32 {
+        let capacity = self.minima.len();
+        // Gains no lower than the new one can never be the minimum again
+        while self.minima_len > 0 {
+            let back = (self.minima_start + self.minima_len - 1) % capacity;
+            if self.minima[back].1 < required {
+                break;
+            }
+            self.minima_len -= 1;
+        }
+        // Drop the front once its frame has come out of the delay line
+        if self.minima_len > 0 && self.frame.wrapping_sub(self.minima[self.minima_start].0) > self.delay {
+            self.minima_start = (self.minima_start + 1) % capacity;
+            self.minima_len -= 1;
+        }
+        self.minima[(self.minima_start + self.minima_len) % capacity] = (self.frame, required);
+        self.minima_len += 1;
+        self.frame = self.frame.wrapping_add(1);
+        self.minima[self.minima_start].1
+    }
+
+    /// Limit a block of stereo frames in place
+    pub fn process_block_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            (*l, *r) = self.process_stereo(*l, *r);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    #[test]
+    fn output_lags_input_by_latency() {
+        let mut limiter = LookaheadLimiter::new(SAMPLE_RATE, 1.0);
+        let latency = limiter.latency_samples();
+        assert_eq!(latency, (0.005 * SAMPLE_RATE) as usize);
+
+        // A quiet click passes through untouched, just later
+        let mut output = Vec::new();
+        for frame in 0..latency * 2 {
+            let input = if frame == 10 { 0.5 } else { 0.0 };
+            output.push(limiter.process_stereo(input, -input));
+        }
+        for (frame, &(left, right)) in output.iter().enumerate() {
+            let expected = if frame == 10 + latency { 0.5 } else { 0.0 };
+            assert_eq!((left, right), (expected, -expected), "frame {frame}");
+        }
+
+        limiter.set_mode(LimiterMode::HardClip);
+        assert_eq!(limiter.latency_samples(), 0);
+    }
+
+    #[test]
+    fn output_stays_under_ceiling() {
+        let threshold = 0.5;
+        let mut limiter = LookaheadLimiter::new(SAMPLE_RATE, threshold);
+        let mut loudest: f32 = 0.0;
+        for frame in 0..4800 {
+            let input = 2.0 * (frame as f32 * 0.05).sin();
+            let (left, right) = limiter.process_stereo(input, input * 0.5);
+            loudest = loudest.max(left.abs()).max(right.abs());
+        }
+        assert!(loudest <= threshold, "peak {loudest} over ceiling");
+        assert!(loudest > threshold * 0.9, "limited too hard, peak {loudest}");
+    }
+
+    #[test]
+    fn gain_covers_every_peak_in_the_delay_line() {
+        let mut limiter = LookaheadLimiter::new(SAMPLE_RATE, 0.5);
+        limiter.set_attack(0.0001);
+        limiter.set_release(0.001);
+        let delay = limiter.latency_samples();
+
+        // A loud peak, then a smaller one that is still in the delay line when the first leaves
+        let second = delay / 2;
+        for frame in 0..second + delay {
+            let input = match frame {
+                0 => 2.0,
+                f if f == second => 1.0,
+                _ => 0.0,
+            };
+            limiter.process_stereo(input, input);
+        }
+        // The smaller peak needs 6 dB of reduction as it comes out
+        limiter.process_stereo(0.0, 0.0);
+        assert!(limiter.gain_reduction_db() > 5.9, "only {} dB", limiter.gain_reduction_db());
+    }
+}
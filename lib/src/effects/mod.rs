@@ -1,3 +1,11 @@
+pub mod compressor;
+pub mod eq;
 pub mod limiter;
+pub mod pan;
+pub mod reverb;
 
-pub use self::limiter::*;
\ No newline at end of file
+pub use self::compressor::*;
+pub use self::eq::*;
+pub use self::limiter::*;
+pub use self::pan::*;
+pub use self::reverb::*;
@@ -0,0 +1,34 @@
+/// How a pan position is turned into left and right channel gains
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PanLaw {
+    /// Full level in both channels at center, the far channel fades out as the source moves away
+    /// Centered sources sound exactly as they did in the mono mix.
+    #[default]
+    Balance,
+    /// Gains sum to one: -6 dB in each channel at center
+    Linear,
+    /// Sine/cosine gains keep perceived loudness steady: -3 dB in each channel at center
+    ConstantPower,
+    /// Halfway between linear and constant power: -4.5 dB in each channel at center
+    Compromise,
+}
+
+impl PanLaw {
+    /// Left and right gains for a pan position from -1.0 (hard left) to 1.0 (hard right)
+    pub fn gains(self, pan: f32) -> (f32, f32) {
+        let pan = pan.clamp(-1.0, 1.0);
+        match self {
+            PanLaw::Balance => ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0)),
+            PanLaw::Linear => ((1.0 - pan) * 0.5, (1.0 + pan) * 0.5),
+            PanLaw::ConstantPower => {
+                let angle = (pan + 1.0) * std::f32::consts::FRAC_PI_4;
+                (angle.cos().max(0.0), angle.sin().max(0.0))
+            }
+            PanLaw::Compromise => {
+                let (linear_left, linear_right) = PanLaw::Linear.gains(pan);
+                let (power_left, power_right) = PanLaw::ConstantPower.gains(pan);
+                ((linear_left * power_left).sqrt(), (linear_right * power_right).sqrt())
+            }
+        }
+    }
+}
@@ -0,0 +1,388 @@
+//! Freeverb-style stereo reverb: eight damped comb filters into four allpasses per channel
+//! Every delay line, including the longest pre-delay, is allocated when the reverb is created, so
+//! changing settings never allocates and the reverb can run on the audio thread in any build.
+
+/// Comb filter lengths in samples at 44.1 kHz
+const COMB_TUNING: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+
+/// Allpass lengths in samples at 44.1 kHz
+const ALLPASS_TUNING: [usize; 4] = [556, 441, 341, 225];
+
+/// Extra delay on the right channel's filters, which decorrelates the channels
+const STEREO_SPREAD: usize = 23;
+
+const TUNING_SAMPLE_RATE: f32 = 44100.0;
+
+/// Input attenuation so the comb bank's sum stays near unity
+const INPUT_GAIN: f32 = 0.015;
+
+const ALLPASS_FEEDBACK: f32 = 0.5;
+
+/// Longest pre-delay, in seconds
+pub const MAX_PRE_DELAY: f32 = 0.25;
+
+/// Tail level, before the mix, below which a reverb with no input is treated as finished
+const SILENCE: f32 = 1e-5;
+
+/// Reverb settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReverbConfig {
+    pub size: f32,      // 0.0-1.0, how long the tail rings
+    pub damping: f32,   // 0.0-1.0, how quickly high frequencies die away in the tail
+    pub pre_delay: f32, // Seconds before the tail starts, up to MAX_PRE_DELAY
+    pub width: f32,     // 0.0 (mono) to 1.0 (full stereo)
+    pub mix: f32,       // Wet level: the return level on a send, the wet/dry balance as an insert
+}
+
+impl ReverbConfig {
+    pub fn new(size: f32, damping: f32, pre_delay: f32, width: f32, mix: f32) -> Self {
+        Self {
+            size: size.clamp(0.0, 1.0),
+            damping: damping.clamp(0.0, 1.0),
+            pre_delay: pre_delay.clamp(0.0, MAX_PRE_DELAY),
+            width: width.clamp(0.0, 1.0),
+            mix: mix.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn room() -> Self {
+        Self::new(0.5, 0.5, 0.01, 1.0, 0.3)
+    }
+
+    pub fn small_room() -> Self {
+        Self::new(0.25, 0.7, 0.0, 0.8, 0.3)
+    }
+
+    pub fn hall() -> Self {
+        Self::new(0.85, 0.3, 0.03, 1.0, 0.35)
+    }
+}
+
+impl Default for ReverbConfig {
+    fn default() -> Self {
+        Self::room()
+    }
+}
+
+/// Feedback comb filter with a one-pole low-pass in the loop
+#[derive(Debug, Clone)]
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    filter_store: f32,
+}
+
+impl Comb {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![0.0; length.max(1)],
+            index: 0,
+            filter_store: 0.0,
+        }
+    }
+
+    #[inline]
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.filter_store = output * (1.0 - damping) + self.filter_store * damping;
+        self.buffer[self.index] = input + self.filter_store * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+
+    fn clear(&mut self) {
+        self.buffer.fill(0.0);
+        self.filter_store = 0.0;
+    }
+}
+
+/// Schroeder allpass that diffuses the comb output
+#[derive(Debug, Clone)]
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl Allpass {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![0.0; length.max(1)],
+            index: 0,
+        }
+    }
+
+    #[inline]
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        self.buffer[self.index] = input + delayed * ALLPASS_FEEDBACK;
+        self.index = (self.index + 1) % self.buffer.len();
+        delayed - input
+    }
+
+    fn clear(&mut self) {
+        self.buffer.fill(0.0);
+    }
+}
+
+/// One channel's comb bank and allpass chain
+#[derive(Debug, Clone)]
+struct ReverbChannel {
+    combs: [Comb; 8],
+    allpasses: [Allpass; 4],
+}
+
+impl ReverbChannel {
+    fn new(sample_rate: f32, spread: usize) -> Self {
+        let scale = |length: usize| ((length + spread) as f32 * sample_rate / TUNING_SAMPLE_RATE) as usize;
+        Self {
+            combs: COMB_TUNING.map(|length| Comb::new(scale(length))),
+            allpasses: ALLPASS_TUNING.map(|length| Allpass::new(scale(length))),
+        }
+    }
+
+    #[inline]
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let mut output = 0.0;
+        for comb in self.combs.iter_mut() {
+            output += comb.process(input, feedback, damping);
+        }
+        for allpass in self.allpasses.iter_mut() {
+            output = allpass.process(output);
+        }
+        output
+    }
+
+    /// Longest time input takes to reach the output
+    fn latency(&self) -> usize {
+        self.combs.iter().map(|comb| comb.buffer.len()).max().unwrap_or(0)
+    }
+
+    fn clear(&mut self) {
+        self.combs.iter_mut().for_each(Comb::clear);
+        self.allpasses.iter_mut().for_each(Allpass::clear);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Reverb {
+    pub sample_rate: f32,
+    config: ReverbConfig,
+    /// Derived from the config
+    feedback: f32,
+    damping: f32,
+    pre_delay_samples: usize,
+    pre_delay: Vec<f32>,
+    pre_delay_index: usize,
+    left: ReverbChannel,
+    right: ReverbChannel,
+    /// Consecutive silent input samples, to tell when the pre-delay has emptied
+    silent_input: usize,
+    /// Loudest channel output this block, before mix and width, so a low mix can't hide the tail
+    tail_peak: f32,
+    /// Set once the tail has died away with no input, so the reverb can be skipped
+    idle: bool,
+}
+
+impl Reverb {
+    pub fn new(sample_rate: f32, config: ReverbConfig) -> Self {
+        let mut reverb = Self {
+            sample_rate,
+            config,
+            feedback: 0.0,
+            damping: 0.0,
+            pre_delay_samples: 0,
+            pre_delay: vec![0.0; (MAX_PRE_DELAY * sample_rate).ceil() as usize + 1],
+            pre_delay_index: 0,
+            left: ReverbChannel::new(sample_rate, 0),
+            right: ReverbChannel::new(sample_rate, STEREO_SPREAD),
+            silent_input: 0,
+            tail_peak: 0.0,
+            idle: true,
+        };
+        reverb.set_config(config);
+        reverb
+    }
+
+    pub fn config(&self) -> ReverbConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: ReverbConfig) {
+        self.config = config;
+        // Freeverb's scaling: feedback from 0.7 to 0.98, damping up to 0.4
+        self.feedback = 0.7 + config.size * 0.28;
+        self.damping = config.damping * 0.4;
+        self.pre_delay_samples = ((config.pre_delay * self.sample_rate).round() as usize).min(self.pre_delay.len() - 1);
+    }
+
+    /// Whether the tail has finished; an idle reverb needs no processing until it gets input again
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// Silence the tail immediately
+    pub fn clear(&mut self) {
+        self.pre_delay.fill(0.0);
+        self.left.clear();
+        self.right.clear();
+        // Empty delay lines are as good as a long run of silent input
+        self.silent_input = usize::MAX;
+        self.tail_peak = 0.0;
+        self.idle = true;
+    }
+
+    /// Wet output for one mono input sample
+    #[inline]
+    fn process_wet(&mut self, input: f32) -> (f32, f32) {
+        self.silent_input = if input == 0.0 { self.silent_input.saturating_add(1) } else { 0 };
+        let length = self.pre_delay.len();
+        self.pre_delay[self.pre_delay_index] = input;
+        let read_index = (self.pre_delay_index + length - self.pre_delay_samples) % length;
+        self.pre_delay_index = (self.pre_delay_index + 1) % length;
+        let input = self.pre_delay[read_index] * INPUT_GAIN;
+
+        let left = self.left.process(input, self.feedback, self.damping);
+        let right = self.right.process(input, self.feedback, self.damping);
+        self.tail_peak = self.tail_peak.max(left.abs()).max(right.abs());
+
+        // Cross-mix the channels for width
+        let wet = self.config.mix;
+        let direct = wet * (self.config.width * 0.5 + 0.5);
+        let cross = wet * (1.0 - self.config.width) * 0.5;
+        (left * direct + right * cross, right * direct + left * cross)
+    }
+
+    /// Use as a send: add the reverb of the mono `input` into `left` and `right`
+    /// All three buffers must be the same length.
+    pub fn process_block(&mut self, input: &[f32], left: &mut [f32], right: &mut [f32]) {
+        for ((&sample, l), r) in input.iter().zip(left.iter_mut()).zip(right.iter_mut()) {
+            let (wet_left, wet_right) = self.process_wet(sample);
+            *l += wet_left;
+            *r += wet_right;
+        }
+        self.update_idle();
+    }
+
+    /// Use as an insert: blend the reverb of a stereo signal with the dry signal in place
+    pub fn process_block_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let dry = 1.0 - self.config.mix;
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let input = (*l + *r) * 0.5;
+            let (wet_left, wet_right) = self.process_wet(input);
+            *l = *l * dry + wet_left;
+            *r = *r * dry + wet_right;
+        }
+        self.update_idle();
+    }
+
+    fn update_idle(&mut self) {
+        let peak = std::mem::take(&mut self.tail_peak);
+        // Input still in the pre-delay or the combs hasn't reached the output yet
+        let latency = self.pre_delay_samples + self.left.latency().max(self.right.latency());
+        let finished = peak < SILENCE && self.silent_input > latency;
+        if finished && !self.idle {
+            // Flush what's left of the tail so denormals don't build up
+            self.clear();
+        }
+        self.idle = finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44100.0;
+    const BLOCK: usize = 64;
+
+    /// Send one block holding an impulse, then return the reverb
+    fn excited(config: ReverbConfig) -> Reverb {
+        let mut reverb = Reverb::new(SAMPLE_RATE, config);
+        let mut input = [0.0; BLOCK];
+        input[0] = 1.0;
+        reverb.process_block(&input, &mut [0.0; BLOCK], &mut [0.0; BLOCK]);
+        reverb
+    }
+
+    /// Send silence in blocks until the reverb goes idle, returning the samples that took and the
+    /// last sample index at which either output channel was audible
+    fn run_until_idle(reverb: &mut Reverb) -> (usize, Option<usize>) {
+        let mut samples = BLOCK;
+        let mut last_audible = None;
+        while !reverb.is_idle() {
+            assert!(samples < 60 * SAMPLE_RATE as usize, "reverb never went idle");
+            let (mut left, mut right) = ([0.0; BLOCK], [0.0; BLOCK]);
+            reverb.process_block(&[0.0; BLOCK], &mut left, &mut right);
+            for (index, (l, r)) in left.iter().zip(right.iter()).enumerate() {
+                if l.abs().max(r.abs()) >= SILENCE {
+                    last_audible = Some(samples + index);
+                }
+            }
+            samples += BLOCK;
+        }
+        (samples, last_audible)
+    }
+
+    #[test]
+    fn tail_keeps_ringing_at_zero_mix() {
+        let mut reverb = excited(ReverbConfig::new(0.8, 0.3, 0.02, 1.0, 0.0));
+
+        // Well past the pre-delay and comb latency the output is silent, but the tail isn't over
+        let mut heard = 0.0f32;
+        for _ in 0..(SAMPLE_RATE as usize / 2 / BLOCK) {
+            let (mut left, mut right) = ([0.0; BLOCK], [0.0; BLOCK]);
+            reverb.process_block(&[0.0; BLOCK], &mut left, &mut right);
+            heard = left.iter().chain(right.iter()).fold(heard, |peak, sample| peak.max(sample.abs()));
+        }
+        assert_eq!(heard, 0.0);
+        assert!(!reverb.is_idle());
+
+        // Turning the return up reveals the tail that kept ringing
+        reverb.set_config(ReverbConfig::new(0.8, 0.3, 0.02, 1.0, 1.0));
+        let (mut left, mut right) = ([0.0; BLOCK], [0.0; BLOCK]);
+        reverb.process_block(&[0.0; BLOCK], &mut left, &mut right);
+        assert!(left.iter().any(|sample| sample.abs() > SILENCE));
+    }
+
+    #[test]
+    fn idle_only_after_pre_delay_latency_and_decay() {
+        let config = ReverbConfig::new(0.5, 0.5, 0.1, 1.0, 1.0);
+        let mut reverb = excited(config);
+        let pre_delay = (0.1 * SAMPLE_RATE).round() as usize;
+        let latency = pre_delay + reverb.right.latency();
+
+        // Nothing comes out during the pre-delay, but the impulse is still on its way
+        let mut samples = BLOCK;
+        while samples + BLOCK <= pre_delay {
+            let (mut left, mut right) = ([0.0; BLOCK], [0.0; BLOCK]);
+            reverb.process_block(&[0.0; BLOCK], &mut left, &mut right);
+            assert!(left.iter().chain(right.iter()).all(|&sample| sample == 0.0));
+            assert!(!reverb.is_idle());
+            samples += BLOCK;
+        }
+
+        let (idle_at, last_audible) = run_until_idle(&mut reverb);
+        let last_audible = last_audible.expect("the tail was never heard");
+        assert!(last_audible > latency);
+        assert!(idle_at > last_audible);
+
+        // A bigger room decays for longer before going idle
+        let (long_idle_at, _) = run_until_idle(&mut excited(ReverbConfig { size: 1.0, ..config }));
+        assert!(long_idle_at > idle_at * 2);
+    }
+
+    #[test]
+    fn zero_width_is_mono() {
+        let mut reverb = excited(ReverbConfig::new(0.7, 0.4, 0.0, 0.0, 1.0));
+        let input: Vec<f32> = (0..4096).map(|index| ((index * 31 % 97) as f32 / 48.0) - 1.0).collect();
+        let (mut left, mut right) = (vec![0.0; input.len()], vec![0.0; input.len()]);
+        reverb.process_block(&input, &mut left, &mut right);
+        assert!(left.iter().any(|&sample| sample != 0.0));
+        assert_eq!(left, right);
+
+        // Full width keeps the channels apart
+        reverb.set_config(ReverbConfig::new(0.7, 0.4, 0.0, 1.0, 1.0));
+        reverb.process_block(&input, &mut left, &mut right);
+        assert_ne!(left, right);
+    }
+}
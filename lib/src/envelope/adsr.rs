@@ -0,0 +1,251 @@
+/// How steeply a curved segment bends; exponential segments cover about 5 time constants
+const CURVE_STEEPNESS: f32 = 5.0;
+
+/// Seconds a `RetriggerMode::FadeOut` envelope takes to fade out before restarting
+pub const RETRIGGER_FADE_TIME: f32 = 0.003;
+
+/// What an envelope does when triggered again while still sounding
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum RetriggerMode {
+    /// Jump straight back to the start; can click if the previous hit is still loud
+    #[default]
+    Hard,
+    /// Attack from the current level and keep the oscillator phase running
+    Legato,
+    /// Fade the previous hit out over `RETRIGGER_FADE_TIME`, then restart from silence
+    /// The new hit starts that much later, in exchange for a clean transient.
+    FadeOut,
+}
+
+/// Shape of one envelope segment
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum EnvelopeCurve {
+    /// Straight line from start to end
+    #[default]
+    Linear,
+    /// Bends toward zero: decays drop quickly then trail off like an analogue envelope, attacks swell slowly
+    Exponential,
+    /// Bends toward full level: attacks jump up quickly, decays hold then fall away
+    Logarithmic,
+    /// Adjustable bend from -1.0 (logarithmic) through 0.0 (linear) to 1.0 (exponential)
+    Tension(f32),
+}
+
+impl EnvelopeCurve {
+    fn tension(self) -> f32 {
+        match self {
+            EnvelopeCurve::Linear => 0.0,
+            EnvelopeCurve::Exponential => 1.0,
+            EnvelopeCurve::Logarithmic => -1.0,
+            EnvelopeCurve::Tension(tension) => tension.clamp(-1.0, 1.0),
+        }
+    }
+
+    /// Level `progress` (0.0 to 1.0) of the way through a segment from `start` to `end`
+    pub fn interpolate(self, start: f32, end: f32, progress: f32) -> f32 {
+        let progress = progress.clamp(0.0, 1.0);
+        let mut k = self.tension() * CURVE_STEEPNESS;
+        if k.abs() < 1e-3 {
+            return start + (end - start) * progress;
+        }
+        // Positive tension bends toward zero whichever way the segment moves
+        if end < start {
+            k = -k;
+        }
+        let weight = ((k * progress).exp() - 1.0) / (k.exp() - 1.0);
+        start + (end - start) * weight
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ADSRConfig {
+    pub attack_time: f32,   // seconds
+    pub decay_time: f32,    // seconds  
+    pub sustain_level: f32, // 0.0 to 1.0
+    pub release_time: f32,  // seconds
+    pub attack_curve: EnvelopeCurve,
+    pub decay_curve: EnvelopeCurve,
+    pub release_curve: EnvelopeCurve,
+}
+
+impl ADSRConfig {
+    pub fn new(attack: f32, decay: f32, sustain: f32, release: f32) -> Self {
+        Self {
+            attack_time: attack.max(0.001), // Minimum attack to avoid artifacts
+            decay_time: decay.max(0.001),   // Minimum decay 
+            sustain_level: sustain.clamp(0.0, 1.0),
+            release_time: release.max(0.001), // Minimum release
+            attack_curve: EnvelopeCurve::Linear,
+            decay_curve: EnvelopeCurve::Linear,
+            release_curve: EnvelopeCurve::Linear,
+        }
+    }
+
+    /// Set the shape of each segment
+    pub fn with_curves(mut self, attack: EnvelopeCurve, decay: EnvelopeCurve, release: EnvelopeCurve) -> Self {
+        self.attack_curve = attack;
+        self.decay_curve = decay;
+        self.release_curve = release;
+        self
+    }
+
+    /// Linear attack with exponential decay and release, the natural shape of a struck drum
+    pub fn percussive(self) -> Self {
+        self.with_curves(EnvelopeCurve::Linear, EnvelopeCurve::Exponential, EnvelopeCurve::Exponential)
+    }
+
+    pub fn default() -> Self {
+        Self::new(0.01, 0.3, 0.7, 0.5)
+    }
+}
+
+pub struct Envelope {
+    pub attack_time: f32,   // seconds
+    pub decay_time: f32,    // seconds
+    pub sustain_level: f32, // 0.0 to 1.0
+    pub release_time: f32,  // seconds
+    pub attack_curve: EnvelopeCurve,
+    pub decay_curve: EnvelopeCurve,
+    pub release_curve: EnvelopeCurve,
+    pub current_time: f32,  // current time in the envelope
+    pub is_active: bool,
+    pub trigger_time: f64,  // when the envelope was triggered
+    pub release_time_start: Option<f64>, // when release was triggered
+    pub retrigger_mode: RetriggerMode,
+    /// Level the attack starts from; above zero after a legato retrigger
+    attack_start_level: f32,
+    /// Level the previous hit is fading out from, with `trigger_time` marking the end of the fade
+    fade_level: Option<f32>,
+    /// Most recent output, used when retriggering
+    last_level: f32,
+}
+
+impl Envelope {
+    pub fn new() -> Self {
+        let config = ADSRConfig::default();
+        Self::with_config(config)
+    }
+
+    pub fn with_config(config: ADSRConfig) -> Self {
+        Self {
+            attack_time: config.attack_time,
+            decay_time: config.decay_time,
+            sustain_level: config.sustain_level,
+            release_time: config.release_time,
+            attack_curve: config.attack_curve,
+            decay_curve: config.decay_curve,
+            release_curve: config.release_curve,
+            current_time: 0.0,
+            is_active: false,
+            trigger_time: 0.0,
+            release_time_start: None,
+            retrigger_mode: RetriggerMode::Hard,
+            attack_start_level: 0.0,
+            fade_level: None,
+            last_level: 0.0,
+        }
+    }
+
+    pub fn set_config(&mut self, config: ADSRConfig) {
+        self.attack_time = config.attack_time;
+        self.decay_time = config.decay_time;
+        self.sustain_level = config.sustain_level;
+        self.release_time = config.release_time;
+        self.attack_curve = config.attack_curve;
+        self.decay_curve = config.decay_curve;
+        self.release_curve = config.release_curve;
+    }
+
+    pub fn trigger(&mut self, time: f64) {
+        let level = if self.is_active { self.last_level } else { 0.0 };
+        self.attack_start_level = 0.0;
+        self.fade_level = None;
+        self.trigger_time = time;
+        match self.retrigger_mode {
+            RetriggerMode::Hard => {}
+            RetriggerMode::Legato => self.attack_start_level = level.min(1.0),
+            RetriggerMode::FadeOut => {
+                if level > 0.0 {
+                    self.fade_level = Some(level);
+                    self.trigger_time = time + RETRIGGER_FADE_TIME as f64;
+                }
+            }
+        }
+        self.is_active = true;
+        self.current_time = 0.0;
+        self.release_time_start = None;
+    }
+
+    pub fn release(&mut self, time: f64) {
+        if self.is_active && self.release_time_start.is_none() {
+            self.release_time_start = Some(time);
+        }
+    }
+
+    pub fn get_amplitude(&mut self, current_time: f64) -> f32 {
+        let level = self.compute_amplitude(current_time);
+        self.last_level = level;
+        level
+    }
+
+    fn compute_amplitude(&mut self, current_time: f64) -> f32 {
+        if !self.is_active {
+            return 0.0;
+        }
+
+        // Fade the previous hit out before the attack starts
+        if let Some(fade_level) = self.fade_level {
+            if current_time < self.trigger_time {
+                let remaining = (self.trigger_time - current_time) as f32 / RETRIGGER_FADE_TIME;
+                return fade_level * remaining.min(1.0);
+            }
+            self.fade_level = None;
+        }
+
+        // Subtract in f64 so long sessions keep sample accuracy; the elapsed time itself is short
+        let elapsed = (current_time - self.trigger_time) as f32;
+        self.current_time = elapsed;
+
+        // Check if we're in release phase
+        if let Some(release_start) = self.release_time_start {
+            let release_elapsed = (current_time - release_start) as f32;
+            if release_elapsed < self.release_time {
+                // Release from wherever the attack, decay or sustain had reached
+                let release_elapsed_from_trigger = (release_start - self.trigger_time) as f32;
+                let release_amplitude = self.level_before_release(release_elapsed_from_trigger);
+                
+                // Apply release envelope
+                let release_progress = release_elapsed / self.release_time;
+                self.release_curve.interpolate(release_amplitude, 0.0, release_progress)
+            } else {
+                // Release phase complete
+                self.is_active = false;
+                0.0
+            }
+        } else {
+            // Normal ADSR without release triggered
+            if elapsed >= self.attack_time + self.decay_time {
+                // Sustain phase (holds until release is triggered)
+                // For drums with 0.0 sustain, automatically trigger release
+                if self.sustain_level == 0.0 && self.release_time_start.is_none() {
+                    self.release_time_start = Some(current_time);
+                }
+            }
+            self.level_before_release(elapsed)
+        }
+    }
+
+    /// Attack, decay and sustain level `elapsed` seconds after the trigger
+    fn level_before_release(&self, elapsed: f32) -> f32 {
+        if elapsed < self.attack_time {
+            // Attack phase
+            self.attack_curve.interpolate(self.attack_start_level, 1.0, elapsed / self.attack_time)
+        } else if elapsed < self.attack_time + self.decay_time {
+            // Decay phase
+            let decay_progress = (elapsed - self.attack_time) / self.decay_time;
+            self.decay_curve.interpolate(1.0, self.sustain_level, decay_progress)
+        } else {
+            self.sustain_level
+        }
+    }
+}
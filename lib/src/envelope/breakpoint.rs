@@ -0,0 +1,260 @@
+//! Multi-segment envelopes with hold stages, sustain and loop points
+//! Each segment moves from wherever the envelope is to a target level over a duration with its
+//! own curve, so a kick's pitch can drop fast and then glide, or a filter sweep can repeat.
+
+use super::adsr::{ADSRConfig, EnvelopeCurve, RetriggerMode, RETRIGGER_FADE_TIME};
+
+/// Most segments a breakpoint envelope can hold; storage is fixed so envelopes never allocate
+pub const MAX_SEGMENTS: usize = 16;
+
+/// One stage of a breakpoint envelope
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvelopeSegment {
+    /// Seconds taken to reach `level`
+    pub duration: f32,
+    pub level: f32,
+    pub curve: EnvelopeCurve,
+}
+
+impl EnvelopeSegment {
+    pub fn new(duration: f32, level: f32, curve: EnvelopeCurve) -> Self {
+        Self {
+            duration: duration.max(0.0),
+            level,
+            curve,
+        }
+    }
+
+    pub fn linear(duration: f32, level: f32) -> Self {
+        Self::new(duration, level, EnvelopeCurve::Linear)
+    }
+
+    pub fn exponential(duration: f32, level: f32) -> Self {
+        Self::new(duration, level, EnvelopeCurve::Exponential)
+    }
+
+    /// Stay at `level` for `duration`; follow a segment that ends at the same level
+    pub fn hold(duration: f32, level: f32) -> Self {
+        Self::linear(duration, level)
+    }
+}
+
+/// An envelope made of arbitrary segments
+/// Without a sustain point it plays straight through once triggered. With one, it holds at the end
+/// of that segment until released, then continues with the segments after it. A loop repeats a
+/// range of segments until the envelope is released.
+#[derive(Debug, Clone, Copy)]
+pub struct BreakpointEnvelope {
+    segments: [EnvelopeSegment; MAX_SEGMENTS],
+    segment_count: usize,
+    /// Level before the first segment
+    pub start_level: f32,
+    sustain: Option<usize>,
+    loop_range: Option<(usize, usize)>,
+    pub retrigger_mode: RetriggerMode,
+
+    is_active: bool,
+    released: bool,
+    /// Fading from `segment_start_level` to `start_level` until `segment_start_time`
+    fading: bool,
+    segment: usize,
+    segment_start_time: f64,
+    segment_start_level: f32,
+    level: f32,
+}
+
+impl BreakpointEnvelope {
+    pub fn new(segments: &[EnvelopeSegment]) -> Self {
+        let mut envelope = Self {
+            segments: [EnvelopeSegment::linear(0.0, 0.0); MAX_SEGMENTS],
+            segment_count: 0,
+            start_level: 0.0,
+            sustain: None,
+            loop_range: None,
+            retrigger_mode: RetriggerMode::Hard,
+            is_active: false,
+            released: false,
+            fading: false,
+            segment: 0,
+            segment_start_time: 0.0,
+            segment_start_level: 0.0,
+            level: 0.0,
+        };
+        envelope.set_segments(segments);
+        envelope
+    }
+
+    /// Attack, hold, decay, sustain, release with exponential decay and release
+    /// A sustain level of 0.0 makes a one-shot that finishes without being released.
+    pub fn ahdsr(attack: f32, hold: f32, decay: f32, sustain: f32, release: f32) -> Self {
+        let sustain = sustain.clamp(0.0, 1.0);
+        let envelope = Self::new(&[
+            EnvelopeSegment::linear(attack.max(0.001), 1.0),
+            EnvelopeSegment::hold(hold, 1.0),
+            EnvelopeSegment::exponential(decay.max(0.001), sustain),
+            EnvelopeSegment::exponential(release.max(0.001), 0.0),
+        ]);
+        if sustain > 0.0 {
+            envelope.with_sustain(2)
+        } else {
+            envelope
+        }
+    }
+
+    /// The same shape as an ADSR envelope, including its curves
+    pub fn from_adsr(config: ADSRConfig) -> Self {
+        let envelope = Self::new(&[
+            EnvelopeSegment::new(config.attack_time, 1.0, config.attack_curve),
+            EnvelopeSegment::new(config.decay_time, config.sustain_level, config.decay_curve),
+            EnvelopeSegment::new(config.release_time, 0.0, config.release_curve),
+        ]);
+        if config.sustain_level > 0.0 {
+            envelope.with_sustain(1)
+        } else {
+            envelope
+        }
+    }
+
+    /// Hold at the end of segment `index` until released
+    pub fn with_sustain(mut self, index: usize) -> Self {
+        self.set_sustain(Some(index));
+        self
+    }
+
+    /// Repeat segments `start..=end` until released
+    pub fn with_loop(mut self, start: usize, end: usize) -> Self {
+        self.set_loop(Some((start, end)));
+        self
+    }
+
+    pub fn with_start_level(mut self, level: f32) -> Self {
+        self.start_level = level;
+        self.level = level;
+        self
+    }
+
+    /// Replace the segments; anything past `MAX_SEGMENTS` is dropped
+    /// A sustain or loop point past the new end is cleared.
+    pub fn set_segments(&mut self, segments: &[EnvelopeSegment]) {
+        self.segment_count = segments.len().min(MAX_SEGMENTS);
+        self.segments[..self.segment_count].copy_from_slice(&segments[..self.segment_count]);
+        self.set_sustain(self.sustain);
+        self.set_loop(self.loop_range);
+    }
+
+    pub fn segments(&self) -> &[EnvelopeSegment] {
+        &self.segments[..self.segment_count]
+    }
+
+    pub fn set_sustain(&mut self, index: Option<usize>) {
+        self.sustain = index.filter(|&index| index < self.segment_count);
+    }
+
+    pub fn set_loop(&mut self, range: Option<(usize, usize)>) {
+        self.loop_range = range.filter(|&(start, end)| start <= end && end < self.segment_count);
+    }
+
+    pub fn sustain(&self) -> Option<usize> {
+        self.sustain
+    }
+
+    pub fn loop_range(&self) -> Option<(usize, usize)> {
+        self.loop_range
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.is_active
+    }
+
+    /// Start again from the first segment
+    /// A legato retrigger moves on from the current level; a fade-out retrigger first ramps back
+    /// to the start level over `RETRIGGER_FADE_TIME`.
+    pub fn trigger(&mut self, time: f64) {
+        let was_active = self.is_active;
+        self.is_active = true;
+        self.released = false;
+        self.fading = false;
+        self.segment = 0;
+        self.segment_start_time = time;
+        self.segment_start_level = self.start_level;
+        match self.retrigger_mode {
+            RetriggerMode::Hard => self.level = self.start_level,
+            RetriggerMode::Legato if was_active => self.segment_start_level = self.level,
+            RetriggerMode::FadeOut if was_active && self.level != self.start_level => {
+                self.fading = true;
+                self.segment_start_time = time + RETRIGGER_FADE_TIME as f64;
+                self.segment_start_level = self.level;
+            }
+            _ => self.level = self.start_level,
+        }
+    }
+
+    /// Leave the sustain point or loop and play the remaining segments from the current level
+    pub fn release(&mut self, time: f64) {
+        if !self.is_active || self.released {
+            return;
+        }
+        self.get_amplitude(time);
+        self.released = true;
+        if let Some(sustain) = self.sustain {
+            if self.segment <= sustain {
+                self.fading = false;
+                self.segment = sustain + 1;
+                self.segment_start_time = time;
+                self.segment_start_level = self.level;
+            }
+        }
+    }
+
+    /// Level at `current_time`; once finished the envelope holds its final level
+    pub fn get_amplitude(&mut self, current_time: f64) -> f32 {
+        if !self.is_active {
+            return self.level;
+        }
+
+        if self.fading {
+            if current_time < self.segment_start_time {
+                let remaining = (self.segment_start_time - current_time) as f32 / RETRIGGER_FADE_TIME;
+                self.level = self.start_level + (self.segment_start_level - self.start_level) * remaining.min(1.0);
+                return self.level;
+            }
+            self.fading = false;
+            self.segment_start_level = self.start_level;
+        }
+
+        loop {
+            let Some(segment) = self.segments().get(self.segment).copied() else {
+                self.is_active = false;
+                return self.level;
+            };
+
+            let elapsed = (current_time - self.segment_start_time) as f32;
+            if elapsed < segment.duration {
+                self.level = segment
+                    .curve
+                    .interpolate(self.segment_start_level, segment.level, elapsed / segment.duration);
+                return self.level;
+            }
+
+            // This segment has finished
+            self.level = segment.level;
+            if !self.released && self.sustain == Some(self.segment) {
+                return self.level;
+            }
+
+            self.segment_start_time += segment.duration as f64;
+            self.segment_start_level = segment.level;
+            match self.loop_range {
+                Some((start, end)) if !self.released && self.segment == end => {
+                    // A loop that takes no time would never let the clock catch up
+                    let loop_duration: f32 = self.segments[start..=end].iter().map(|segment| segment.duration).sum();
+                    if loop_duration <= 0.0 {
+                        return self.level;
+                    }
+                    self.segment = start;
+                }
+                _ => self.segment += 1,
+            }
+        }
+    }
+}
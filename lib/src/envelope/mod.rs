@@ -0,0 +1,5 @@
+pub mod adsr;
+pub mod breakpoint;
+
+pub use self::adsr::*;
+pub use self::breakpoint::*;
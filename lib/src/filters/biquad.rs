@@ -0,0 +1,412 @@
+//! Second-order IIR filters from Robert Bristow-Johnson's Audio EQ Cookbook
+//! Coefficients are cached and only recalculated when a parameter moves. Parameter changes glide
+//! over a few milliseconds so sweeping the cutoff or gain doesn't produce zipper noise.
+
+use std::f32::consts::PI;
+
+use super::mono_filter::MonoFilter;
+
+/// Time parameter changes take to settle, in seconds
+const SMOOTHING_TIME: f32 = 0.005;
+
+/// Samples between coefficient updates while a parameter is gliding
+const UPDATE_INTERVAL: u32 = 16;
+
+/// Relative distance from the target at which a glide snaps to it
+const SETTLE_THRESHOLD: f32 = 1e-4;
+
+pub const MIN_Q: f32 = 0.05;
+pub const MAX_Q: f32 = 40.0;
+
+indexed_enum! {
+    /// Frequency response of a biquad
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum BiquadType {
+        #[default]
+        LowPass,
+        HighPass,
+        /// Constant 0 dB peak at the center frequency
+        BandPass,
+        Notch,
+        /// Bell boost or cut of `gain_db` around the center frequency
+        Peak,
+        /// Boost or cut of `gain_db` below the corner frequency
+        LowShelf,
+        /// Boost or cut of `gain_db` above the corner frequency
+        HighShelf,
+        /// Flat magnitude with a phase shift around the center frequency
+        AllPass,
+    }
+}
+
+impl BiquadType {
+    /// Whether `gain_db` changes the response
+    pub fn uses_gain(self) -> bool {
+        matches!(self, BiquadType::Peak | BiquadType::LowShelf | BiquadType::HighShelf)
+    }
+}
+
+/// Normalised biquad coefficients, with a0 divided out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiquadCoefficients {
+    pub b0: f32,
+    pub b1: f32,
+    pub b2: f32,
+    pub a1: f32,
+    pub a2: f32,
+}
+
+impl BiquadCoefficients {
+    /// Passes the input through unchanged
+    pub const IDENTITY: Self = Self {
+        b0: 1.0,
+        b1: 0.0,
+        b2: 0.0,
+        a1: 0.0,
+        a2: 0.0,
+    };
+
+    /// Cookbook coefficients for a response at `frequency` Hz
+    /// The frequency is kept below Nyquist and Q within `MIN_Q..=MAX_Q`.
+    pub fn new(filter_type: BiquadType, sample_rate: f32, frequency: f32, q: f32, gain_db: f32) -> Self {
+        let frequency = frequency.clamp(10.0, sample_rate * 0.49);
+        let q = q.clamp(MIN_Q, MAX_Q);
+        let omega = 2.0 * PI * frequency / sample_rate;
+        let (sin_omega, cos_omega) = omega.sin_cos();
+        let alpha = sin_omega / (2.0 * q);
+        // Square root of the linear gain, as the cookbook's A
+        let amplitude = 10.0f32.powf(gain_db / 40.0);
+
+        let (b0, b1, b2, a0, a1, a2) = match filter_type {
+            BiquadType::LowPass => {
+                let b1 = 1.0 - cos_omega;
+                (b1 / 2.0, b1, b1 / 2.0, 1.0 + alpha, -2.0 * cos_omega, 1.0 - alpha)
+            }
+            BiquadType::HighPass => {
+                let b1 = -(1.0 + cos_omega);
+                (-b1 / 2.0, b1, -b1 / 2.0, 1.0 + alpha, -2.0 * cos_omega, 1.0 - alpha)
+            }
+            BiquadType::BandPass => (alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos_omega, 1.0 - alpha),
+            BiquadType::Notch => (1.0, -2.0 * cos_omega, 1.0, 1.0 + alpha, -2.0 * cos_omega, 1.0 - alpha),
+            BiquadType::Peak => (
+                1.0 + alpha * amplitude,
+                -2.0 * cos_omega,
+                1.0 - alpha * amplitude,
+                1.0 + alpha / amplitude,
+                -2.0 * cos_omega,
+                1.0 - alpha / amplitude,
+            ),
+            BiquadType::LowShelf => {
+                let shelf = 2.0 * amplitude.sqrt() * alpha;
+                (
+                    amplitude * ((amplitude + 1.0) - (amplitude - 1.0) * cos_omega + shelf),
+                    2.0 * amplitude * ((amplitude - 1.0) - (amplitude + 1.0) * cos_omega),
+                    amplitude * ((amplitude + 1.0) - (amplitude - 1.0) * cos_omega - shelf),
+                    (amplitude + 1.0) + (amplitude - 1.0) * cos_omega + shelf,
+                    -2.0 * ((amplitude - 1.0) + (amplitude + 1.0) * cos_omega),
+                    (amplitude + 1.0) + (amplitude - 1.0) * cos_omega - shelf,
+                )
+            }
+            BiquadType::HighShelf => {
+                let shelf = 2.0 * amplitude.sqrt() * alpha;
+                (
+                    amplitude * ((amplitude + 1.0) + (amplitude - 1.0) * cos_omega + shelf),
+                    -2.0 * amplitude * ((amplitude - 1.0) + (amplitude + 1.0) * cos_omega),
+                    amplitude * ((amplitude + 1.0) + (amplitude - 1.0) * cos_omega - shelf),
+                    (amplitude + 1.0) - (amplitude - 1.0) * cos_omega + shelf,
+                    2.0 * ((amplitude - 1.0) - (amplitude + 1.0) * cos_omega),
+                    (amplitude + 1.0) - (amplitude - 1.0) * cos_omega - shelf,
+                )
+            }
+            BiquadType::AllPass => (
+                1.0 - alpha,
+                -2.0 * cos_omega,
+                1.0 + alpha,
+                1.0 + alpha,
+                -2.0 * cos_omega,
+                1.0 - alpha,
+            ),
+        };
+
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+
+    /// Gain of these coefficients at `frequency` Hz, e.g. for drawing a response curve
+    pub fn magnitude(&self, frequency: f32, sample_rate: f32) -> f32 {
+        let omega = 2.0 * PI * frequency / sample_rate;
+        let (sin1, cos1) = omega.sin_cos();
+        let (sin2, cos2) = (2.0 * omega).sin_cos();
+        let numerator_re = self.b0 + self.b1 * cos1 + self.b2 * cos2;
+        let numerator_im = -(self.b1 * sin1 + self.b2 * sin2);
+        let denominator_re = 1.0 + self.a1 * cos1 + self.a2 * cos2;
+        let denominator_im = -(self.a1 * sin1 + self.a2 * sin2);
+        numerator_re.hypot(numerator_im) / denominator_re.hypot(denominator_im).max(f32::MIN_POSITIVE)
+    }
+}
+
+/// A biquad filter with smoothed parameters
+/// Runs in transposed direct form II, which behaves well when coefficients change mid-stream.
+#[derive(Debug, Clone, Copy)]
+pub struct Biquad {
+    pub sample_rate: f32,
+    filter_type: BiquadType,
+    /// Targets the smoothed values glide toward
+    frequency: f32,
+    q: f32,
+    gain_db: f32,
+    /// Values the current coefficients were calculated from
+    current_frequency: f32,
+    current_q: f32,
+    current_gain_db: f32,
+    /// Fraction of the remaining distance covered at each coefficient update
+    smoothing: f32,
+    /// Samples left until the next coefficient update during a glide
+    countdown: u32,
+    coefficients: BiquadCoefficients,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    pub fn new(sample_rate: f32, filter_type: BiquadType, frequency: f32, q: f32) -> Self {
+        let mut filter = Self {
+            sample_rate,
+            filter_type,
+            frequency,
+            q,
+            gain_db: 0.0,
+            current_frequency: frequency,
+            current_q: q,
+            current_gain_db: 0.0,
+            smoothing: 1.0 - (-(UPDATE_INTERVAL as f32) / (SMOOTHING_TIME * sample_rate)).exp(),
+            countdown: 0,
+            coefficients: BiquadCoefficients::IDENTITY,
+            z1: 0.0,
+            z2: 0.0,
+        };
+        filter.snap();
+        filter
+    }
+
+    pub fn with_gain_db(mut self, gain_db: f32) -> Self {
+        self.gain_db = gain_db;
+        self.snap();
+        self
+    }
+
+    pub fn filter_type(&self) -> BiquadType {
+        self.filter_type
+    }
+
+    pub fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    pub fn q(&self) -> f32 {
+        self.q
+    }
+
+    pub fn gain_db(&self) -> f32 {
+        self.gain_db
+    }
+
+    pub fn coefficients(&self) -> BiquadCoefficients {
+        self.coefficients
+    }
+
+    /// Change the response; takes effect immediately since responses can't be blended
+    pub fn set_type(&mut self, filter_type: BiquadType) {
+        if filter_type != self.filter_type {
+            self.filter_type = filter_type;
+            self.update_coefficients();
+        }
+    }
+
+    /// Glide to a new cutoff or center frequency in Hz
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.frequency = frequency.clamp(10.0, self.sample_rate * 0.49);
+    }
+
+    /// Glide to a new Q; higher values give a sharper resonance or a narrower band
+    pub fn set_q(&mut self, q: f32) {
+        self.q = q.clamp(MIN_Q, MAX_Q);
+    }
+
+    /// Glide to a new gain for the peak and shelf responses
+    pub fn set_gain_db(&mut self, gain_db: f32) {
+        self.gain_db = gain_db;
+    }
+
+    /// Jump straight to the target parameters, skipping the glide
+    pub fn snap(&mut self) {
+        self.frequency = self.frequency.clamp(10.0, self.sample_rate * 0.49);
+        self.q = self.q.clamp(MIN_Q, MAX_Q);
+        self.current_frequency = self.frequency;
+        self.current_q = self.q;
+        self.current_gain_db = self.gain_db;
+        self.update_coefficients();
+    }
+
+    /// Clear the filter's memory and settle its parameters, e.g. before a new transient
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+        self.snap();
+    }
+
+    fn is_settled(&self) -> bool {
+        self.current_frequency == self.frequency && self.current_q == self.q && self.current_gain_db == self.gain_db
+    }
+
+    fn update_coefficients(&mut self) {
+        self.coefficients = BiquadCoefficients::new(
+            self.filter_type,
+            self.sample_rate,
+            self.current_frequency,
+            self.current_q,
+            self.current_gain_db,
+        );
+    }
+
+    /// Move the smoothed parameters one update interval toward their targets
+    fn glide(&mut self) {
+        // Frequency and Q glide in ratio so sweeps sound even across the spectrum
+        let step = |current: f32, target: f32| {
+            let next = current * (target / current).powf(self.smoothing);
+            if ((next - target) / target).abs() < SETTLE_THRESHOLD {
+                target
+            } else {
+                next
+            }
+        };
+        self.current_frequency = step(self.current_frequency, self.frequency);
+        self.current_q = step(self.current_q, self.q);
+
+        let gain_gap = self.gain_db - self.current_gain_db;
+        self.current_gain_db = if gain_gap.abs() < 0.01 {
+            self.gain_db
+        } else {
+            self.current_gain_db + gain_gap * self.smoothing
+        };
+        self.update_coefficients();
+    }
+
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        if self.countdown == 0 {
+            if !self.is_settled() {
+                self.glide();
+            }
+            self.countdown = UPDATE_INTERVAL;
+        }
+        self.countdown -= 1;
+
+        let BiquadCoefficients { b0, b1, b2, a1, a2 } = self.coefficients;
+        let output = b0 * input + self.z1;
+        self.z1 = b1 * input - a1 * output + self.z2;
+        self.z2 = b2 * input - a2 * output;
+        output
+    }
+
+    /// Gain of the current coefficients at `frequency` Hz
+    pub fn magnitude(&self, frequency: f32) -> f32 {
+        self.coefficients.magnitude(frequency, self.sample_rate)
+    }
+}
+
+impl MonoFilter for Biquad {
+    fn process(&mut self, input: f32) -> f32 {
+        Biquad::process(self, input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    fn magnitude_db(coefficients: &BiquadCoefficients, frequency: f32) -> f32 {
+        20.0 * coefficients.magnitude(frequency, SAMPLE_RATE).log10()
+    }
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!((actual - expected).abs() <= tolerance, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn pass_filters_have_unity_passband_and_q_at_cutoff() {
+        let q = 2.0;
+        let low = BiquadCoefficients::new(BiquadType::LowPass, SAMPLE_RATE, 1000.0, q, 0.0);
+        assert_close(low.magnitude(0.0, SAMPLE_RATE), 1.0, 1e-4);
+        assert_close(low.magnitude(1000.0, SAMPLE_RATE), q, 1e-3);
+        assert!(low.magnitude(SAMPLE_RATE * 0.5, SAMPLE_RATE) < 1e-4);
+
+        let high = BiquadCoefficients::new(BiquadType::HighPass, SAMPLE_RATE, 1000.0, q, 0.0);
+        assert!(high.magnitude(0.0, SAMPLE_RATE) < 1e-4);
+        assert_close(high.magnitude(1000.0, SAMPLE_RATE), q, 1e-3);
+        assert_close(high.magnitude(SAMPLE_RATE * 0.5, SAMPLE_RATE), 1.0, 1e-4);
+    }
+
+    #[test]
+    fn band_pass_peaks_at_unity_and_notch_nulls_at_center() {
+        let band = BiquadCoefficients::new(BiquadType::BandPass, SAMPLE_RATE, 2000.0, 4.0, 0.0);
+        assert_close(band.magnitude(2000.0, SAMPLE_RATE), 1.0, 1e-3);
+        assert!(band.magnitude(0.0, SAMPLE_RATE) < 1e-4);
+
+        let notch = BiquadCoefficients::new(BiquadType::Notch, SAMPLE_RATE, 2000.0, 4.0, 0.0);
+        assert!(notch.magnitude(2000.0, SAMPLE_RATE) < 1e-3);
+        assert_close(notch.magnitude(0.0, SAMPLE_RATE), 1.0, 1e-4);
+    }
+
+    #[test]
+    fn peak_and_shelves_reach_their_gain() {
+        let gain_db = 9.0;
+        let peak = BiquadCoefficients::new(BiquadType::Peak, SAMPLE_RATE, 3000.0, 1.0, gain_db);
+        assert_close(magnitude_db(&peak, 3000.0), gain_db, 0.01);
+        assert_close(magnitude_db(&peak, 0.0), 0.0, 0.01);
+
+        // Shelves sit at half their gain at the corner frequency
+        let low = BiquadCoefficients::new(BiquadType::LowShelf, SAMPLE_RATE, 500.0, 0.707, gain_db);
+        assert_close(magnitude_db(&low, 0.0), gain_db, 0.01);
+        assert_close(magnitude_db(&low, 500.0), gain_db * 0.5, 0.01);
+        assert_close(magnitude_db(&low, SAMPLE_RATE * 0.5), 0.0, 0.01);
+
+        let high = BiquadCoefficients::new(BiquadType::HighShelf, SAMPLE_RATE, 5000.0, 0.707, -gain_db);
+        assert_close(magnitude_db(&high, 0.0), 0.0, 0.01);
+        assert_close(magnitude_db(&high, 5000.0), -gain_db * 0.5, 0.01);
+        assert_close(magnitude_db(&high, SAMPLE_RATE * 0.5), -gain_db, 0.01);
+    }
+
+    #[test]
+    fn all_pass_is_flat() {
+        let all = BiquadCoefficients::new(BiquadType::AllPass, SAMPLE_RATE, 1000.0, 2.0, 0.0);
+        for frequency in [0.0, 100.0, 1000.0, 10000.0, SAMPLE_RATE * 0.5] {
+            assert_close(all.magnitude(frequency, SAMPLE_RATE), 1.0, 1e-4);
+        }
+    }
+
+    #[test]
+    fn magnitude_matches_filtered_sine() {
+        let frequency = 1500.0;
+        let mut filter = Biquad::new(SAMPLE_RATE, BiquadType::Peak, 1000.0, 1.5).with_gain_db(6.0);
+        let expected = filter.coefficients().magnitude(frequency, SAMPLE_RATE);
+
+        // Let the filter settle, then measure the output peak over a few cycles
+        let mut peak: f32 = 0.0;
+        for n in 0..(SAMPLE_RATE as usize / 4) {
+            let input = (2.0 * PI * frequency * n as f32 / SAMPLE_RATE).sin();
+            let output = filter.process(input);
+            if n > SAMPLE_RATE as usize / 8 {
+                peak = peak.max(output.abs());
+            }
+        }
+        assert_close(peak, expected, 0.01);
+    }
+}
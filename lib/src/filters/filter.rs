@@ -0,0 +1,111 @@
+//! One resonant filter an instrument can embed, switchable between the SVF and ladder responses
+//! Cutoff and resonance mean the same thing for every type, so a config can change type without
+//! retuning. It is Copy and never allocates, so it can live inside any voice.
+
+use super::ladder::{LadderFilter, LadderMode};
+use super::mono_filter::MonoFilter;
+use super::svf::{StateVariableFilter, SvfMode};
+
+indexed_enum! {
+    /// Response of an instrument filter
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum FilterType {
+        /// Passes the signal through untouched
+        #[default]
+        Off,
+        LowPass,
+        BandPass,
+        HighPass,
+        Notch,
+        /// 24 dB/octave ladder low-pass; self-oscillates at full resonance
+        Ladder,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Filter {
+    filter_type: FilterType,
+    cutoff: f32,
+    resonance: f32,
+    svf: StateVariableFilter,
+    ladder: LadderFilter,
+}
+
+impl Filter {
+    pub fn new(sample_rate: f32, filter_type: FilterType, cutoff: f32, resonance: f32) -> Self {
+        let mut filter = Self {
+            filter_type,
+            cutoff,
+            resonance,
+            svf: StateVariableFilter::new(sample_rate, SvfMode::LowPass, cutoff, resonance),
+            ladder: LadderFilter::new(sample_rate, LadderMode::LowPass24, cutoff, resonance),
+        };
+        filter.set_type(filter_type);
+        filter
+    }
+
+    pub fn filter_type(&self) -> FilterType {
+        self.filter_type
+    }
+
+    pub fn set_type(&mut self, filter_type: FilterType) {
+        self.filter_type = filter_type;
+        match filter_type {
+            FilterType::LowPass => self.svf.mode = SvfMode::LowPass,
+            FilterType::BandPass => self.svf.mode = SvfMode::BandPass,
+            FilterType::HighPass => self.svf.mode = SvfMode::HighPass,
+            FilterType::Notch => self.svf.mode = SvfMode::Notch,
+            FilterType::Off | FilterType::Ladder => {}
+        }
+        // Only the active filter follows parameter changes, so bring it up to date
+        self.set_parameters(self.cutoff, self.resonance);
+    }
+
+    pub fn cutoff(&self) -> f32 {
+        self.cutoff
+    }
+
+    pub fn resonance(&self) -> f32 {
+        self.resonance
+    }
+
+    /// Set cutoff in Hz and resonance from 0.0 to 1.0; cheap when nothing changed
+    pub fn set_parameters(&mut self, cutoff: f32, resonance: f32) {
+        self.cutoff = cutoff;
+        self.resonance = resonance;
+        match self.filter_type {
+            FilterType::Off => {}
+            FilterType::Ladder => self.ladder.set_parameters(cutoff, resonance),
+            _ => self.svf.set_parameters(cutoff, resonance),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.svf.reset();
+        self.ladder.reset();
+    }
+
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        match self.filter_type {
+            FilterType::Off => input,
+            FilterType::Ladder => self.ladder.process(input),
+            _ => self.svf.process(input),
+        }
+    }
+}
+
+impl MonoFilter for Filter {
+    fn process(&mut self, input: f32) -> f32 {
+        Filter::process(self, input)
+    }
+
+    /// Picks the filter once per block; Off leaves the block untouched
+    fn process_block(&mut self, buffer: &mut [f32]) {
+        match self.filter_type {
+            FilterType::Off => {}
+            FilterType::Ladder => self.ladder.process_block(buffer),
+            _ => self.svf.process_block(buffer),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Four-pole transistor ladder filter in zero-delay-feedback form
+//! Four trapezoidal one-pole stages share a feedback path solved without a unit delay, so the
+//! resonance tracks the cutoff. A tanh in the feedback loop keeps high resonance bounded: at full
+//! resonance the filter self-oscillates at the cutoff as a sine.
+
+use std::f32::consts::PI;
+
+use super::mono_filter::MonoFilter;
+
+/// Feedback gain at full resonance; the loop self-oscillates from 4.0
+const MAX_FEEDBACK: f32 = 4.2;
+
+indexed_enum! {
+    /// Response taken from the ladder's stages
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum LadderMode {
+        /// The classic 24 dB/octave low-pass
+        #[default]
+        LowPass24,
+        LowPass12,
+        BandPass12,
+        HighPass24,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LadderFilter {
+    pub sample_rate: f32,
+    pub mode: LadderMode,
+    /// Gain into the saturating feedback stage; 1.0 is clean at moderate levels
+    pub drive: f32,
+    cutoff: f32,
+    /// 0.0 to 1.0; self-oscillates near the top of the range
+    /// As on the hardware, more resonance thins out the low-pass passband.
+    resonance: f32,
+    /// Cached one-pole gain G = g / (1 + g) and feedback gain
+    gain: f32,
+    feedback: f32,
+    stages: [f32; 4],
+}
+
+impl LadderFilter {
+    pub fn new(sample_rate: f32, mode: LadderMode, cutoff: f32, resonance: f32) -> Self {
+        let mut filter = Self {
+            sample_rate,
+            mode,
+            drive: 1.0,
+            cutoff: 0.0,
+            resonance: 0.0,
+            gain: 0.0,
+            feedback: 0.0,
+            stages: [0.0; 4],
+        };
+        filter.set_parameters(cutoff, resonance);
+        filter
+    }
+
+    pub fn cutoff(&self) -> f32 {
+        self.cutoff
+    }
+
+    pub fn resonance(&self) -> f32 {
+        self.resonance
+    }
+
+    pub fn set_cutoff(&mut self, cutoff: f32) {
+        self.set_parameters(cutoff, self.resonance);
+    }
+
+    pub fn set_resonance(&mut self, resonance: f32) {
+        self.set_parameters(self.cutoff, resonance);
+    }
+
+    /// Set cutoff in Hz and resonance from 0.0 to 1.0 together
+    pub fn set_parameters(&mut self, cutoff: f32, resonance: f32) {
+        let cutoff = cutoff.clamp(10.0, self.sample_rate * 0.45);
+        let resonance = resonance.clamp(0.0, 1.0);
+        if cutoff == self.cutoff && resonance == self.resonance {
+            return;
+        }
+        self.cutoff = cutoff;
+        self.resonance = resonance;
+
+        let g = (PI * cutoff / self.sample_rate).tan();
+        self.gain = g / (1.0 + g);
+        self.feedback = resonance * MAX_FEEDBACK;
+    }
+
+    pub fn reset(&mut self) {
+        self.stages = [0.0; 4];
+    }
+
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        let gain = self.gain;
+        let hold = 1.0 - gain;
+
+        // The last stage's output is gain^4 * u plus this contribution from the stored states
+        let [s1, s2, s3, s4] = self.stages;
+        let state_sum = gain * gain * gain * hold * s1 + gain * gain * hold * s2 + gain * hold * s3 + hold * s4;
+        let gain4 = gain * gain * gain * gain;
+        let linear_input = (input - self.feedback * state_sum) / (1.0 + self.feedback * gain4);
+        // Saturate the ladder input, which bounds self-oscillation
+        let drive = self.drive.max(0.1);
+        let u = (linear_input * drive).tanh() / drive;
+
+        let mut outputs = [0.0; 4];
+        let mut stage_input = u;
+        for (state, output) in self.stages.iter_mut().zip(outputs.iter_mut()) {
+            let v = (stage_input - *state) * gain;
+            *output = v + *state;
+            *state = *output + v;
+            stage_input = *output;
+        }
+
+        let [y1, y2, y3, y4] = outputs;
+        match self.mode {
+            LadderMode::LowPass24 => y4,
+            LadderMode::LowPass12 => y2,
+            LadderMode::BandPass12 => 2.0 * y1 - 2.0 * y2,
+            LadderMode::HighPass24 => u - 4.0 * y1 + 6.0 * y2 - 4.0 * y3 + y4,
+        }
+    }
+}
+
+impl MonoFilter for LadderFilter {
+    fn process(&mut self, input: f32) -> f32 {
+        LadderFilter::process(self, input)
+    }
+}
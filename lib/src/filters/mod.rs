@@ -1,3 +1,13 @@
+pub mod biquad;
+pub mod filter;
+pub mod ladder;
+pub mod mono_filter;
 pub mod resonant_highpass;
+pub mod svf;
 
-pub use self::resonant_highpass::ResonantHighpassFilter;
\ No newline at end of file
+pub use self::biquad::{Biquad, BiquadCoefficients, BiquadType};
+pub use self::filter::{Filter, FilterType};
+pub use self::ladder::{LadderFilter, LadderMode};
+pub use self::mono_filter::MonoFilter;
+pub use self::resonant_highpass::ResonantHighpassFilter;
+pub use self::svf::{StateVariableFilter, SvfMode, SvfOutputs};
\ No newline at end of file
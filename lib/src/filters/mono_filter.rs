@@ -0,0 +1,11 @@
+/// A filter that runs one sample at a time, with block processing built on top
+pub trait MonoFilter {
+    fn process(&mut self, input: f32) -> f32;
+
+    /// Filter a block of samples in place
+    fn process_block(&mut self, buffer: &mut [f32]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+}
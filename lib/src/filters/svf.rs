@@ -0,0 +1,144 @@
+//! Zero-delay-feedback state-variable filter, after Andrew Simper's trapezoidal SVF
+//! One update produces low-pass, band-pass and high-pass outputs together. The structure stays
+//! stable and free of zipper noise when the cutoff moves every sample, e.g. under an envelope.
+
+use std::f32::consts::PI;
+
+use super::mono_filter::MonoFilter;
+
+indexed_enum! {
+    /// Which output of the state-variable filter to use
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum SvfMode {
+        #[default]
+        LowPass,
+        BandPass,
+        HighPass,
+        /// Everything except a band around the cutoff
+        Notch,
+    }
+}
+
+/// All outputs of one filter update
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SvfOutputs {
+    pub low: f32,
+    pub band: f32,
+    pub high: f32,
+}
+
+impl SvfOutputs {
+    pub fn get(&self, mode: SvfMode) -> f32 {
+        match mode {
+            SvfMode::LowPass => self.low,
+            SvfMode::BandPass => self.band,
+            SvfMode::HighPass => self.high,
+            SvfMode::Notch => self.low + self.high,
+        }
+    }
+}
+
+/// Two-pole (12 dB/octave) state-variable filter
+#[derive(Debug, Clone, Copy)]
+pub struct StateVariableFilter {
+    pub sample_rate: f32,
+    pub mode: SvfMode,
+    cutoff: f32,
+    /// 0.0 is a gentle slope, 1.0 rings strongly at the cutoff
+    resonance: f32,
+    /// Cached coefficients, recalculated only when cutoff or resonance change
+    g: f32,
+    k: f32,
+    a1: f32,
+    a2: f32,
+    a3: f32,
+    ic1: f32,
+    ic2: f32,
+}
+
+impl StateVariableFilter {
+    pub fn new(sample_rate: f32, mode: SvfMode, cutoff: f32, resonance: f32) -> Self {
+        let mut filter = Self {
+            sample_rate,
+            mode,
+            cutoff: 0.0,
+            resonance: 0.0,
+            g: 0.0,
+            k: 2.0,
+            a1: 0.0,
+            a2: 0.0,
+            a3: 0.0,
+            ic1: 0.0,
+            ic2: 0.0,
+        };
+        filter.set_parameters(cutoff, resonance);
+        filter
+    }
+
+    pub fn cutoff(&self) -> f32 {
+        self.cutoff
+    }
+
+    pub fn resonance(&self) -> f32 {
+        self.resonance
+    }
+
+    pub fn set_cutoff(&mut self, cutoff: f32) {
+        self.set_parameters(cutoff, self.resonance);
+    }
+
+    pub fn set_resonance(&mut self, resonance: f32) {
+        self.set_parameters(self.cutoff, resonance);
+    }
+
+    /// Set cutoff in Hz and resonance from 0.0 to 1.0 together
+    pub fn set_parameters(&mut self, cutoff: f32, resonance: f32) {
+        let cutoff = cutoff.clamp(10.0, self.sample_rate * 0.49);
+        let resonance = resonance.clamp(0.0, 1.0);
+        if cutoff == self.cutoff && resonance == self.resonance {
+            return;
+        }
+        self.cutoff = cutoff;
+        self.resonance = resonance;
+
+        // Damping runs from a Q of 0.5 down to a Q of about 25
+        self.g = (PI * cutoff / self.sample_rate).tan();
+        self.k = 2.0 - 1.96 * resonance;
+        self.a1 = 1.0 / (1.0 + self.g * (self.g + self.k));
+        self.a2 = self.g * self.a1;
+        self.a3 = self.g * self.a2;
+    }
+
+    pub fn reset(&mut self) {
+        self.ic1 = 0.0;
+        self.ic2 = 0.0;
+    }
+
+    /// Run one sample and return every output
+    #[inline]
+    pub fn process_all(&mut self, input: f32) -> SvfOutputs {
+        let v3 = input - self.ic2;
+        let v1 = self.a1 * self.ic1 + self.a2 * v3;
+        let v2 = self.ic2 + self.a2 * self.ic1 + self.a3 * v3;
+        self.ic1 = 2.0 * v1 - self.ic1;
+        self.ic2 = 2.0 * v2 - self.ic2;
+        SvfOutputs {
+            low: v2,
+            band: v1,
+            high: input - self.k * v1 - v2,
+        }
+    }
+
+    /// Run one sample and return the output selected by `mode`
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        let mode = self.mode;
+        self.process_all(input).get(mode)
+    }
+}
+
+impl MonoFilter for StateVariableFilter {
+    fn process(&mut self, input: f32) -> f32 {
+        StateVariableFilter::process(self, input)
+    }
+}
@@ -0,0 +1,181 @@
+//! Low-frequency oscillators for modulating parameters over time
+//! An LFO runs at control rate: the Stage advances it once per control block rather than per
+//! sample. Its rate is either free in Hz or a number of beats, following the sequencer's BPM.
+
+use super::noise::{next_default_seed, Rng};
+use std::f64::consts::TAU;
+
+indexed_enum! {
+    /// Waveform of an LFO; every shape moves between -1.0 and 1.0
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum LfoShape {
+        Sine,
+        Triangle,
+        /// Rising ramp
+        Saw,
+        Square,
+        /// A new random level at the start of every cycle
+        SampleAndHold,
+        /// Glides between random levels, one per cycle
+        SmoothRandom,
+    }
+}
+
+/// How fast an LFO cycles
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LfoRate {
+    /// Cycles per second, independent of tempo
+    Hertz(f32),
+    /// Length of one cycle in beats (quarter notes), e.g. 4.0 for one bar, 0.25 for one step
+    Beats(f32),
+}
+
+impl LfoRate {
+    /// Cycle frequency in Hz at the given tempo
+    pub fn frequency_hz(self, bpm: f32) -> f32 {
+        match self {
+            LfoRate::Hertz(frequency) => frequency.max(0.0),
+            LfoRate::Beats(beats) => bpm / 60.0 / beats.max(1.0 / 64.0),
+        }
+    }
+
+    pub fn is_synced(self) -> bool {
+        matches!(self, LfoRate::Beats(_))
+    }
+}
+
+/// Whether an LFO restarts its cycle when the instrument it modulates is triggered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LfoMode {
+    /// Keeps cycling regardless of triggers, so successive hits land on different values
+    #[default]
+    FreeRunning,
+    /// Restarts from phase 0 on every trigger, so each hit gets the same movement
+    Retrigger,
+}
+
+/// A control-rate modulation source
+#[derive(Debug, Clone, Copy)]
+pub struct Lfo {
+    pub shape: LfoShape,
+    pub rate: LfoRate,
+    pub mode: LfoMode,
+    /// Position in the current cycle, in [0, 1)
+    phase: f64,
+    rng: Rng,
+    /// Random level for the current cycle
+    held: f32,
+    /// Random level the smooth random shape started the cycle from
+    previous: f32,
+}
+
+impl Lfo {
+    pub fn new(shape: LfoShape, rate: LfoRate) -> Self {
+        let mut lfo = Self {
+            shape,
+            rate,
+            mode: LfoMode::FreeRunning,
+            phase: 0.0,
+            rng: Rng::new(0),
+            held: 0.0,
+            previous: 0.0,
+        };
+        // Unseeded LFOs still get their own streams, so two random LFOs don't move in lockstep
+        lfo.set_seed(next_default_seed());
+        lfo
+    }
+
+    pub fn with_mode(mut self, mode: LfoMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Restart the random shapes from a seed
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+        self.previous = self.rng.next_bipolar();
+        self.held = self.rng.next_bipolar();
+    }
+
+    /// Return to the start of a cycle; the random shapes move on to a new level
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+        self.next_random();
+    }
+
+    pub fn phase(&self) -> f64 {
+        self.phase
+    }
+
+    /// Current output, from -1.0 to 1.0
+    pub fn value(&self) -> f32 {
+        let phase = self.phase;
+        match self.shape {
+            LfoShape::Sine => (phase * TAU).sin() as f32,
+            // Starts at zero and rises, like the sine
+            LfoShape::Triangle => {
+                let t = (phase + 0.25) % 1.0;
+                (1.0 - 4.0 * (t - 0.5).abs()) as f32
+            }
+            LfoShape::Saw => (2.0 * phase - 1.0) as f32,
+            LfoShape::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            LfoShape::SampleAndHold => self.held,
+            LfoShape::SmoothRandom => {
+                // Cosine interpolation has no corners at the cycle boundaries
+                let blend = (0.5 - 0.5 * (phase * std::f64::consts::PI).cos()) as f32;
+                self.previous + (self.held - self.previous) * blend
+            }
+        }
+    }
+
+    /// Move forward by `seconds` at the given tempo
+    pub fn advance(&mut self, seconds: f64, bpm: f32) {
+        self.phase += seconds * self.rate.frequency_hz(bpm) as f64;
+        if self.phase >= 1.0 {
+            // Skip whole cycles at once if the step was long; the random level changes once
+            self.phase %= 1.0;
+            self.next_random();
+        }
+    }
+
+    fn next_random(&mut self) {
+        self.previous = self.held;
+        self.held = self.rng.next_bipolar();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_levels(lfo: &mut Lfo) -> Vec<f32> {
+        (0..8)
+            .map(|_| {
+                lfo.advance(1.0, 120.0);
+                lfo.value()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unseeded_random_lfos_differ() {
+        let mut first = Lfo::new(LfoShape::SampleAndHold, LfoRate::Hertz(1.0));
+        let mut second = Lfo::new(LfoShape::SampleAndHold, LfoRate::Hertz(1.0));
+        assert_ne!(random_levels(&mut first), random_levels(&mut second));
+    }
+
+    #[test]
+    fn same_seed_gives_same_levels() {
+        let mut first = Lfo::new(LfoShape::SmoothRandom, LfoRate::Hertz(1.0));
+        let mut second = Lfo::new(LfoShape::SmoothRandom, LfoRate::Hertz(1.0));
+        first.set_seed(42);
+        second.set_seed(42);
+        assert_eq!(random_levels(&mut first), random_levels(&mut second));
+    }
+}
@@ -1,5 +1,13 @@
+pub mod lfo;
+pub mod noise;
 pub mod oscillator;
+pub mod polyblep;
 pub mod waveform;
+pub mod wavetable;
 
+pub use self::lfo::*;
+pub use self::noise::*;
 pub use self::oscillator::*;
-pub use self::waveform::*;
\ No newline at end of file
+pub use self::polyblep::*;
+pub use self::waveform::*;
+pub use self::wavetable::*;
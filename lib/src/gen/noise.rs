@@ -0,0 +1,127 @@
+//! Seedable noise sources
+//! A small xorshift PRNG drives white noise, which is filtered into pink and brown noise.
+//! The same seed always produces the same stream, on every platform and Rust version.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A different seed on every call, for sources nobody has seeded explicitly
+/// Seeds are handed out in order, so a program that builds its sounds in the same order gets
+/// the same streams on every run.
+pub fn next_default_seed() -> u64 {
+    static NEXT_SEED: AtomicU64 = AtomicU64::new(0);
+    NEXT_SEED.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Fast xorshift64* pseudo-random number generator
+#[derive(Debug, Clone, Copy)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Scramble the seed (splitmix64) so nearby seeds give unrelated streams
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        // xorshift gets stuck at zero, so never start there
+        Self {
+            state: if z == 0 { 0x2545_F491_4F6C_DD1D } else { z },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform value in [0.0, 1.0)
+    pub fn next_f32(&mut self) -> f32 {
+        // The top 24 bits fill an f32 mantissa exactly
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniform value in [-1.0, 1.0)
+    pub fn next_bipolar(&mut self) -> f32 {
+        self.next_f32() * 2.0 - 1.0
+    }
+}
+
+/// Spectral color of a noise generator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseColor {
+    /// Equal energy per frequency
+    White,
+    /// Equal energy per octave (-3 dB/octave), softer and fuller than white
+    Pink,
+    /// Integrated white noise (-6 dB/octave), a deep rumble
+    Brown,
+}
+
+/// Noise source producing roughly full-scale samples of the chosen color
+#[derive(Debug, Clone)]
+pub struct NoiseGenerator {
+    pub color: NoiseColor,
+    rng: Rng,
+    /// Filter states for the pink noise approximation
+    pink: [f32; 7],
+    /// Leaky integrator state for brown noise
+    brown: f32,
+}
+
+impl NoiseGenerator {
+    pub fn new(color: NoiseColor, seed: u64) -> Self {
+        Self {
+            color,
+            rng: Rng::new(seed),
+            pink: [0.0; 7],
+            brown: 0.0,
+        }
+    }
+
+    /// Restart the stream from a seed, clearing the filter states
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+        self.pink = [0.0; 7];
+        self.brown = 0.0;
+    }
+
+    /// Next sample in this generator's color
+    pub fn next_sample(&mut self) -> f32 {
+        match self.color {
+            NoiseColor::White => self.next_white(),
+            NoiseColor::Pink => self.next_pink(),
+            NoiseColor::Brown => self.next_brown(),
+        }
+    }
+
+    pub fn next_white(&mut self) -> f32 {
+        self.rng.next_bipolar()
+    }
+
+    /// Paul Kellet's refined pink noise filter
+    pub fn next_pink(&mut self) -> f32 {
+        let white = self.rng.next_bipolar();
+        let b = &mut self.pink;
+        b[0] = 0.99886 * b[0] + white * 0.055_517_9;
+        b[1] = 0.99332 * b[1] + white * 0.075_075_9;
+        b[2] = 0.96900 * b[2] + white * 0.153_852;
+        b[3] = 0.86650 * b[3] + white * 0.310_485_6;
+        b[4] = 0.55000 * b[4] + white * 0.532_952_2;
+        b[5] = -0.7616 * b[5] - white * 0.016_898;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115_926;
+        pink * 0.11
+    }
+
+    pub fn next_brown(&mut self) -> f32 {
+        let white = self.rng.next_bipolar();
+        // Leak slightly so the integrator cannot drift off to a DC offset
+        self.brown = (self.brown + 0.02 * white) / 1.02;
+        self.brown * 3.5
+    }
+}
@@ -1,17 +1,39 @@
-use crate::envelope::{ADSRConfig, Envelope};
+use crate::envelope::{ADSRConfig, Envelope, RetriggerMode};
+use crate::gen::noise::{next_default_seed, NoiseColor, NoiseGenerator};
+use crate::gen::polyblep::PolyBlepOscillator;
 use crate::gen::waveform::Waveform;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use crate::gen::wavetable::Wavetable;
+use crate::instruments::instrument::{sample_time, Instrument, ParameterInfo};
+use std::any::Any;
+use std::sync::Arc;
 
 pub struct Oscillator {
     pub sample_rate: f32,
     pub waveform: Waveform,
-    pub current_sample_index: f32,
+    pub current_sample_index: f64,
     pub frequency_hz: f32,
     pub envelope: Envelope,
     pub volume: f32,
     pub modulator_frequency_hz: f32,
     pub enabled: bool,
+    /// Fraction of each cycle the `PolyBlepPulse` waveform spends high (0.01 to 0.99)
+    pub pulse_width: f32,
+    /// Running phase for the PolyBLEP waveforms
+    polyblep: PolyBlepOscillator,
+    /// Source for the noise waveforms; keeps running across triggers so every hit differs
+    noise: NoiseGenerator,
+    /// Table played by the `Wavetable` waveform; silent until one is set
+    pub wavetable: Option<Arc<Wavetable>>,
+    /// Morph position across the wavetable's frames (0.0 to 1.0)
+    pub wavetable_position: f32,
+    /// Running phase for the wavetable, in cycles
+    wavetable_phase: f64,
+    /// Time the time-based waveforms measure their phase from
+    phase_origin: f64,
+    /// Origin of the previous hit, still playing while a fade-out retrigger fades it
+    fading_phase_origin: f64,
+    /// The running phases restart once `phase_origin` is reached
+    phase_reset_pending: bool,
 }
 
 impl Oscillator {
@@ -25,17 +47,29 @@ impl Oscillator {
             volume: 1.0,
             modulator_frequency_hz: frequency_hz * 0.5, // Default modulator at half carrier frequency
             enabled: true,
+            pulse_width: 0.5,
+            polyblep: PolyBlepOscillator::new(),
+            // Unseeded oscillators still get their own streams, so layered noise is not correlated
+            noise: NoiseGenerator::new(NoiseColor::White, next_default_seed()),
+            wavetable: None,
+            wavetable_position: 0.0,
+            wavetable_phase: 0.0,
+            phase_origin: 0.0,
+            fading_phase_origin: 0.0,
+            phase_reset_pending: false,
         }
     }
 
     fn advance_sample(&mut self) {
-        self.current_sample_index = (self.current_sample_index + 1.0) % self.sample_rate;
+        self.current_sample_index = (self.current_sample_index + 1.0) % self.sample_rate as f64;
     }
 
     fn calculate_sine_output_from_freq(&self, freq: f32) -> f32 {
-        let two_pi = 2.0 * std::f32::consts::PI;
+        let two_pi = 2.0 * std::f64::consts::PI;
         // current_sample_index is now in samples, so use the original calculation
-        (self.current_sample_index * freq * two_pi / self.sample_rate).sin()
+        // Wrap the phase in f64 so it stays precise however long the note has been sounding
+        let phase = self.current_sample_index * freq as f64 * two_pi / self.sample_rate as f64;
+        ((phase % two_pi) as f32).sin()
     }
 
     fn is_multiple_of_freq_above_nyquist(&self, multiple: f32) -> bool {
@@ -80,15 +114,7 @@ impl Oscillator {
 
     fn noise_wave(&mut self) -> f32 {
         self.advance_sample();
-        
-        // Use current sample index to generate pseudo-random noise
-        let mut hasher = DefaultHasher::new();
-        (self.current_sample_index as u64).hash(&mut hasher);
-        let hash = hasher.finish();
-        
-        // Convert hash to float in range [-1.0, 1.0]
-        let normalized = (hash as f32) / (u64::MAX as f32);
-        (normalized * 2.0) - 1.0
+        self.noise.next_white()
     }
 
     // Time-based waveform methods that don't use advance_sample()
@@ -128,24 +154,40 @@ impl Oscillator {
         carrier * modulator
     }
 
-    fn noise_wave_time_based(&self) -> f32 {
-        // Use current sample index to generate pseudo-random noise
-        let mut hasher = DefaultHasher::new();
-        (self.current_sample_index as u64).hash(&mut hasher);
-        let hash = hasher.finish();
-        
-        // Convert hash to float in range [-1, 1.0]
-        let normalized = (hash as f32) / (u64::MAX as f32);
-        (normalized * 2.0) - 1.0
-    }
 
-    pub fn trigger(&mut self, time: f32) {
+    pub fn trigger(&mut self, time: f64) {
+        let was_active = self.envelope.is_active;
         self.envelope.trigger(time);
+        // A legato retrigger keeps the waveform running where it is
+        if was_active && self.envelope.retrigger_mode == RetriggerMode::Legato {
+            return;
+        }
+
+        self.fading_phase_origin = self.phase_origin;
+        self.phase_origin = self.envelope.trigger_time;
+        // A fade-out retrigger starts the envelope late; keep the old phase until then
+        if self.phase_origin > time {
+            self.phase_reset_pending = true;
+            return;
+        }
+
         // Reset phase for consistent sound on each trigger
+        self.phase_reset_pending = false;
+        self.reset_phase();
+    }
+
+    fn reset_phase(&mut self) {
         self.current_sample_index = 0.0;
+        self.polyblep.reset();
+        self.wavetable_phase = 0.0;
+    }
+
+    /// How a trigger while still sounding affects the envelope and phase
+    pub fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        self.envelope.retrigger_mode = mode;
     }
 
-    pub fn release(&mut self, time: f32) {
+    pub fn release(&mut self, time: f64) {
         self.envelope.release(time);
     }
 
@@ -165,6 +207,38 @@ impl Oscillator {
         self.modulator_frequency_hz
     }
 
+    pub fn set_pulse_width(&mut self, pulse_width: f32) {
+        self.pulse_width = pulse_width.clamp(0.01, 0.99);
+    }
+
+    /// Set the table played by the `Wavetable` waveform
+    pub fn set_wavetable(&mut self, wavetable: Arc<Wavetable>) {
+        self.wavetable = Some(wavetable);
+    }
+
+    pub fn set_wavetable_position(&mut self, position: f32) {
+        self.wavetable_position = position.clamp(0.0, 1.0);
+    }
+
+    fn wavetable_wave(&mut self) -> f32 {
+        let output = match &self.wavetable {
+            Some(wavetable) => wavetable.sample(
+                self.wavetable_phase,
+                self.wavetable_position,
+                self.frequency_hz,
+                self.sample_rate,
+            ),
+            None => 0.0,
+        };
+        self.wavetable_phase = (self.wavetable_phase + self.frequency_hz as f64 / self.sample_rate as f64).rem_euclid(1.0);
+        output
+    }
+
+    /// Restart the noise stream from a seed so renders are reproducible
+    pub fn set_seed(&mut self, seed: u64) {
+        self.noise.set_seed(seed);
+    }
+
     pub fn set_enabled(&mut self, enabled: bool) {
         self.enabled = enabled;
     }
@@ -173,20 +247,28 @@ impl Oscillator {
         self.enabled
     }
 
-    pub fn tick(&mut self, current_time: f32) -> f32 {
+    pub fn tick(&mut self, current_time: f64) -> f32 {
         if !self.enabled {
             return 0.0;
         }
         
         // Update phase based on time elapsed since trigger
         let elapsed_since_trigger = if self.envelope.is_active {
-            current_time - self.envelope.trigger_time
+            if current_time < self.phase_origin {
+                current_time - self.fading_phase_origin
+            } else {
+                if self.phase_reset_pending {
+                    self.phase_reset_pending = false;
+                    self.reset_phase();
+                }
+                current_time - self.phase_origin
+            }
         } else {
             0.0
         };
         
         // Calculate phase in samples for consistent waveform generation
-        self.current_sample_index = elapsed_since_trigger * self.sample_rate;
+        self.current_sample_index = elapsed_since_trigger * self.sample_rate as f64;
         
         let raw_output = match self.waveform {
             Waveform::Sine => self.sine_wave_time_based(),
@@ -194,7 +276,16 @@ impl Oscillator {
             Waveform::Saw => self.saw_wave_time_based(),
             Waveform::Triangle => self.triangle_wave_time_based(),
             Waveform::RingMod => self.ring_mod_wave_time_based(),
-            Waveform::Noise => self.noise_wave_time_based(),
+            Waveform::Noise => self.noise.next_white(),
+            Waveform::PinkNoise => self.noise.next_pink(),
+            Waveform::BrownNoise => self.noise.next_brown(),
+            Waveform::PolyBlepSaw => self.polyblep.next_saw(self.frequency_hz, self.sample_rate),
+            Waveform::PolyBlepSquare => self.polyblep.next_square(self.frequency_hz, self.sample_rate),
+            Waveform::PolyBlepTriangle => self.polyblep.next_triangle(self.frequency_hz, self.sample_rate),
+            Waveform::PolyBlepPulse => {
+                self.polyblep.next_pulse(self.frequency_hz, self.sample_rate, self.pulse_width)
+            }
+            Waveform::Wavetable => self.wavetable_wave(),
         };
         
         // Simple anti-aliasing: reduce volume for high frequencies
@@ -207,4 +298,99 @@ impl Oscillator {
         let envelope_amplitude = self.envelope.get_amplitude(current_time);
         raw_output * anti_alias_gain * envelope_amplitude * self.volume
     }
-}
\ No newline at end of file
+}
+
+const OSCILLATOR_PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo::new("frequency", 20.0, 20000.0, 440.0),
+    ParameterInfo::new("modulator_frequency", 0.0, 20000.0, 220.0),
+    ParameterInfo::new("volume", 0.0, 1.0, 1.0),
+    ParameterInfo::new("pulse_width", 0.01, 0.99, 0.5),
+    ParameterInfo::new("wavetable_position", 0.0, 1.0, 0.0),
+];
+
+impl Instrument for Oscillator {
+    fn trigger(&mut self, time: f64) {
+        Oscillator::trigger(self, time);
+    }
+
+    fn release(&mut self, time: f64) {
+        Oscillator::release(self, time);
+    }
+
+    fn tick(&mut self, current_time: f64) -> f32 {
+        Oscillator::tick(self, current_time)
+    }
+
+    fn is_active(&self) -> bool {
+        self.enabled && self.envelope.is_active
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        Oscillator::set_volume(self, volume);
+    }
+
+    fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        Oscillator::set_retrigger_mode(self, mode);
+    }
+
+    fn retrigger_mode(&self) -> RetriggerMode {
+        self.envelope.retrigger_mode
+    }
+
+    /// Render a block of consecutive samples starting at `start_time`
+    fn process_block(&mut self, output: &mut [f32], start_time: f64) {
+        // A disabled or silent oscillator renders nothing, so skip the waveform work
+        if !self.is_active() {
+            output.fill(0.0);
+            return;
+        }
+
+        let sample_rate = self.sample_rate;
+        for (index, sample) in output.iter_mut().enumerate() {
+            *sample = self.tick(sample_time(start_time, index, sample_rate));
+        }
+    }
+
+    fn parameters(&self) -> &'static [ParameterInfo] {
+        OSCILLATOR_PARAMETERS
+    }
+
+    fn get_parameter(&self, name: &str) -> Option<f32> {
+        match name {
+            "frequency" => Some(self.frequency_hz),
+            "modulator_frequency" => Some(self.modulator_frequency_hz),
+            "volume" => Some(self.volume),
+            "pulse_width" => Some(self.pulse_width),
+            "wavetable_position" => Some(self.wavetable_position),
+            _ => None,
+        }
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) -> bool {
+        match name {
+            "frequency" => self.frequency_hz = value.clamp(20.0, 20000.0),
+            "modulator_frequency" => self.set_modulator_frequency(value),
+            "volume" => Oscillator::set_volume(self, value),
+            "pulse_width" => self.set_pulse_width(value),
+            "wavetable_position" => self.set_wavetable_position(value),
+            _ => return false,
+        }
+        true
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        Oscillator::set_seed(self, seed);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,103 @@
+//! Band-limited classic waveforms using PolyBLEP (steps) and PolyBLAMP (corners)
+//! The naive waveform is computed from a running phase and the discontinuities are smoothed
+//! with a short polynomial residual, which removes most aliasing at a tiny fraction of the
+//! cost of additive synthesis and keeps every harmonic up to Nyquist.
+
+/// Polynomial band-limited step residual for a discontinuity at phase 0
+/// `t` is the phase in [0, 1) and `dt` the phase increment per sample.
+pub fn poly_blep(t: f64, dt: f64) -> f64 {
+    if t < dt {
+        let t = t / dt;
+        t + t - t * t - 1.0
+    } else if t > 1.0 - dt {
+        let t = (t - 1.0) / dt;
+        t * t + t + t + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Polynomial band-limited ramp residual for a slope change at phase 0
+pub fn poly_blamp(t: f64, dt: f64) -> f64 {
+    if t < dt {
+        let t = t / dt - 1.0;
+        -t * t * t / 3.0
+    } else if t > 1.0 - dt {
+        let t = (t - 1.0) / dt + 1.0;
+        t * t * t / 3.0
+    } else {
+        0.0
+    }
+}
+
+/// Phase accumulator producing band-limited saw, square, pulse and triangle waves
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PolyBlepOscillator {
+    /// Position in the current cycle, in [0, 1)
+    phase: f64,
+}
+
+impl PolyBlepOscillator {
+    pub fn new() -> Self {
+        Self { phase: 0.0 }
+    }
+
+    /// Restart the waveform at the beginning of a cycle
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+    }
+
+    pub fn phase(&self) -> f64 {
+        self.phase
+    }
+
+    /// Next sample of a rising sawtooth
+    pub fn next_saw(&mut self, frequency_hz: f32, sample_rate: f32) -> f32 {
+        let (t, dt) = self.advance(frequency_hz, sample_rate);
+        (2.0 * t - 1.0 - poly_blep(t, dt)) as f32
+    }
+
+    /// Next sample of a square wave (a pulse with 50% width)
+    pub fn next_square(&mut self, frequency_hz: f32, sample_rate: f32) -> f32 {
+        self.next_pulse(frequency_hz, sample_rate, 0.5)
+    }
+
+    /// Next sample of a pulse wave that is high for `width` of each cycle (0.01 to 0.99)
+    pub fn next_pulse(&mut self, frequency_hz: f32, sample_rate: f32, width: f32) -> f32 {
+        let width = width.clamp(0.01, 0.99) as f64;
+        let (t, dt) = self.advance(frequency_hz, sample_rate);
+
+        let naive = if t < width { 1.0 } else { -1.0 };
+        // Rising edge at phase 0, falling edge at `width`
+        let falling = (t + 1.0 - width) % 1.0;
+        (naive + poly_blep(t, dt) - poly_blep(falling, dt)) as f32
+    }
+
+    /// Next sample of a triangle wave, starting at zero and rising
+    pub fn next_triangle(&mut self, frequency_hz: f32, sample_rate: f32) -> f32 {
+        let (t, dt) = self.advance(frequency_hz, sample_rate);
+
+        let mut naive = 4.0 * t;
+        if naive >= 3.0 {
+            naive -= 4.0;
+        } else if naive > 1.0 {
+            naive = 2.0 - naive;
+        }
+
+        // Corners at a quarter (peak) and three quarters (trough) of the cycle
+        let trough = (t + 0.25) % 1.0;
+        let peak = (t + 0.75) % 1.0;
+        (naive + 4.0 * dt * (poly_blamp(trough, dt) - poly_blamp(peak, dt))) as f32
+    }
+
+    /// Return the phase for this sample and the increment, then step the accumulator
+    fn advance(&mut self, frequency_hz: f32, sample_rate: f32) -> (f64, f64) {
+        let dt = (frequency_hz.abs() as f64 / sample_rate as f64).min(0.5);
+        let t = self.phase;
+        self.phase += dt;
+        if self.phase >= 1.0 {
+            self.phase -= 1.0;
+        }
+        (t, dt)
+    }
+}
@@ -5,5 +5,16 @@ pub enum Waveform {
     Saw,
     Triangle,
     RingMod,
+    /// White noise
     Noise,
+    PinkNoise,
+    BrownNoise,
+    /// Band-limited waveforms generated with PolyBLEP from a running phase
+    PolyBlepSaw,
+    PolyBlepSquare,
+    PolyBlepTriangle,
+    /// Band-limited pulse with a variable width, see `Oscillator::pulse_width`
+    PolyBlepPulse,
+    /// Morphable wavetable, see `Oscillator::set_wavetable`
+    Wavetable,
 }
\ No newline at end of file
@@ -0,0 +1,306 @@
+//! Mip-mapped, morphable wavetables
+//! Each single-cycle frame is analysed once at load time and resynthesised at a fixed table
+//! size into a set of band-limited copies (one per octave), so playback never produces
+//! harmonics above Nyquist. A position parameter crossfades between neighbouring frames.
+
+use crate::wav::read_wav_file;
+use std::f64::consts::PI;
+use std::path::Path;
+
+/// Number of samples in every stored table
+pub const TABLE_SIZE: usize = 2048;
+
+/// Highest harmonic a table can hold
+const MAX_HARMONICS: usize = TABLE_SIZE / 2;
+
+/// Band-limited copies per frame: all harmonics, then half as many for each octave up to one
+const MIP_LEVELS: usize = 11;
+
+/// Stored length of one table; the extra guard sample lets interpolation read one past the end
+const STRIDE: usize = TABLE_SIZE + 1;
+
+/// A set of single-cycle frames ready for band-limited playback
+/// Share one between oscillators with `Arc<Wavetable>`; tables are built off the audio thread.
+#[derive(Clone)]
+pub struct Wavetable {
+    frame_count: usize,
+    /// Tables laid out as [frame][mip level][sample]
+    tables: Vec<f32>,
+}
+
+impl Wavetable {
+    /// Build a wavetable from a buffer of consecutive single-cycle frames of `frame_size` samples
+    /// Frames of any length are accepted; they are resampled to `TABLE_SIZE` while band-limiting.
+    pub fn from_samples(samples: &[f32], frame_size: usize) -> Result<Self, anyhow::Error> {
+        if frame_size < 2 {
+            return Err(anyhow::anyhow!("Wavetable frames need at least 2 samples"));
+        }
+        if samples.is_empty() || !samples.len().is_multiple_of(frame_size) {
+            return Err(anyhow::anyhow!(
+                "Wavetable length {} is not a whole number of {}-sample frames",
+                samples.len(),
+                frame_size
+            ));
+        }
+
+        let spectra = samples.chunks_exact(frame_size).map(analyse_frame).collect();
+        Ok(Self::from_spectra(spectra))
+    }
+
+    /// Build a one-frame wavetable from a single cycle
+    pub fn from_frame(samples: &[f32]) -> Result<Self, anyhow::Error> {
+        Self::from_samples(samples, samples.len())
+    }
+
+    /// Load a wavetable from a WAV file of consecutive `frame_size`-sample frames
+    /// Only the first channel of a multichannel file is used.
+    pub fn from_wav_file<P: AsRef<Path>>(path: P, frame_size: usize) -> Result<Self, anyhow::Error> {
+        let (samples, spec) = read_wav_file(path)?;
+        let mono: Vec<f32> = samples.iter().step_by(spec.channels as usize).copied().collect();
+        Self::from_samples(&mono, frame_size)
+    }
+
+    /// Built-in table morphing sine, triangle, saw and square
+    pub fn basic_shapes() -> Self {
+        let sine = additive_spectrum(|harmonic| if harmonic == 1 { 1.0 } else { 0.0 });
+        let triangle = additive_spectrum(|harmonic| {
+            if harmonic % 2 == 1 {
+                // Alternating signs give the triangle its corners
+                let sign = if (harmonic / 2) % 2 == 0 { 1.0 } else { -1.0 };
+                sign * 8.0 / (PI * PI * (harmonic * harmonic) as f64)
+            } else {
+                0.0
+            }
+        });
+        let saw = additive_spectrum(|harmonic| 2.0 / (PI * harmonic as f64));
+        let square = additive_spectrum(|harmonic| {
+            if harmonic % 2 == 1 {
+                4.0 / (PI * harmonic as f64)
+            } else {
+                0.0
+            }
+        });
+        Self::from_spectra(vec![sine, triangle, saw, square])
+    }
+
+    /// Built-in table sweeping a pulse wave from 50% down to 5% width
+    pub fn pulse_widths() -> Self {
+        let spectra = (0..8)
+            .map(|frame| {
+                let width = 0.5 - 0.45 * frame as f64 / 7.0;
+                // Pulse harmonics: (4 / (pi k)) sin(pi k w), as cosine-phase partials
+                (0..=MAX_HARMONICS)
+                    .map(|harmonic| {
+                        if harmonic == 0 {
+                            return (0.0, 0.0);
+                        }
+                        let k = harmonic as f64;
+                        let amplitude = 4.0 / (PI * k) * (PI * k * width).sin();
+                        // A cosine of amplitude a appears as a/2 * N in the real part of its bin
+                        (amplitude * TABLE_SIZE as f64 * 0.5, 0.0)
+                    })
+                    .collect()
+            })
+            .collect();
+        Self::from_spectra(spectra)
+    }
+
+    /// Look up a built-in table by name ("basic" or "pulse")
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "basic" => Some(Self::basic_shapes()),
+            "pulse" => Some(Self::pulse_widths()),
+            _ => None,
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Read the table at `phase` (0.0 to 1.0) and morph `position` (0.0 to 1.0 across all frames)
+    /// The mip level is chosen so that no harmonic exceeds Nyquist at `frequency_hz`.
+    pub fn sample(&self, phase: f64, position: f32, frequency_hz: f32, sample_rate: f32) -> f32 {
+        let level = mip_level(frequency_hz, sample_rate);
+
+        let frame_position = position.clamp(0.0, 1.0) * (self.frame_count - 1) as f32;
+        let frame = (frame_position as usize).min(self.frame_count - 1);
+        let next_frame = (frame + 1).min(self.frame_count - 1);
+        let morph = frame_position - frame as f32;
+
+        let index = phase.rem_euclid(1.0) * TABLE_SIZE as f64;
+        let sample_index = (index as usize).min(TABLE_SIZE - 1);
+        let fraction = (index - sample_index as f64) as f32;
+
+        let current = self.read(frame, level, sample_index, fraction);
+        if morph > 0.0 {
+            let next = self.read(next_frame, level, sample_index, fraction);
+            current + (next - current) * morph
+        } else {
+            current
+        }
+    }
+
+    fn read(&self, frame: usize, level: usize, index: usize, fraction: f32) -> f32 {
+        let start = (frame * MIP_LEVELS + level) * STRIDE + index;
+        let a = self.tables[start];
+        let b = self.tables[start + 1];
+        a + (b - a) * fraction
+    }
+
+    /// Resynthesise every frame's spectrum into band-limited tables, normalised across the set
+    fn from_spectra(spectra: Vec<Vec<(f64, f64)>>) -> Self {
+        let frame_count = spectra.len();
+        let mut tables = vec![0.0; frame_count * MIP_LEVELS * STRIDE];
+        let mut buffer = vec![(0.0, 0.0); TABLE_SIZE];
+
+        for (frame, spectrum) in spectra.iter().enumerate() {
+            for level in 0..MIP_LEVELS {
+                // The table's own Nyquist bin is left empty
+                let harmonics = (MAX_HARMONICS >> level).min(spectrum.len() - 1).min(MAX_HARMONICS - 1);
+
+                buffer.fill((0.0, 0.0));
+                for harmonic in 1..=harmonics {
+                    let (re, im) = spectrum[harmonic];
+                    buffer[harmonic] = (re, im);
+                    buffer[TABLE_SIZE - harmonic] = (re, -im);
+                }
+                fft(&mut buffer, true);
+
+                let start = (frame * MIP_LEVELS + level) * STRIDE;
+                let table = &mut tables[start..start + STRIDE];
+                for (sample, value) in table.iter_mut().zip(buffer.iter()) {
+                    *sample = value.0 as f32;
+                }
+                table[TABLE_SIZE] = table[0];
+            }
+        }
+
+        // Normalise the whole set by its loudest full-band frame so morphing keeps relative levels
+        let peak = (0..frame_count)
+            .flat_map(|frame| {
+                let start = frame * MIP_LEVELS * STRIDE;
+                tables[start..start + STRIDE].iter()
+            })
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        if peak > 0.0 {
+            for sample in tables.iter_mut() {
+                *sample /= peak;
+            }
+        }
+
+        Self { frame_count, tables }
+    }
+}
+
+impl std::fmt::Debug for Wavetable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Wavetable").field("frame_count", &self.frame_count).finish()
+    }
+}
+
+/// Mip level whose highest harmonic stays below Nyquist at the given frequency
+fn mip_level(frequency_hz: f32, sample_rate: f32) -> usize {
+    let allowed = sample_rate * 0.5 / frequency_hz.abs().max(1e-3);
+    if allowed >= MAX_HARMONICS as f32 {
+        0
+    } else if allowed < 1.0 {
+        MIP_LEVELS - 1
+    } else {
+        ((MAX_HARMONICS as f32 / allowed).log2().ceil() as usize).min(MIP_LEVELS - 1)
+    }
+}
+
+/// Spectrum of one cycle as (re, im) per harmonic, scaled for synthesis at `TABLE_SIZE`
+/// Index 0 (DC) is dropped; partials at or above the source frame's Nyquist are discarded.
+fn analyse_frame(frame: &[f32]) -> Vec<(f64, f64)> {
+    let length = frame.len();
+    let harmonics = ((length - 1) / 2).min(MAX_HARMONICS);
+    let mut spectrum = vec![(0.0, 0.0); harmonics + 1];
+
+    if length.is_power_of_two() {
+        let mut buffer: Vec<(f64, f64)> = frame.iter().map(|&sample| (sample as f64, 0.0)).collect();
+        fft(&mut buffer, false);
+        spectrum[1..].copy_from_slice(&buffer[1..harmonics + 1]);
+    } else {
+        // Plain DFT for odd frame lengths; this only runs when a table is loaded
+        for (harmonic, bin) in spectrum.iter_mut().enumerate().skip(1) {
+            let (mut re, mut im) = (0.0, 0.0);
+            for (n, &sample) in frame.iter().enumerate() {
+                let angle = -2.0 * PI * (harmonic * n) as f64 / length as f64;
+                re += sample as f64 * angle.cos();
+                im += sample as f64 * angle.sin();
+            }
+            *bin = (re, im);
+        }
+    }
+
+    // Rescale so the inverse transform at TABLE_SIZE reproduces the original amplitude
+    let scale = TABLE_SIZE as f64 / length as f64;
+    for bin in spectrum.iter_mut() {
+        bin.0 *= scale;
+        bin.1 *= scale;
+    }
+    spectrum
+}
+
+/// Spectrum of sine partials with the given amplitude per harmonic, scaled for `TABLE_SIZE`
+fn additive_spectrum(amplitude: impl Fn(usize) -> f64) -> Vec<(f64, f64)> {
+    (0..=MAX_HARMONICS)
+        .map(|harmonic| {
+            if harmonic == 0 {
+                (0.0, 0.0)
+            } else {
+                // A sine of amplitude a appears as -a/2 * N in the imaginary part of its bin
+                (0.0, -amplitude(harmonic) * TABLE_SIZE as f64 * 0.5)
+            }
+        })
+        .collect()
+}
+
+/// In-place radix-2 FFT over (re, im) pairs; the inverse includes the 1/N scaling
+fn fft(buffer: &mut [(f64, f64)], inverse: bool) {
+    let n = buffer.len();
+    debug_assert!(n.is_power_of_two());
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            buffer.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut length = 2;
+    while length <= n {
+        let angle = sign * 2.0 * PI / length as f64;
+        let (w_re, w_im) = (angle.cos(), angle.sin());
+        for start in (0..n).step_by(length) {
+            let (mut re, mut im) = (1.0, 0.0);
+            for k in 0..length / 2 {
+                let (a_re, a_im) = buffer[start + k];
+                let (b_re, b_im) = buffer[start + k + length / 2];
+                let (t_re, t_im) = (b_re * re - b_im * im, b_re * im + b_im * re);
+                buffer[start + k] = (a_re + t_re, a_im + t_im);
+                buffer[start + k + length / 2] = (a_re - t_re, a_im - t_im);
+                (re, im) = (re * w_re - im * w_im, re * w_im + im * w_re);
+            }
+        }
+        length <<= 1;
+    }
+
+    if inverse {
+        let scale = 1.0 / n as f64;
+        for value in buffer.iter_mut() {
+            value.0 *= scale;
+            value.1 *= scale;
+        }
+    }
+}
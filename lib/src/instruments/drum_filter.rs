@@ -0,0 +1,140 @@
+//! Filter with its own decaying envelope on the cutoff, shared by the drum voices
+//! The envelope sweeps the cutoff in octaves, so an amount means the same movement whatever the
+//! base cutoff. With the type set to `FilterType::Off` the drum sounds exactly as it did unfiltered.
+
+use crate::envelope::{BreakpointEnvelope, EnvelopeSegment, RetriggerMode};
+use crate::filters::{Filter, FilterType, MonoFilter};
+
+/// Octaves the cutoff moves at full envelope amount
+const ENVELOPE_OCTAVES: f32 = 6.0;
+
+pub const MIN_CUTOFF: f32 = 20.0;
+pub const MAX_CUTOFF: f32 = 20000.0;
+
+/// Filter settings carried by a drum config
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FilterConfig {
+    pub filter_type: FilterType,
+    pub cutoff: f32,           // Cutoff or center frequency in Hz
+    pub resonance: f32,        // 0.0-1.0
+    pub envelope_amount: f32,  // Cutoff sweep at the hit, -1.0 (down) to 1.0 (up)
+    pub envelope_decay: f32,   // Seconds for the sweep to fall back
+}
+
+impl FilterConfig {
+    pub fn new(filter_type: FilterType, cutoff: f32, resonance: f32, envelope_amount: f32, envelope_decay: f32) -> Self {
+        Self {
+            filter_type,
+            cutoff: cutoff.clamp(MIN_CUTOFF, MAX_CUTOFF),
+            resonance: resonance.clamp(0.0, 1.0),
+            envelope_amount: envelope_amount.clamp(-1.0, 1.0),
+            envelope_decay: envelope_decay.clamp(0.005, 2.0),
+        }
+    }
+
+    /// No filtering
+    pub fn off() -> Self {
+        Self::new(FilterType::Off, MAX_CUTOFF, 0.0, 0.0, 0.1)
+    }
+
+    /// Value of one of the `filter_*` instrument parameters
+    pub fn get_parameter(&self, name: &str) -> Option<f32> {
+        match name {
+            "filter_cutoff" => Some(self.cutoff),
+            "filter_resonance" => Some(self.resonance),
+            "filter_env_amount" => Some(self.envelope_amount),
+            "filter_env_decay" => Some(self.envelope_decay),
+            _ => None,
+        }
+    }
+
+    /// Set one of the `filter_*` instrument parameters, returning false for any other name
+    pub fn set_parameter(&mut self, name: &str, value: f32) -> bool {
+        let mut updated = *self;
+        match name {
+            "filter_cutoff" => updated.cutoff = value,
+            "filter_resonance" => updated.resonance = value,
+            "filter_env_amount" => updated.envelope_amount = value,
+            "filter_env_decay" => updated.envelope_decay = value,
+            _ => return false,
+        }
+        *self = Self::new(
+            updated.filter_type,
+            updated.cutoff,
+            updated.resonance,
+            updated.envelope_amount,
+            updated.envelope_decay,
+        );
+        true
+    }
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self::off()
+    }
+}
+
+/// Runtime state for a drum's filter
+#[derive(Clone, Copy, Debug)]
+pub struct DrumFilter {
+    pub config: FilterConfig,
+    filter: Filter,
+    envelope: BreakpointEnvelope,
+}
+
+impl DrumFilter {
+    pub fn new(sample_rate: f32, config: FilterConfig) -> Self {
+        let mut filter = Self {
+            config,
+            filter: Filter::new(sample_rate, config.filter_type, config.cutoff, config.resonance),
+            envelope: BreakpointEnvelope::new(&[]),
+        };
+        filter.set_config(config);
+        filter
+    }
+
+    pub fn set_config(&mut self, config: FilterConfig) {
+        self.config = config;
+        self.filter.set_type(config.filter_type);
+        self.envelope.set_segments(&[
+            EnvelopeSegment::linear(0.001, 1.0),
+            EnvelopeSegment::exponential(config.envelope_decay, 0.0),
+        ]);
+    }
+
+    pub fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        self.envelope.retrigger_mode = mode;
+    }
+
+    /// Start the cutoff sweep; the filter keeps its state so a ringing drum isn't cut short
+    pub fn trigger(&mut self, time: f64) {
+        self.envelope.trigger(time);
+    }
+
+    #[inline]
+    pub fn process(&mut self, input: f32, current_time: f64) -> f32 {
+        if self.config.filter_type == FilterType::Off {
+            return input;
+        }
+        self.update_cutoff(current_time);
+        self.filter.process(input)
+    }
+
+    /// Filter a short block in place, moving the cutoff once at `start_time`
+    /// Keep blocks to a control chunk or so, since the sweep holds still across the block.
+    pub fn process_block(&mut self, buffer: &mut [f32], start_time: f64) {
+        if self.config.filter_type == FilterType::Off {
+            return;
+        }
+        self.update_cutoff(start_time);
+        self.filter.process_block(buffer);
+    }
+
+    #[inline]
+    fn update_cutoff(&mut self, current_time: f64) {
+        let sweep = self.envelope.get_amplitude(current_time) * self.config.envelope_amount * ENVELOPE_OCTAVES;
+        let cutoff = (self.config.cutoff * sweep.exp2()).clamp(MIN_CUTOFF, MAX_CUTOFF);
+        self.filter.set_parameters(cutoff, self.config.resonance);
+    }
+}
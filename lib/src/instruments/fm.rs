@@ -0,0 +1,503 @@
+use crate::envelope::{ADSRConfig, Envelope, RetriggerMode, RETRIGGER_FADE_TIME};
+use crate::instruments::instrument::{Instrument, ParameterInfo};
+use std::any::Any;
+use std::f64::consts::TAU;
+
+/// Number of operators in every FM voice
+pub const OPERATOR_COUNT: usize = 6;
+
+/// Phase deviation in radians applied by a modulator at full level
+const MODULATION_DEPTH: f32 = std::f32::consts::TAU;
+
+/// Phase deviation in radians applied by an operator's own output at full feedback
+const FEEDBACK_DEPTH: f32 = std::f32::consts::PI;
+
+indexed_enum! {
+    /// How the operators are wired together
+    /// Operators are numbered from 0 and only ever modulate lower-numbered operators,
+    /// so each sample is computed in a single pass from the last operator down to the first.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum FmAlgorithm {
+        /// 5 -> 4 -> 3 -> 2 -> 1 -> 0, a single deep stack for harsh, evolving tones
+        Stack,
+        /// 2 -> 1 -> 0 and 5 -> 4 -> 3, two independent three-operator stacks
+        TwoStacks,
+        /// 1 -> 0, 3 -> 2 and 5 -> 4, three simple pairs (classic electric piano and bell layering)
+        ThreePairs,
+        /// 1..5 all modulate 0, dense sidebands for metallic percussion
+        ManyToOne,
+        /// 5 modulates 0..4, which are all carriers, like an organ with shared brightness
+        OneToMany,
+        /// Every operator is a carrier, plain additive synthesis
+        Additive,
+    }
+}
+
+impl FmAlgorithm {
+    /// Bitmask of the operators that modulate `operator`
+    fn modulators(self, operator: usize) -> u8 {
+        match (self, operator) {
+            (FmAlgorithm::Stack, 0..=4) => 1 << (operator + 1),
+            (FmAlgorithm::TwoStacks, 0 | 1 | 3 | 4) => 1 << (operator + 1),
+            (FmAlgorithm::ThreePairs, 0 | 2 | 4) => 1 << (operator + 1),
+            (FmAlgorithm::ManyToOne, 0) => 0b11_1110,
+            (FmAlgorithm::OneToMany, 0..=4) => 1 << 5,
+            _ => 0,
+        }
+    }
+
+    /// Whether `operator` is heard directly rather than only modulating others
+    fn is_carrier(self, operator: usize) -> bool {
+        match self {
+            FmAlgorithm::Stack | FmAlgorithm::ManyToOne => operator == 0,
+            FmAlgorithm::TwoStacks => operator == 0 || operator == 3,
+            FmAlgorithm::ThreePairs => operator.is_multiple_of(2),
+            FmAlgorithm::OneToMany => operator < 5,
+            FmAlgorithm::Additive => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct OperatorConfig {
+    pub ratio: f32,         // Frequency as a multiple of the voice frequency (0.125-32)
+    pub level: f32,         // Output level for carriers, modulation depth for modulators (0.0-1.0)
+    pub feedback: f32,      // Self-modulation amount (0.0-1.0)
+    pub envelope: ADSRConfig,
+}
+
+impl OperatorConfig {
+    pub fn new(ratio: f32, level: f32, feedback: f32, envelope: ADSRConfig) -> Self {
+        Self {
+            ratio: ratio.clamp(0.125, 32.0),
+            level: level.clamp(0.0, 1.0),
+            feedback: feedback.clamp(0.0, 1.0),
+            envelope,
+        }
+    }
+
+    /// A silent operator
+    pub fn off() -> Self {
+        Self::new(1.0, 0.0, 0.0, ADSRConfig::new(0.001, 0.1, 0.0, 0.1))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FmConfig {
+    pub frequency: f32, // Voice frequency in Hz
+    pub algorithm: FmAlgorithm,
+    pub operators: [OperatorConfig; OPERATOR_COUNT],
+    pub volume: f32, // Overall volume (0.0-1.0)
+}
+
+impl FmConfig {
+    pub fn new(frequency: f32, algorithm: FmAlgorithm, operators: [OperatorConfig; OPERATOR_COUNT], volume: f32) -> Self {
+        Self {
+            frequency: frequency.clamp(20.0, 20000.0),
+            algorithm,
+            operators,
+            volume: volume.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Two-pair electric piano: a bright tine over a warm body
+    pub fn electric_piano() -> Self {
+        let off = OperatorConfig::off();
+        Self::new(
+            220.0,
+            FmAlgorithm::ThreePairs,
+            [
+                OperatorConfig::new(1.0, 0.9, 0.0, ADSRConfig::new(0.002, 1.5, 0.0, 0.3)),
+                OperatorConfig::new(1.0, 0.25, 0.1, ADSRConfig::new(0.002, 1.0, 0.0, 0.3)),
+                OperatorConfig::new(1.0, 0.5, 0.0, ADSRConfig::new(0.001, 0.6, 0.0, 0.2)),
+                OperatorConfig::new(14.0, 0.12, 0.0, ADSRConfig::new(0.001, 0.15, 0.0, 0.1)),
+                off,
+                off,
+            ],
+            0.7,
+        )
+    }
+
+    /// Inharmonic pairs with long decays
+    pub fn bell() -> Self {
+        let off = OperatorConfig::off();
+        Self::new(
+            440.0,
+            FmAlgorithm::ThreePairs,
+            [
+                OperatorConfig::new(1.0, 0.8, 0.0, ADSRConfig::new(0.001, 3.0, 0.0, 0.5)),
+                OperatorConfig::new(3.5, 0.35, 0.0, ADSRConfig::new(0.001, 2.0, 0.0, 0.5)),
+                OperatorConfig::new(2.0, 0.4, 0.0, ADSRConfig::new(0.001, 2.0, 0.0, 0.5)),
+                OperatorConfig::new(5.19, 0.3, 0.0, ADSRConfig::new(0.001, 1.2, 0.0, 0.4)),
+                off,
+                off,
+            ],
+            0.6,
+        )
+    }
+
+    /// Short, clangorous hit from five inharmonic modulators (cowbell, metal, ride-like tones)
+    pub fn metallic() -> Self {
+        let envelope = |decay: f32| ADSRConfig::new(0.001, decay, 0.0, 0.05);
+        Self::new(
+            540.0,
+            FmAlgorithm::ManyToOne,
+            [
+                OperatorConfig::new(1.0, 0.9, 0.0, envelope(0.35)),
+                OperatorConfig::new(1.48, 0.3, 0.0, envelope(0.25)),
+                OperatorConfig::new(2.13, 0.25, 0.0, envelope(0.2)),
+                OperatorConfig::new(3.27, 0.2, 0.2, envelope(0.15)),
+                OperatorConfig::new(4.61, 0.15, 0.0, envelope(0.1)),
+                OperatorConfig::new(6.93, 0.1, 0.0, envelope(0.08)),
+            ],
+            0.6,
+        )
+    }
+
+    /// Punchy bass with a feedback-driven attack
+    pub fn bass() -> Self {
+        let off = OperatorConfig::off();
+        Self::new(
+            55.0,
+            FmAlgorithm::Stack,
+            [
+                OperatorConfig::new(1.0, 0.9, 0.0, ADSRConfig::new(0.002, 0.5, 0.0, 0.1)),
+                OperatorConfig::new(1.0, 0.3, 0.0, ADSRConfig::new(0.001, 0.25, 0.0, 0.1)),
+                OperatorConfig::new(2.0, 0.15, 0.5, ADSRConfig::new(0.001, 0.08, 0.0, 0.05)),
+                off,
+                off,
+                off,
+            ],
+            0.8,
+        )
+    }
+}
+
+impl Default for FmConfig {
+    fn default() -> Self {
+        Self::electric_piano()
+    }
+}
+
+/// Runtime state of one operator
+struct FmOperator {
+    /// Position in the current cycle, in [0, 1)
+    phase: f64,
+    envelope: Envelope,
+    /// The last two outputs, averaged for feedback to keep it stable
+    history: [f32; 2],
+}
+
+impl FmOperator {
+    fn new(config: &OperatorConfig) -> Self {
+        Self {
+            phase: 0.0,
+            envelope: Envelope::with_config(config.envelope),
+            history: [0.0; 2],
+        }
+    }
+}
+
+/// Phase-modulation voice with six operators and selectable algorithms
+/// Works as a standalone Instrument, or inside other instruments as a tone source.
+pub struct FmSynth {
+    pub sample_rate: f32,
+    pub config: FmConfig,
+    operators: [FmOperator; OPERATOR_COUNT],
+    pub is_active: bool,
+    /// Time a fade-out retrigger restarts the operator phases
+    phase_reset_at: Option<f64>,
+}
+
+impl FmSynth {
+    pub fn new(sample_rate: f32) -> Self {
+        Self::with_config(sample_rate, FmConfig::default())
+    }
+
+    pub fn with_config(sample_rate: f32, config: FmConfig) -> Self {
+        Self {
+            sample_rate,
+            config,
+            operators: std::array::from_fn(|index| FmOperator::new(&config.operators[index])),
+            is_active: false,
+            phase_reset_at: None,
+        }
+    }
+
+    pub fn set_config(&mut self, config: FmConfig) {
+        self.config = config;
+        for (operator, operator_config) in self.operators.iter_mut().zip(config.operators.iter()) {
+            operator.envelope.set_config(operator_config.envelope);
+        }
+    }
+
+    pub fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        for operator in &mut self.operators {
+            operator.envelope.retrigger_mode = mode;
+        }
+    }
+
+    pub fn retrigger_mode(&self) -> RetriggerMode {
+        self.operators[0].envelope.retrigger_mode
+    }
+
+    pub fn trigger(&mut self, time: f64) {
+        let was_active = self.is_active;
+        self.is_active = true;
+        for operator in &mut self.operators {
+            operator.envelope.trigger(time);
+        }
+
+        self.phase_reset_at = None;
+        match self.retrigger_mode() {
+            RetriggerMode::Legato if was_active => {}
+            // Let the previous hit fade out on its own phases
+            RetriggerMode::FadeOut if was_active => self.phase_reset_at = Some(time + RETRIGGER_FADE_TIME as f64),
+            _ => self.reset_phases(),
+        }
+    }
+
+    /// Restart every operator at phase zero so each hit has the same attack
+    fn reset_phases(&mut self) {
+        for operator in &mut self.operators {
+            operator.phase = 0.0;
+            operator.history = [0.0; 2];
+        }
+    }
+
+    pub fn release(&mut self, time: f64) {
+        if self.is_active {
+            for operator in &mut self.operators {
+                operator.envelope.release(time);
+            }
+        }
+    }
+
+    pub fn tick(&mut self, current_time: f64) -> f32 {
+        if !self.is_active {
+            return 0.0;
+        }
+        if self.phase_reset_at.is_some_and(|at| current_time >= at) {
+            self.phase_reset_at = None;
+            self.reset_phases();
+        }
+
+        let algorithm = self.config.algorithm;
+        let mut outputs = [0.0f32; OPERATOR_COUNT];
+        let mut carrier_sum = 0.0;
+        let mut carrier_count = 0;
+        let mut carrier_active = false;
+
+        // Modulators always have higher indices, so compute from the last operator down
+        for index in (0..OPERATOR_COUNT).rev() {
+            let config = self.config.operators[index];
+            let operator = &mut self.operators[index];
+            if config.level <= 0.0 {
+                continue;
+            }
+            let is_carrier = algorithm.is_carrier(index);
+            if is_carrier {
+                carrier_count += 1;
+            }
+
+            let amplitude = operator.envelope.get_amplitude(current_time);
+            if is_carrier && operator.envelope.is_active {
+                carrier_active = true;
+            }
+
+            let modulators = algorithm.modulators(index);
+            let mut modulation = 0.0;
+            for (modulator, &output) in outputs.iter().enumerate().skip(index + 1) {
+                if modulators & (1 << modulator) != 0 {
+                    modulation += output;
+                }
+            }
+            modulation *= MODULATION_DEPTH;
+            modulation += config.feedback * (operator.history[0] + operator.history[1]) * 0.5 * FEEDBACK_DEPTH;
+
+            let output = ((operator.phase * TAU) as f32 + modulation).sin() * amplitude * config.level;
+            operator.history = [output, operator.history[0]];
+            outputs[index] = output;
+
+            let increment = self.config.frequency as f64 * config.ratio as f64 / self.sample_rate as f64;
+            operator.phase = (operator.phase + increment).fract();
+
+            if is_carrier {
+                carrier_sum += output;
+            }
+        }
+
+        self.is_active = carrier_active;
+
+        // Scale by the number of carriers with a level above zero so additive algorithms do not
+        // clip; finished carriers still count, so the mix does not jump as they die away
+        carrier_sum / carrier_count.max(1) as f32 * self.config.volume
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.is_active
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.config.volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.config.frequency = frequency.clamp(20.0, 20000.0);
+    }
+
+    pub fn set_algorithm(&mut self, algorithm: FmAlgorithm) {
+        self.config.algorithm = algorithm;
+    }
+
+    /// Replace one operator's settings; out-of-range indices are ignored
+    pub fn set_operator(&mut self, index: usize, config: OperatorConfig) {
+        if index < OPERATOR_COUNT {
+            self.config.operators[index] = config;
+            self.operators[index].envelope.set_config(config.envelope);
+        }
+    }
+}
+
+/// Parameter list: voice-wide settings, then the same seven settings for each operator
+macro_rules! fm_parameters {
+    ($($operator:literal),*) => {
+        &[
+            ParameterInfo::new("frequency", 20.0, 20000.0, 220.0),
+            ParameterInfo::new("algorithm", 0.0, 5.0, 2.0),
+            ParameterInfo::new("volume", 0.0, 1.0, 0.7),
+            $(
+                ParameterInfo::new(concat!($operator, "_ratio"), 0.125, 32.0, 1.0),
+                ParameterInfo::new(concat!($operator, "_level"), 0.0, 1.0, 0.0),
+                ParameterInfo::new(concat!($operator, "_feedback"), 0.0, 1.0, 0.0),
+                ParameterInfo::new(concat!($operator, "_attack"), 0.001, 5.0, 0.001),
+                ParameterInfo::new(concat!($operator, "_decay"), 0.001, 10.0, 0.1),
+                ParameterInfo::new(concat!($operator, "_sustain"), 0.0, 1.0, 0.0),
+                ParameterInfo::new(concat!($operator, "_release"), 0.001, 10.0, 0.1),
+            )*
+        ]
+    };
+}
+
+const FM_PARAMETERS: &[ParameterInfo] = fm_parameters!("op1", "op2", "op3", "op4", "op5", "op6");
+
+/// Split an operator parameter name like "op3_ratio" into (2, "ratio")
+fn parse_operator_parameter(name: &str) -> Option<(usize, &str)> {
+    let (operator, setting) = name.strip_prefix("op")?.split_once('_')?;
+    let number: usize = operator.parse().ok()?;
+    (1..=OPERATOR_COUNT).contains(&number).then_some((number - 1, setting))
+}
+
+impl Instrument for FmSynth {
+    fn trigger(&mut self, time: f64) {
+        FmSynth::trigger(self, time);
+    }
+
+    fn release(&mut self, time: f64) {
+        FmSynth::release(self, time);
+    }
+
+    fn tick(&mut self, current_time: f64) -> f32 {
+        FmSynth::tick(self, current_time)
+    }
+
+    fn is_active(&self) -> bool {
+        self.is_active
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        FmSynth::set_volume(self, volume);
+    }
+
+    fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        FmSynth::set_retrigger_mode(self, mode);
+    }
+
+    fn retrigger_mode(&self) -> RetriggerMode {
+        FmSynth::retrigger_mode(self)
+    }
+
+    fn parameters(&self) -> &'static [ParameterInfo] {
+        FM_PARAMETERS
+    }
+
+    fn get_parameter(&self, name: &str) -> Option<f32> {
+        match name {
+            "frequency" => return Some(self.config.frequency),
+            "algorithm" => return Some(self.config.algorithm.index() as f32),
+            "volume" => return Some(self.config.volume),
+            _ => {}
+        }
+
+        let (index, setting) = parse_operator_parameter(name)?;
+        let operator = &self.config.operators[index];
+        match setting {
+            "ratio" => Some(operator.ratio),
+            "level" => Some(operator.level),
+            "feedback" => Some(operator.feedback),
+            "attack" => Some(operator.envelope.attack_time),
+            "decay" => Some(operator.envelope.decay_time),
+            "sustain" => Some(operator.envelope.sustain_level),
+            "release" => Some(operator.envelope.release_time),
+            _ => None,
+        }
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) -> bool {
+        match name {
+            "frequency" => {
+                self.set_frequency(value);
+                return true;
+            }
+            "algorithm" => {
+                let index = value.round().max(0.0) as usize;
+                return match FmAlgorithm::from_index(index) {
+                    Some(algorithm) => {
+                        self.set_algorithm(algorithm);
+                        true
+                    }
+                    None => false,
+                };
+            }
+            "volume" => {
+                FmSynth::set_volume(self, value);
+                return true;
+            }
+            _ => {}
+        }
+
+        let Some((index, setting)) = parse_operator_parameter(name) else {
+            return false;
+        };
+        let mut operator = self.config.operators[index];
+        let envelope = operator.envelope;
+        match setting {
+            "ratio" => operator.ratio = value,
+            "level" => operator.level = value,
+            "feedback" => operator.feedback = value,
+            "attack" => operator.envelope = ADSRConfig::new(value, envelope.decay_time, envelope.sustain_level, envelope.release_time),
+            "decay" => operator.envelope = ADSRConfig::new(envelope.attack_time, value, envelope.sustain_level, envelope.release_time),
+            "sustain" => operator.envelope = ADSRConfig::new(envelope.attack_time, envelope.decay_time, value, envelope.release_time),
+            "release" => operator.envelope = ADSRConfig::new(envelope.attack_time, envelope.decay_time, envelope.sustain_level, value),
+            _ => return false,
+        }
+        // The constructor starts with linear segments, so keep the operator's curves
+        operator.envelope = operator.envelope.with_curves(envelope.attack_curve, envelope.decay_curve, envelope.release_curve);
+        // Re-apply the ranges enforced by the constructor
+        let operator = OperatorConfig::new(operator.ratio, operator.level, operator.feedback, operator.envelope);
+        self.set_operator(index, operator);
+        true
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -1,5 +1,10 @@
+use crate::envelope::{ADSRConfig, Envelope, EnvelopeCurve, RetriggerMode};
 use std::f32::consts::PI;
 
+/// Length of the decay segment in time constants; an exponential segment this long
+/// starts out falling as exp(-t / decay_time) and lands smoothly on silence
+const DECAY_TIME_CONSTANTS: f32 = 5.0;
+
 pub struct FMSnapSynthesizer {
     pub sample_rate: f32,
     pub attack_time: f32,
@@ -8,8 +13,13 @@ pub struct FMSnapSynthesizer {
     pub modulator_freq: f32,
     pub modulation_index: f32,
     pub phase: f32,
-    pub trigger_time: f32,
+    pub trigger_time: f64,
     pub is_active: bool,
+    envelope: Envelope,
+    /// Trigger time of the previous hit, still playing while a fade-out retrigger fades it
+    fading_trigger_time: f64,
+    /// The carrier phase restarts once `trigger_time` is reached
+    phase_reset_pending: bool,
 }
 
 impl FMSnapSynthesizer {
@@ -24,36 +34,69 @@ impl FMSnapSynthesizer {
             phase: 0.0,
             trigger_time: 0.0,
             is_active: false,
+            envelope: Envelope::new(),
+            fading_trigger_time: 0.0,
+            phase_reset_pending: false,
         }
     }
 
-    pub fn trigger(&mut self, time: f32) {
-        self.trigger_time = time;
-        self.phase = 0.0;
+    pub fn trigger(&mut self, time: f64) {
+        let was_active = self.is_active;
         self.is_active = true;
+        self.envelope.set_config(self.envelope_config());
+        self.envelope.trigger(time);
+        // A legato retrigger keeps the snap running where it is
+        if was_active && self.envelope.retrigger_mode == RetriggerMode::Legato {
+            return;
+        }
+
+        self.fading_trigger_time = self.trigger_time;
+        self.trigger_time = self.envelope.trigger_time;
+        // A fade-out retrigger starts the envelope late; keep the old phase until then
+        if self.trigger_time > time {
+            self.phase_reset_pending = true;
+            return;
+        }
+
+        self.phase_reset_pending = false;
+        self.phase = 0.0;
+    }
+
+    /// How a trigger while still sounding affects the envelope and phase
+    pub fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        self.envelope.retrigger_mode = mode;
+    }
+
+    /// Linear attack, then an exponential decay with `decay_time` as its time constant
+    fn envelope_config(&self) -> ADSRConfig {
+        ADSRConfig::new(self.attack_time, self.decay_time * DECAY_TIME_CONSTANTS, 0.0, 0.001).with_curves(
+            EnvelopeCurve::Linear,
+            EnvelopeCurve::Exponential,
+            EnvelopeCurve::Linear,
+        )
     }
 
-    pub fn tick(&mut self, current_time: f32) -> f32 {
+    pub fn tick(&mut self, current_time: f64) -> f32 {
         if !self.is_active {
             return 0.0;
         }
 
-        let t = current_time - self.trigger_time;
+        let t = if current_time < self.trigger_time {
+            (current_time - self.fading_trigger_time) as f32
+        } else {
+            if self.phase_reset_pending {
+                self.phase_reset_pending = false;
+                self.phase = 0.0;
+            }
+            (current_time - self.trigger_time) as f32
+        };
         
-        // Check if we're past the envelope duration
-        if t > self.attack_time + self.decay_time {
+        let env = self.envelope.get_amplitude(current_time);
+        if !self.envelope.is_active {
             self.is_active = false;
             return 0.0;
         }
 
-        // Generate envelope (exponential decay)
-        let env = if t < self.attack_time {
-            t / self.attack_time
-        } else {
-            let decay = (-(t - self.attack_time) / self.decay_time).exp();
-            decay.clamp(0.0, 1.0)
-        };
-
         // FM synthesis
         let dt = 1.0 / self.sample_rate;
         let mod_signal = (2.0 * PI * self.modulator_freq * t).sin();
@@ -1,16 +1,21 @@
-use crate::envelope::{ADSRConfig, Envelope};
+use crate::envelope::{ADSRConfig, Envelope, RetriggerMode};
 use crate::gen::oscillator::Oscillator;
 use crate::gen::waveform::Waveform;
+use crate::instruments::drum_filter::{DrumFilter, FilterConfig};
+use crate::instruments::instrument::{control_chunks, sample_time, Instrument, ParameterInfo, CONTROL_INTERVAL};
+use crate::stage::mix_into;
+use std::any::Any;
 
 #[derive(Clone, Copy, Debug)]
 pub struct HiHatConfig {
     pub base_frequency: f32,     // Base frequency for filtering (6000-12000Hz typical)
-    pub resonance: f32,          // Filter resonance (0.0-1.0)
+    pub resonance: f32,          // Filter resonance (0.0-1.0), kept equal to filter.resonance
     pub brightness: f32,         // High-frequency content (0.0-1.0)
     pub decay_time: f32,         // Decay length in seconds
     pub attack_time: f32,        // Attack time in seconds
     pub volume: f32,             // Overall volume (0.0-1.0)
     pub is_open: bool,           // true for open, false for closed
+    pub filter: FilterConfig,    // Filter and filter envelope, off unless set
 }
 
 impl HiHatConfig {
@@ -23,17 +28,26 @@ impl HiHatConfig {
         volume: f32,
         is_open: bool,
     ) -> Self {
+        let resonance = resonance.clamp(0.0, 1.0);
         Self {
             base_frequency: base_frequency.max(4000.0).min(16000.0), // Reasonable hi-hat range
-            resonance: resonance.clamp(0.0, 1.0),
+            resonance,
             brightness: brightness.clamp(0.0, 1.0),
             decay_time: decay_time.max(0.01).min(3.0), // Reasonable decay range
             attack_time: attack_time.max(0.001).min(0.1), // Quick attack for hi-hats
             volume: volume.clamp(0.0, 1.0),
             is_open,
+            filter: FilterConfig { resonance, ..FilterConfig::off() },
         }
     }
 
+    /// Set the filter; its resonance becomes the hat's `resonance`
+    pub fn with_filter(mut self, filter: FilterConfig) -> Self {
+        self.filter = filter;
+        self.resonance = filter.resonance;
+        self
+    }
+
     pub fn closed_default() -> Self {
         Self::new(8000.0, 0.7, 0.6, 0.1, 0.001, 0.8, false)
     }
@@ -59,6 +73,9 @@ impl HiHatConfig {
     }
 }
 
+/// Seed for the noise sources until `set_seed` is called
+const DEFAULT_SEED: u64 = 0x4849_4854; // "HIHT"
+
 pub struct HiHat {
     pub sample_rate: f32,
     pub config: HiHatConfig,
@@ -70,6 +87,8 @@ pub struct HiHat {
     // Amplitude envelope
     pub amplitude_envelope: Envelope,
 
+    pub filter: DrumFilter,
+
     pub is_active: bool,
 }
 
@@ -96,15 +115,20 @@ impl HiHat {
             noise_oscillator: Oscillator::new(sample_rate, config.base_frequency),
             brightness_oscillator: Oscillator::new(sample_rate, config.base_frequency * 2.0),
             amplitude_envelope: Envelope::new(),
+            filter: DrumFilter::new(sample_rate, config.filter),
             is_active: false,
         };
 
         hihat.configure_oscillators();
+        hihat.set_seed(DEFAULT_SEED);
+        // An open hat retriggered while still ringing would otherwise click
+        hihat.set_retrigger_mode(RetriggerMode::FadeOut);
         hihat
     }
 
     fn configure_oscillators(&mut self) {
         let config = self.config;
+        self.filter.set_config(config.filter);
 
         // Main noise oscillator
         self.noise_oscillator.waveform = Waveform::Noise;
@@ -119,7 +143,7 @@ impl HiHat {
                 config.decay_time * 0.3, // Medium decay
                 0.3,                    // Some sustain for open sound
                 config.decay_time * 0.7, // Longer release
-            ));
+            ).percussive());
         } else {
             // Closed hi-hat: very short decay, no sustain
             self.noise_oscillator.set_adsr(ADSRConfig::new(
@@ -127,7 +151,7 @@ impl HiHat {
                 config.decay_time * 0.8, // Most of the decay
                 0.0,                    // No sustain for closed sound
                 config.decay_time * 0.2, // Short release
-            ));
+            ).percussive());
         }
 
         // Brightness oscillator for high-frequency emphasis
@@ -141,7 +165,7 @@ impl HiHat {
             config.decay_time * 0.3, // Shorter decay for brightness
             0.0,                    // No sustain
             config.decay_time * 0.1, // Very short release
-        ));
+        ).percussive());
 
         // Amplitude envelope for overall shaping
         if config.is_open {
@@ -150,23 +174,36 @@ impl HiHat {
                 config.decay_time * 0.4, // Medium decay
                 0.2,                    // Low sustain
                 config.decay_time * 0.6, // Longer release for open sound
-            ));
+            ).percussive());
         } else {
             self.amplitude_envelope.set_config(ADSRConfig::new(
                 config.attack_time,     // Quick attack
                 config.decay_time * 0.9, // Most of the decay
                 0.0,                    // No sustain for closed sound
                 config.decay_time * 0.1, // Very short release
-            ));
+            ).percussive());
         }
     }
 
+    /// Restart the noise sources from a seed; the same seed always gives the same hits
+    pub fn set_seed(&mut self, seed: u64) {
+        self.noise_oscillator.set_seed(seed);
+        self.brightness_oscillator.set_seed(seed.wrapping_add(1));
+    }
+
     pub fn set_config(&mut self, config: HiHatConfig) {
         self.config = config;
         self.configure_oscillators();
     }
 
-    pub fn trigger(&mut self, time: f32) {
+    pub fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        self.noise_oscillator.set_retrigger_mode(mode);
+        self.brightness_oscillator.set_retrigger_mode(mode);
+        self.amplitude_envelope.retrigger_mode = mode;
+        self.filter.set_retrigger_mode(mode);
+    }
+
+    pub fn trigger(&mut self, time: f64) {
         self.is_active = true;
 
         // Trigger all oscillators
@@ -175,9 +212,10 @@ impl HiHat {
 
         // Trigger amplitude envelope
         self.amplitude_envelope.trigger(time);
+        self.filter.trigger(time);
     }
 
-    pub fn release(&mut self, time: f32) {
+    pub fn release(&mut self, time: f64) {
         if self.is_active {
             self.noise_oscillator.release(time);
             self.brightness_oscillator.release(time);
@@ -185,7 +223,16 @@ impl HiHat {
         }
     }
 
-    pub fn tick(&mut self, current_time: f32) -> f32 {
+    fn update_active(&mut self) {
+        if !self.noise_oscillator.envelope.is_active
+            && !self.brightness_oscillator.envelope.is_active
+            && !self.amplitude_envelope.is_active
+        {
+            self.is_active = false;
+        }
+    }
+
+    pub fn tick(&mut self, current_time: f64) -> f32 {
         if !self.is_active {
             return 0.0;
         }
@@ -201,19 +248,35 @@ impl HiHat {
         let amplitude = self.amplitude_envelope.get_amplitude(current_time);
         let final_output = combined_output * amplitude;
 
-        // Apply simple resonance simulation by emphasizing certain frequencies
-        let resonance_factor = 1.0 + self.config.resonance * 0.5;
-        let resonant_output = final_output * resonance_factor;
+        let filtered_output = self.filter.process(final_output, current_time);
 
-        // Check if hi-hat is still active
-        if !self.noise_oscillator.envelope.is_active
-            && !self.brightness_oscillator.envelope.is_active
-            && !self.amplitude_envelope.is_active
-        {
-            self.is_active = false;
-        }
+        self.update_active();
+        filtered_output
+    }
 
-        resonant_output
+    /// Render a block starting at `start_time`, following the filter envelope once per control chunk
+    pub fn process_block(&mut self, output: &mut [f32], start_time: f64) {
+        output.fill(0.0);
+        let mut scratch = [0.0; CONTROL_INTERVAL];
+        for (range, chunk_time, control_time) in control_chunks(output.len(), start_time, self.sample_rate) {
+            if !self.is_active {
+                break;
+            }
+            let chunk = &mut output[range];
+            let scratch = &mut scratch[..chunk.len()];
+            self.noise_oscillator.process_block(chunk, chunk_time);
+            self.brightness_oscillator.process_block(scratch, chunk_time);
+            mix_into(chunk, scratch, 1.0);
+
+            // The amplitude envelope stays sample accurate for the attack
+            for (index, sample) in chunk.iter_mut().enumerate() {
+                let time = sample_time(chunk_time, index, self.sample_rate);
+                *sample *= self.amplitude_envelope.get_amplitude(time);
+            }
+            self.filter.process_block(chunk, control_time);
+
+            self.update_active();
+        }
     }
 
     pub fn is_active(&self) -> bool {
@@ -240,8 +303,10 @@ impl HiHat {
         self.configure_oscillators();
     }
 
+    /// Set the filter resonance, the same control as `filter_resonance`
     pub fn set_resonance(&mut self, resonance: f32) {
         self.config.resonance = resonance.clamp(0.0, 1.0);
+        self.config.filter.resonance = self.config.resonance;
         self.configure_oscillators();
     }
 
@@ -254,4 +319,145 @@ impl HiHat {
         self.config.is_open = is_open;
         self.configure_oscillators();
     }
-}
\ No newline at end of file
+
+    pub fn set_filter(&mut self, filter: FilterConfig) {
+        self.config.filter = filter;
+        self.config.resonance = filter.resonance;
+        self.filter.set_config(filter);
+    }
+}
+
+const HIHAT_PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo::new("frequency", 4000.0, 16000.0, 8000.0),
+    ParameterInfo::new("resonance", 0.0, 1.0, 0.7),
+    ParameterInfo::new("brightness", 0.0, 1.0, 0.6),
+    ParameterInfo::new("decay", 0.01, 3.0, 0.1),
+    ParameterInfo::new("attack", 0.001, 0.1, 0.001),
+    ParameterInfo::new("volume", 0.0, 1.0, 0.8),
+    ParameterInfo::new("open", 0.0, 1.0, 0.0),
+    ParameterInfo::new("filter_cutoff", 20.0, 20000.0, 20000.0),
+    ParameterInfo::new("filter_resonance", 0.0, 1.0, 0.7),
+    ParameterInfo::new("filter_env_amount", -1.0, 1.0, 0.0),
+    ParameterInfo::new("filter_env_decay", 0.005, 2.0, 0.1),
+];
+
+impl Instrument for HiHat {
+    fn trigger(&mut self, time: f64) {
+        HiHat::trigger(self, time);
+    }
+
+    fn release(&mut self, time: f64) {
+        HiHat::release(self, time);
+    }
+
+    fn tick(&mut self, current_time: f64) -> f32 {
+        HiHat::tick(self, current_time)
+    }
+
+    fn process_block(&mut self, output: &mut [f32], start_time: f64) {
+        HiHat::process_block(self, output, start_time);
+    }
+
+    fn is_active(&self) -> bool {
+        self.is_active
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        HiHat::set_volume(self, volume);
+    }
+
+    fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        HiHat::set_retrigger_mode(self, mode);
+    }
+
+    fn retrigger_mode(&self) -> RetriggerMode {
+        self.amplitude_envelope.retrigger_mode
+    }
+
+    fn parameters(&self) -> &'static [ParameterInfo] {
+        HIHAT_PARAMETERS
+    }
+
+    fn get_parameter(&self, name: &str) -> Option<f32> {
+        match name {
+            "frequency" => Some(self.config.base_frequency),
+            "resonance" => Some(self.config.resonance),
+            "brightness" => Some(self.config.brightness),
+            "decay" => Some(self.config.decay_time),
+            "attack" => Some(self.config.attack_time),
+            "volume" => Some(self.config.volume),
+            "open" => Some(if self.config.is_open { 1.0 } else { 0.0 }),
+            _ => self.config.filter.get_parameter(name),
+        }
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) -> bool {
+        match name {
+            "frequency" => self.set_frequency(value),
+            "resonance" => self.set_resonance(value),
+            "brightness" => self.set_brightness(value),
+            "decay" => self.set_decay(value),
+            "attack" => self.set_attack(value),
+            "volume" => self.set_volume(value),
+            "open" => self.set_open(value >= 0.5),
+            _ => {
+                let mut filter = self.config.filter;
+                if !filter.set_parameter(name, value) {
+                    return false;
+                }
+                self.set_filter(filter);
+            }
+        }
+        true
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        HiHat::set_seed(self, seed);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::FilterType;
+
+    #[test]
+    fn resonance_is_the_filter_resonance() {
+        let mut hihat = HiHat::new(44100.0);
+        assert_eq!(hihat.config.filter.resonance, hihat.config.resonance);
+
+        hihat.set_parameter("resonance", 0.3);
+        assert_eq!(hihat.get_parameter("filter_resonance"), Some(0.3));
+        hihat.set_parameter("filter_resonance", 0.9);
+        assert_eq!(hihat.get_parameter("resonance"), Some(0.9));
+
+        let config = HiHatConfig::closed_default()
+            .with_filter(FilterConfig::new(FilterType::LowPass, 6000.0, 0.2, 0.0, 0.1));
+        assert_eq!(config.resonance, 0.2);
+    }
+
+    #[test]
+    fn resonance_does_nothing_with_the_filter_off() {
+        let render = |resonance: f32| {
+            let mut hihat = HiHat::new(44100.0);
+            hihat.set_resonance(resonance);
+            hihat.trigger(0.0);
+            let mut output = vec![0.0; 2048];
+            hihat.process_block(&mut output, 0.0);
+            output
+        };
+        assert_eq!(render(0.0), render(1.0));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::envelope::RetriggerMode;
+use crate::instruments::voice_pool::VoiceStealing;
+use std::any::Any;
+use std::ops::Range;
+
+/// Samples between updates of a drum's pitch and filter envelopes in `process_block`
+/// Kept short because the time-based oscillators jump in phase when their frequency steps.
+pub const CONTROL_INTERVAL: usize = 8;
+
+/// Describes one parameter an instrument exposes for generic control
+#[derive(Debug, Clone, Copy)]
+pub struct ParameterInfo {
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+}
+
+impl ParameterInfo {
+    pub const fn new(name: &'static str, min: f32, max: f32, default: f32) -> Self {
+        Self { name, min, max, default }
+    }
+}
+
+/// Time of the sample `index` samples after `start_time`
+/// Counted in whole samples from the block start, so the result doesn't depend on how a render is
+/// split into blocks.
+pub fn sample_time(start_time: f64, index: usize, sample_rate: f32) -> f64 {
+    let sample_rate = sample_rate as f64;
+    ((start_time * sample_rate).round() + index as f64) / sample_rate
+}
+
+/// Split a block of `len` samples from `start_time` into chunks of at most `CONTROL_INTERVAL`
+/// Chunks end on whole multiples of the interval in absolute sample positions. Yields each chunk's
+/// range in the block, its start time and the time to read control envelopes at: the end of the
+/// chunk's interval, which is the same however a render is split into blocks and never comes
+/// before a trigger at the start of the chunk.
+pub fn control_chunks(len: usize, start_time: f64, sample_rate: f32) -> impl Iterator<Item = (Range<usize>, f64, f64)> {
+    let sample_rate = sample_rate as f64;
+    let start = (start_time * sample_rate).round() as u64;
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        if offset >= len {
+            return None;
+        }
+        let position = start + offset as u64;
+        let interval_end = (position / CONTROL_INTERVAL as u64 + 1) * CONTROL_INTERVAL as u64;
+        let chunk_len = ((interval_end - position) as usize).min(len - offset);
+        let range = offset..offset + chunk_len;
+        offset += chunk_len;
+        Some((range, position as f64 / sample_rate, interval_end as f64 / sample_rate))
+    })
+}
+
+/// Common interface for anything the Stage and sequencer can play
+/// Times are absolute engine times in seconds, derived from the Stage's sample clock.
+pub trait Instrument: Send {
+    fn trigger(&mut self, time: f64);
+
+    fn release(&mut self, time: f64);
+
+    fn tick(&mut self, current_time: f64) -> f32;
+
+    fn is_active(&self) -> bool;
+
+    fn set_volume(&mut self, volume: f32);
+
+    fn sample_rate(&self) -> f32;
+
+    /// Choose what a trigger does while the previous hit is still sounding
+    fn set_retrigger_mode(&mut self, _mode: RetriggerMode) {}
+
+    fn retrigger_mode(&self) -> RetriggerMode {
+        RetriggerMode::Hard
+    }
+
+    /// Number of hits that can ring at once; single-voice instruments ignore this
+    fn set_polyphony(&mut self, _voices: usize) {}
+
+    fn polyphony(&self) -> usize {
+        1
+    }
+
+    /// Which voice to reuse when every voice is sounding; single-voice instruments ignore this
+    fn set_voice_stealing(&mut self, _stealing: VoiceStealing) {}
+
+    fn voice_stealing(&self) -> VoiceStealing {
+        VoiceStealing::Oldest
+    }
+
+    /// Render a block of consecutive samples starting at `start_time`
+    fn process_block(&mut self, output: &mut [f32], start_time: f64) {
+        let sample_rate = self.sample_rate();
+        let mut index = 0;
+        while index < output.len() && self.is_active() {
+            output[index] = self.tick(sample_time(start_time, index, sample_rate));
+            index += 1;
+        }
+        // Nothing more to render once the instrument has finished
+        output[index..].fill(0.0);
+    }
+
+    /// Parameters that can be read and written by name
+    fn parameters(&self) -> &'static [ParameterInfo];
+
+    /// Current value of a named parameter, or None if it does not exist
+    fn get_parameter(&self, name: &str) -> Option<f32>;
+
+    /// Set a named parameter, returning false if it does not exist
+    fn set_parameter(&mut self, name: &str, value: f32) -> bool;
+
+    /// Restart any random sources from a seed so renders are reproducible
+    fn set_seed(&mut self, _seed: u64) {}
+
+    /// Access the concrete instrument, e.g. to reach its typed config
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    #[test]
+    fn control_chunks_end_on_the_absolute_grid() {
+        // A block starting 3 samples past a grid line first runs up to the next one
+        let start = 3.0 / SAMPLE_RATE as f64;
+        let chunks: Vec<_> = control_chunks(20, start, SAMPLE_RATE).collect();
+        let ranges: Vec<_> = chunks.iter().map(|(range, _, _)| range.clone()).collect();
+        assert_eq!(ranges, vec![0..5, 5..13, 13..20]);
+        assert_eq!(chunks[1].1, sample_time(start, 5, SAMPLE_RATE));
+        assert_eq!(chunks[0].2, chunks[1].1);
+        assert_eq!(chunks[1].2, chunks[2].1);
+    }
+
+    #[test]
+    fn drum_blocks_do_not_depend_on_block_size() {
+        let render = |block: usize| {
+            let mut kick = crate::instruments::KickDrum::new(SAMPLE_RATE);
+            kick.trigger(0.0);
+            let mut output = vec![0.0; 9000];
+            for (index, chunk) in output.chunks_mut(block).enumerate() {
+                kick.process_block(chunk, sample_time(0.0, index * block, SAMPLE_RATE));
+            }
+            output
+        };
+        let reference = render(256);
+        for block in [1, 7, 64, 1000] {
+            assert!(render(block) == reference, "{block}-sample blocks differ");
+        }
+    }
+}
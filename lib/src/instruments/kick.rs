@@ -1,8 +1,11 @@
-use crate::envelope::{ADSRConfig, Envelope};
-use crate::filters::ResonantHighpassFilter;
+use crate::envelope::{ADSRConfig, BreakpointEnvelope, EnvelopeSegment, RetriggerMode};
+use crate::filters::{Biquad, BiquadType, MonoFilter};
 use crate::instruments::fm_snap::FMSnapSynthesizer;
 use crate::gen::oscillator::Oscillator;
 use crate::gen::waveform::Waveform;
+use crate::instruments::instrument::{control_chunks, sample_time, Instrument, ParameterInfo, CONTROL_INTERVAL};
+use crate::stage::mix_into;
+use std::any::Any;
 
 #[derive(Clone, Copy, Debug)]
 pub struct KickConfig {
@@ -53,6 +56,12 @@ impl KickConfig {
     }
 }
 
+/// Seed for the noise sources until `set_seed` is called
+const DEFAULT_SEED: u64 = 0x4B49_434B; // "KICK"
+
+/// Resonance of the click's high-pass; a little above flat so the click has some ring at 8 kHz
+const CLICK_FILTER_Q: f32 = 1.2;
+
 pub struct KickDrum {
     pub sample_rate: f32,
     pub config: KickConfig,
@@ -63,12 +72,12 @@ pub struct KickDrum {
     pub click_oscillator: Oscillator, // High-frequency click
 
     // Pitch envelope for frequency sweeping
-    pub pitch_envelope: Envelope,
+    pub pitch_envelope: BreakpointEnvelope,
     pub base_frequency: f32,
     pub pitch_start_multiplier: f32,
 
     // High-pass filter for click oscillator
-    pub click_filter: ResonantHighpassFilter,
+    pub click_filter: Biquad,
 
     // FM snap synthesizer for beater sound
     pub fm_snap: FMSnapSynthesizer,
@@ -89,15 +98,16 @@ impl KickDrum {
             sub_oscillator: Oscillator::new(sample_rate, config.kick_frequency),
             punch_oscillator: Oscillator::new(sample_rate, config.kick_frequency * 2.5),
             click_oscillator: Oscillator::new(sample_rate, config.kick_frequency * 40.0),
-            pitch_envelope: Envelope::new(),
+            pitch_envelope: BreakpointEnvelope::new(&[]),
             base_frequency: config.kick_frequency,
             pitch_start_multiplier: 1.0 + config.pitch_drop * 2.0, // Start 1-3x higher
-            click_filter: ResonantHighpassFilter::new(sample_rate, 8000.0, 4.0),
+            click_filter: Biquad::new(sample_rate, BiquadType::HighPass, 8000.0, CLICK_FILTER_Q),
             fm_snap: FMSnapSynthesizer::new(sample_rate),
             is_active: false,
         };
 
         kick.configure_oscillators();
+        kick.set_seed(DEFAULT_SEED);
         kick
     }
 
@@ -114,7 +124,7 @@ impl KickDrum {
             config.decay_time,       // Synchronized decay time
             0.0,                     // No sustain
             config.decay_time * 0.2, // Synchronized release
-        ));
+        ).percussive());
 
         // Punch oscillator: Sine or triangle for mid-range impact
         self.punch_oscillator.waveform = Waveform::Triangle;
@@ -126,7 +136,7 @@ impl KickDrum {
             config.decay_time,       // Synchronized decay time
             0.0,                     // No sustain
             config.decay_time * 0.2, // Synchronized release
-        ));
+        ).percussive());
 
         // Click oscillator: High-frequency filtered noise transient
         self.click_oscillator.waveform = Waveform::Noise;
@@ -138,15 +148,19 @@ impl KickDrum {
             config.decay_time * 0.2,  // Much shorter decay time for click
             0.0,                      // No sustain
             config.decay_time * 0.02, // Extremely short release for click
-        ));
+        ).percussive());
+
+        // Pitch envelope: 808-style sweep, a fast initial drop then a long glide down to the base frequency
+        self.pitch_envelope.set_segments(&[
+            EnvelopeSegment::linear(0.001, 1.0),                       // Instant attack
+            EnvelopeSegment::exponential(config.decay_time * 0.1, 0.3), // Fast initial drop
+            EnvelopeSegment::exponential(config.decay_time * 0.9, 0.0), // Long tail, synchronized with the decay
+        ]);
+    }
 
-        // Pitch envelope: Fast attack, synchronized decay for frequency sweeping
-        self.pitch_envelope.set_config(ADSRConfig::new(
-            0.001,                   // Instant attack
-            config.decay_time,       // Synchronized decay time
-            0.0,                     // Drop to base frequency
-            config.decay_time * 0.2, // Synchronized release
-        ));
+    /// Restart the noise sources from a seed; the same seed always gives the same hits
+    pub fn set_seed(&mut self, seed: u64) {
+        self.click_oscillator.set_seed(seed);
     }
 
     pub fn set_config(&mut self, config: KickConfig) {
@@ -156,7 +170,15 @@ impl KickDrum {
         self.configure_oscillators();
     }
 
-    pub fn trigger(&mut self, time: f32) {
+    pub fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        self.sub_oscillator.set_retrigger_mode(mode);
+        self.punch_oscillator.set_retrigger_mode(mode);
+        self.click_oscillator.set_retrigger_mode(mode);
+        self.pitch_envelope.retrigger_mode = mode;
+        self.fm_snap.set_retrigger_mode(mode);
+    }
+
+    pub fn trigger(&mut self, time: f64) {
         self.is_active = true;
 
         // Trigger all oscillators
@@ -174,7 +196,7 @@ impl KickDrum {
         self.click_filter.reset();
     }
 
-    pub fn release(&mut self, time: f32) {
+    pub fn release(&mut self, time: f64) {
         if self.is_active {
             self.sub_oscillator.release(time);
             self.punch_oscillator.release(time);
@@ -183,12 +205,8 @@ impl KickDrum {
         }
     }
 
-    pub fn tick(&mut self, current_time: f32) -> f32 {
-        if !self.is_active {
-            return 0.0;
-        }
-
-        // Calculate pitch modulation
+    /// Set the oscillator frequencies from the pitch envelope at `current_time`
+    fn update_pitch(&mut self, current_time: f64) {
         let pitch_envelope_value = self.pitch_envelope.get_amplitude(current_time);
         let frequency_multiplier = 1.0 + (self.pitch_start_multiplier - 1.0) * pitch_envelope_value;
 
@@ -199,6 +217,24 @@ impl KickDrum {
         // Click oscillator gets less pitch modulation to maintain transient character
         let click_pitch_mod = 1.0 + (frequency_multiplier - 1.0) * 0.3;
         self.click_oscillator.frequency_hz = self.base_frequency * 40.0 * click_pitch_mod;
+    }
+
+    fn update_active(&mut self) {
+        if !self.sub_oscillator.envelope.is_active
+            && !self.punch_oscillator.envelope.is_active
+            && !self.click_oscillator.envelope.is_active
+            && !self.fm_snap.is_active()
+        {
+            self.is_active = false;
+        }
+    }
+
+    pub fn tick(&mut self, current_time: f64) -> f32 {
+        if !self.is_active {
+            return 0.0;
+        }
+
+        self.update_pitch(current_time);
 
         // Sum all oscillator outputs
         let sub_output = self.sub_oscillator.tick(current_time);
@@ -213,18 +249,38 @@ impl KickDrum {
 
         let total_output = sub_output + punch_output + filtered_click_output + (fm_snap_output * self.config.volume);
 
-        // Check if kick is still active
-        if !self.sub_oscillator.envelope.is_active
-            && !self.punch_oscillator.envelope.is_active
-            && !self.click_oscillator.envelope.is_active
-            && !self.fm_snap.is_active()
-        {
-            self.is_active = false;
-        }
-
+        self.update_active();
         total_output
     }
 
+    /// Render a block starting at `start_time`, following the pitch envelope once per control chunk
+    pub fn process_block(&mut self, output: &mut [f32], start_time: f64) {
+        output.fill(0.0);
+        let mut scratch = [0.0; CONTROL_INTERVAL];
+        for (range, chunk_time, control_time) in control_chunks(output.len(), start_time, self.sample_rate) {
+            if !self.is_active {
+                break;
+            }
+            self.update_pitch(control_time);
+
+            let chunk = &mut output[range];
+            let scratch = &mut scratch[..chunk.len()];
+            self.sub_oscillator.process_block(chunk, chunk_time);
+            self.punch_oscillator.process_block(scratch, chunk_time);
+            mix_into(chunk, scratch, 1.0);
+            self.click_oscillator.process_block(scratch, chunk_time);
+            self.click_filter.process_block(scratch);
+            mix_into(chunk, scratch, 1.0);
+
+            for (index, sample) in chunk.iter_mut().enumerate() {
+                let time = sample_time(chunk_time, index, self.sample_rate);
+                *sample += self.fm_snap.tick(time) * self.config.volume;
+            }
+
+            self.update_active();
+        }
+    }
+
     pub fn is_active(&self) -> bool {
         self.is_active
     }
@@ -264,4 +320,95 @@ impl KickDrum {
         self.config.pitch_drop = pitch_drop.clamp(0.0, 1.0);
         self.pitch_start_multiplier = 1.0 + self.config.pitch_drop * 2.0;
     }
-}
\ No newline at end of file
+}
+
+const KICK_PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo::new("frequency", 20.0, 200.0, 30.0),
+    ParameterInfo::new("punch", 0.0, 1.0, 0.8),
+    ParameterInfo::new("sub", 0.0, 1.0, 0.8),
+    ParameterInfo::new("click", 0.0, 1.0, 0.2),
+    ParameterInfo::new("decay", 0.01, 5.0, 0.28),
+    ParameterInfo::new("pitch_drop", 0.0, 1.0, 0.2),
+    ParameterInfo::new("volume", 0.0, 1.0, 0.8),
+];
+
+impl Instrument for KickDrum {
+    fn trigger(&mut self, time: f64) {
+        KickDrum::trigger(self, time);
+    }
+
+    fn release(&mut self, time: f64) {
+        KickDrum::release(self, time);
+    }
+
+    fn tick(&mut self, current_time: f64) -> f32 {
+        KickDrum::tick(self, current_time)
+    }
+
+    fn process_block(&mut self, output: &mut [f32], start_time: f64) {
+        KickDrum::process_block(self, output, start_time);
+    }
+
+    fn is_active(&self) -> bool {
+        self.is_active
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        KickDrum::set_volume(self, volume);
+    }
+
+    fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        KickDrum::set_retrigger_mode(self, mode);
+    }
+
+    fn retrigger_mode(&self) -> RetriggerMode {
+        self.sub_oscillator.envelope.retrigger_mode
+    }
+
+    fn parameters(&self) -> &'static [ParameterInfo] {
+        KICK_PARAMETERS
+    }
+
+    fn get_parameter(&self, name: &str) -> Option<f32> {
+        match name {
+            "frequency" => Some(self.config.kick_frequency),
+            "punch" => Some(self.config.punch_amount),
+            "sub" => Some(self.config.sub_amount),
+            "click" => Some(self.config.click_amount),
+            "decay" => Some(self.config.decay_time),
+            "pitch_drop" => Some(self.config.pitch_drop),
+            "volume" => Some(self.config.volume),
+            _ => None,
+        }
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) -> bool {
+        match name {
+            "frequency" => self.set_frequency(value),
+            "punch" => self.set_punch(value),
+            "sub" => self.set_sub(value),
+            "click" => self.set_click(value),
+            "decay" => self.set_decay(value),
+            "pitch_drop" => self.set_pitch_drop(value),
+            "volume" => self.set_volume(value),
+            _ => return false,
+        }
+        true
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        KickDrum::set_seed(self, seed);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
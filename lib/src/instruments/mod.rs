@@ -3,9 +3,17 @@ pub mod snare;
 pub mod hihat;
 pub mod tom;
 pub mod fm_snap;
+pub mod fm;
+pub mod drum_filter;
+pub mod instrument;
+pub mod voice_pool;
 
 pub use self::kick::*;
 pub use self::snare::*;
 pub use self::hihat::*;
 pub use self::tom::*;
-pub use self::fm_snap::*;
\ No newline at end of file
+pub use self::fm_snap::*;
+pub use self::fm::*;
+pub use self::drum_filter::*;
+pub use self::instrument::*;
+pub use self::voice_pool::*;
\ No newline at end of file
@@ -1,6 +1,10 @@
-use crate::envelope::{ADSRConfig, Envelope};
+use crate::envelope::{ADSRConfig, Envelope, RetriggerMode};
 use crate::gen::oscillator::Oscillator;
 use crate::gen::waveform::Waveform;
+use crate::instruments::drum_filter::{DrumFilter, FilterConfig};
+use crate::instruments::instrument::{control_chunks, Instrument, ParameterInfo, CONTROL_INTERVAL};
+use crate::stage::mix_into;
+use std::any::Any;
 
 #[derive(Clone, Copy, Debug)]
 pub struct SnareConfig {
@@ -11,6 +15,7 @@ pub struct SnareConfig {
     pub decay_time: f32,      // Overall decay length in seconds
     pub pitch_drop: f32,      // Frequency sweep amount (0.0-1.0)
     pub volume: f32,          // Overall volume (0.0-1.0)
+    pub filter: FilterConfig, // Filter and filter envelope, off unless set
 }
 
 impl SnareConfig {
@@ -31,9 +36,15 @@ impl SnareConfig {
             decay_time: decay_time.max(0.01).min(2.0), // Reasonable decay range for snare
             pitch_drop: pitch_drop.clamp(0.0, 1.0),
             volume: volume.clamp(0.0, 1.0),
+            filter: FilterConfig::off(),
         }
     }
 
+    pub fn with_filter(mut self, filter: FilterConfig) -> Self {
+        self.filter = filter;
+        self
+    }
+
     pub fn default() -> Self {
         Self::new(200.0, 0.4, 0.7, 0.5, 0.15, 0.3, 0.8)
     }
@@ -55,6 +66,9 @@ impl SnareConfig {
     }
 }
 
+/// Seed for the noise sources until `set_seed` is called
+const DEFAULT_SEED: u64 = 0x534E_4152; // "SNAR"
+
 pub struct SnareDrum {
     pub sample_rate: f32,
     pub config: SnareConfig,
@@ -69,6 +83,8 @@ pub struct SnareDrum {
     pub base_frequency: f32,
     pub pitch_start_multiplier: f32,
 
+    pub filter: DrumFilter,
+
     pub is_active: bool,
 }
 
@@ -88,15 +104,18 @@ impl SnareDrum {
             pitch_envelope: Envelope::new(),
             base_frequency: config.snare_frequency,
             pitch_start_multiplier: 1.0 + config.pitch_drop * 1.5, // Start 1-2.5x higher
+            filter: DrumFilter::new(sample_rate, config.filter),
             is_active: false,
         };
 
         snare.configure_oscillators();
+        snare.set_seed(DEFAULT_SEED);
         snare
     }
 
     fn configure_oscillators(&mut self) {
         let config = self.config;
+        self.filter.set_config(config.filter);
 
         // Tonal oscillator: Triangle wave for body
         self.tonal_oscillator.waveform = Waveform::Triangle;
@@ -108,7 +127,7 @@ impl SnareDrum {
             config.decay_time * 0.8, // Main decay
             0.0,                     // No sustain - drums should decay to silence
             config.decay_time * 0.4, // Medium release
-        ));
+        ).percussive());
 
         // Noise oscillator: Main snare noise character
         self.noise_oscillator.waveform = Waveform::Noise;
@@ -120,7 +139,7 @@ impl SnareDrum {
             config.decay_time * 0.6, // Shorter decay for noise
             0.0,                     // No sustain - drums should decay to silence
             config.decay_time * 0.3, // Quick release
-        ));
+        ).percussive());
 
         // Crack oscillator: High-frequency transient
         self.crack_oscillator.waveform = Waveform::Noise;
@@ -132,7 +151,7 @@ impl SnareDrum {
             config.decay_time * 0.2,  // Very short decay for crack
             0.0,                      // No sustain
             config.decay_time * 0.1,  // Very short release
-        ));
+        ).percussive());
 
         // Pitch envelope: Fast attack, quick decay for frequency sweeping
         self.pitch_envelope.set_config(ADSRConfig::new(
@@ -140,7 +159,13 @@ impl SnareDrum {
             config.decay_time * 0.3, // Quick pitch drop
             0.0,                     // Drop to base frequency
             config.decay_time * 0.1, // Quick release
-        ));
+        ).percussive());
+    }
+
+    /// Restart the noise sources from a seed; the same seed always gives the same hits
+    pub fn set_seed(&mut self, seed: u64) {
+        self.noise_oscillator.set_seed(seed);
+        self.crack_oscillator.set_seed(seed.wrapping_add(1));
     }
 
     pub fn set_config(&mut self, config: SnareConfig) {
@@ -150,7 +175,15 @@ impl SnareDrum {
         self.configure_oscillators();
     }
 
-    pub fn trigger(&mut self, time: f32) {
+    pub fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        self.tonal_oscillator.set_retrigger_mode(mode);
+        self.noise_oscillator.set_retrigger_mode(mode);
+        self.crack_oscillator.set_retrigger_mode(mode);
+        self.pitch_envelope.retrigger_mode = mode;
+        self.filter.set_retrigger_mode(mode);
+    }
+
+    pub fn trigger(&mut self, time: f64) {
         self.is_active = true;
 
         // Trigger all oscillators
@@ -160,9 +193,10 @@ impl SnareDrum {
 
         // Trigger pitch envelope
         self.pitch_envelope.trigger(time);
+        self.filter.trigger(time);
     }
 
-    pub fn release(&mut self, time: f32) {
+    pub fn release(&mut self, time: f64) {
         if self.is_active {
             self.tonal_oscillator.release(time);
             self.noise_oscillator.release(time);
@@ -171,12 +205,8 @@ impl SnareDrum {
         }
     }
 
-    pub fn tick(&mut self, current_time: f32) -> f32 {
-        if !self.is_active {
-            return 0.0;
-        }
-
-        // Calculate pitch modulation
+    /// Set the tonal oscillator's frequency from the pitch envelope at `current_time`
+    fn update_pitch(&mut self, current_time: f64) {
         let pitch_envelope_value = self.pitch_envelope.get_amplitude(current_time);
         let frequency_multiplier = 1.0 + (self.pitch_start_multiplier - 1.0) * pitch_envelope_value;
 
@@ -184,25 +214,59 @@ impl SnareDrum {
         self.tonal_oscillator.frequency_hz = self.base_frequency * frequency_multiplier;
 
         // Noise components don't get pitch modulation to maintain their character
+    }
 
-        // Sum all oscillator outputs
-        let tonal_output = self.tonal_oscillator.tick(current_time);
-        let noise_output = self.noise_oscillator.tick(current_time);
-        let crack_output = self.crack_oscillator.tick(current_time);
-
-        let total_output = tonal_output + noise_output + crack_output;
-
-        // Check if snare is still active
+    fn update_active(&mut self) {
         if !self.tonal_oscillator.envelope.is_active
             && !self.noise_oscillator.envelope.is_active
             && !self.crack_oscillator.envelope.is_active
         {
             self.is_active = false;
         }
+    }
+
+    pub fn tick(&mut self, current_time: f64) -> f32 {
+        if !self.is_active {
+            return 0.0;
+        }
+
+        self.update_pitch(current_time);
+
+        // Sum all oscillator outputs
+        let tonal_output = self.tonal_oscillator.tick(current_time);
+        let noise_output = self.noise_oscillator.tick(current_time);
+        let crack_output = self.crack_oscillator.tick(current_time);
 
+        let total_output = self.filter.process(tonal_output + noise_output + crack_output, current_time);
+
+        self.update_active();
         total_output
     }
 
+    /// Render a block starting at `start_time`, following the pitch and filter envelopes once per
+    /// control chunk
+    pub fn process_block(&mut self, output: &mut [f32], start_time: f64) {
+        output.fill(0.0);
+        let mut scratch = [0.0; CONTROL_INTERVAL];
+        for (range, chunk_time, control_time) in control_chunks(output.len(), start_time, self.sample_rate) {
+            if !self.is_active {
+                break;
+            }
+            self.update_pitch(control_time);
+
+            let chunk = &mut output[range];
+            let scratch = &mut scratch[..chunk.len()];
+            self.tonal_oscillator.process_block(chunk, chunk_time);
+            self.noise_oscillator.process_block(scratch, chunk_time);
+            mix_into(chunk, scratch, 1.0);
+            self.crack_oscillator.process_block(scratch, chunk_time);
+            mix_into(chunk, scratch, 1.0);
+            self.filter.process_block(chunk, control_time);
+
+            self.update_active();
+        }
+    }
+
     pub fn is_active(&self) -> bool {
         self.is_active
     }
@@ -242,4 +306,110 @@ impl SnareDrum {
         self.config.pitch_drop = pitch_drop.clamp(0.0, 1.0);
         self.pitch_start_multiplier = 1.0 + self.config.pitch_drop * 1.5;
     }
-}
\ No newline at end of file
+
+    pub fn set_filter(&mut self, filter: FilterConfig) {
+        self.config.filter = filter;
+        self.filter.set_config(filter);
+    }
+}
+
+const SNARE_PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo::new("frequency", 100.0, 600.0, 200.0),
+    ParameterInfo::new("tonal", 0.0, 1.0, 0.4),
+    ParameterInfo::new("noise", 0.0, 1.0, 0.7),
+    ParameterInfo::new("crack", 0.0, 1.0, 0.5),
+    ParameterInfo::new("decay", 0.01, 2.0, 0.15),
+    ParameterInfo::new("pitch_drop", 0.0, 1.0, 0.3),
+    ParameterInfo::new("volume", 0.0, 1.0, 0.8),
+    ParameterInfo::new("filter_cutoff", 20.0, 20000.0, 20000.0),
+    ParameterInfo::new("filter_resonance", 0.0, 1.0, 0.0),
+    ParameterInfo::new("filter_env_amount", -1.0, 1.0, 0.0),
+    ParameterInfo::new("filter_env_decay", 0.005, 2.0, 0.1),
+];
+
+impl Instrument for SnareDrum {
+    fn trigger(&mut self, time: f64) {
+        SnareDrum::trigger(self, time);
+    }
+
+    fn release(&mut self, time: f64) {
+        SnareDrum::release(self, time);
+    }
+
+    fn tick(&mut self, current_time: f64) -> f32 {
+        SnareDrum::tick(self, current_time)
+    }
+
+    fn process_block(&mut self, output: &mut [f32], start_time: f64) {
+        SnareDrum::process_block(self, output, start_time);
+    }
+
+    fn is_active(&self) -> bool {
+        self.is_active
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        SnareDrum::set_volume(self, volume);
+    }
+
+    fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        SnareDrum::set_retrigger_mode(self, mode);
+    }
+
+    fn retrigger_mode(&self) -> RetriggerMode {
+        self.tonal_oscillator.envelope.retrigger_mode
+    }
+
+    fn parameters(&self) -> &'static [ParameterInfo] {
+        SNARE_PARAMETERS
+    }
+
+    fn get_parameter(&self, name: &str) -> Option<f32> {
+        match name {
+            "frequency" => Some(self.config.snare_frequency),
+            "tonal" => Some(self.config.tonal_amount),
+            "noise" => Some(self.config.noise_amount),
+            "crack" => Some(self.config.crack_amount),
+            "decay" => Some(self.config.decay_time),
+            "pitch_drop" => Some(self.config.pitch_drop),
+            "volume" => Some(self.config.volume),
+            _ => self.config.filter.get_parameter(name),
+        }
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) -> bool {
+        match name {
+            "frequency" => self.set_frequency(value),
+            "tonal" => self.set_tonal(value),
+            "noise" => self.set_noise(value),
+            "crack" => self.set_crack(value),
+            "decay" => self.set_decay(value),
+            "pitch_drop" => self.set_pitch_drop(value),
+            "volume" => self.set_volume(value),
+            _ => {
+                let mut filter = self.config.filter;
+                if !filter.set_parameter(name, value) {
+                    return false;
+                }
+                self.set_filter(filter);
+            }
+        }
+        true
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        SnareDrum::set_seed(self, seed);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -1,6 +1,10 @@
-use crate::envelope::{ADSRConfig, Envelope};
+use crate::envelope::{ADSRConfig, Envelope, RetriggerMode};
 use crate::gen::oscillator::Oscillator;
 use crate::gen::waveform::Waveform;
+use crate::instruments::drum_filter::{DrumFilter, FilterConfig};
+use crate::instruments::instrument::{control_chunks, Instrument, ParameterInfo, CONTROL_INTERVAL};
+use crate::stage::mix_into;
+use std::any::Any;
 
 #[derive(Clone, Copy, Debug)]
 pub struct TomConfig {
@@ -10,6 +14,7 @@ pub struct TomConfig {
     pub decay_time: f32,     // Overall decay length in seconds
     pub pitch_drop: f32,     // Frequency sweep amount (0.0-1.0)
     pub volume: f32,         // Overall volume (0.0-1.0)
+    pub filter: FilterConfig, // Filter and filter envelope, off unless set
 }
 
 impl TomConfig {
@@ -28,9 +33,15 @@ impl TomConfig {
             decay_time: decay_time.max(0.05).min(3.0), // Reasonable decay range for toms
             pitch_drop: pitch_drop.clamp(0.0, 1.0),
             volume: volume.clamp(0.0, 1.0),
+            filter: FilterConfig::off(),
         }
     }
 
+    pub fn with_filter(mut self, filter: FilterConfig) -> Self {
+        self.filter = filter;
+        self
+    }
+
     pub fn default() -> Self {
         Self::new(120.0, 0.8, 0.4, 0.4, 0.3, 0.8)
     }
@@ -65,6 +76,8 @@ pub struct TomDrum {
     pub base_frequency: f32,
     pub pitch_start_multiplier: f32,
 
+    pub filter: DrumFilter,
+
     pub is_active: bool,
 }
 
@@ -83,15 +96,19 @@ impl TomDrum {
             pitch_envelope: Envelope::new(),
             base_frequency: config.tom_frequency,
             pitch_start_multiplier: 1.0 + config.pitch_drop * 1.0, // More subtle pitch drop than snare
+            filter: DrumFilter::new(sample_rate, config.filter),
             is_active: false,
         };
 
         tom.configure_oscillators();
+        // A tom is often hit again while still ringing; fade it out rather than click
+        tom.set_retrigger_mode(RetriggerMode::FadeOut);
         tom
     }
 
     fn configure_oscillators(&mut self) {
         let config = self.config;
+        self.filter.set_config(config.filter);
 
         // Tonal oscillator: Sine wave for body/tone
         self.tonal_oscillator.waveform = Waveform::Sine;
@@ -103,7 +120,7 @@ impl TomDrum {
             config.decay_time * 0.9,  // Main decay
             0.0,                      // No sustain - drums should decay to silence
             config.decay_time * 0.3,  // Medium release
-        ));
+        ).percussive());
 
         // Punch oscillator: Triangle wave for attack character
         self.punch_oscillator.waveform = Waveform::Triangle;
@@ -115,7 +132,7 @@ impl TomDrum {
             config.decay_time * 0.3,  // Short decay for punch
             0.0,                      // No sustain for punch
             config.decay_time * 0.1,  // Quick release
-        ));
+        ).percussive());
 
         // Pitch envelope: Fast attack, medium decay for frequency sweeping
         self.pitch_envelope.set_config(ADSRConfig::new(
@@ -123,7 +140,7 @@ impl TomDrum {
             config.decay_time * 0.4,  // Medium pitch drop
             0.0,                      // Drop to base frequency
             config.decay_time * 0.2,  // Medium release
-        ));
+        ).percussive());
     }
 
     pub fn set_config(&mut self, config: TomConfig) {
@@ -133,7 +150,14 @@ impl TomDrum {
         self.configure_oscillators();
     }
 
-    pub fn trigger(&mut self, time: f32) {
+    pub fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        self.tonal_oscillator.set_retrigger_mode(mode);
+        self.punch_oscillator.set_retrigger_mode(mode);
+        self.pitch_envelope.retrigger_mode = mode;
+        self.filter.set_retrigger_mode(mode);
+    }
+
+    pub fn trigger(&mut self, time: f64) {
         self.is_active = true;
 
         // Trigger both oscillators
@@ -142,9 +166,10 @@ impl TomDrum {
 
         // Trigger pitch envelope
         self.pitch_envelope.trigger(time);
+        self.filter.trigger(time);
     }
 
-    pub fn release(&mut self, time: f32) {
+    pub fn release(&mut self, time: f64) {
         if self.is_active {
             self.tonal_oscillator.release(time);
             self.punch_oscillator.release(time);
@@ -152,12 +177,8 @@ impl TomDrum {
         }
     }
 
-    pub fn tick(&mut self, current_time: f32) -> f32 {
-        if !self.is_active {
-            return 0.0;
-        }
-
-        // Calculate pitch modulation
+    /// Set the oscillator frequencies from the pitch envelope at `current_time`
+    fn update_pitch(&mut self, current_time: f64) {
         let pitch_envelope_value = self.pitch_envelope.get_amplitude(current_time);
         let frequency_multiplier = 1.0 + (self.pitch_start_multiplier - 1.0) * pitch_envelope_value;
 
@@ -166,23 +187,55 @@ impl TomDrum {
 
         // Punch oscillator gets a more subtle pitch modulation
         self.punch_oscillator.frequency_hz = self.base_frequency * 3.0 * (1.0 + (frequency_multiplier - 1.0) * 0.5);
+    }
 
-        // Sum oscillator outputs
-        let tonal_output = self.tonal_oscillator.tick(current_time);
-        let punch_output = self.punch_oscillator.tick(current_time);
-
-        let total_output = tonal_output + punch_output;
-
-        // Check if tom is still active
+    fn update_active(&mut self) {
         if !self.tonal_oscillator.envelope.is_active
             && !self.punch_oscillator.envelope.is_active
         {
             self.is_active = false;
         }
+    }
+
+    pub fn tick(&mut self, current_time: f64) -> f32 {
+        if !self.is_active {
+            return 0.0;
+        }
+
+        self.update_pitch(current_time);
+
+        // Sum oscillator outputs
+        let tonal_output = self.tonal_oscillator.tick(current_time);
+        let punch_output = self.punch_oscillator.tick(current_time);
+
+        let total_output = self.filter.process(tonal_output + punch_output, current_time);
 
+        self.update_active();
         total_output
     }
 
+    /// Render a block starting at `start_time`, following the pitch and filter envelopes once per
+    /// control chunk
+    pub fn process_block(&mut self, output: &mut [f32], start_time: f64) {
+        output.fill(0.0);
+        let mut scratch = [0.0; CONTROL_INTERVAL];
+        for (range, chunk_time, control_time) in control_chunks(output.len(), start_time, self.sample_rate) {
+            if !self.is_active {
+                break;
+            }
+            self.update_pitch(control_time);
+
+            let chunk = &mut output[range];
+            let scratch = &mut scratch[..chunk.len()];
+            self.tonal_oscillator.process_block(chunk, chunk_time);
+            self.punch_oscillator.process_block(scratch, chunk_time);
+            mix_into(chunk, scratch, 1.0);
+            self.filter.process_block(chunk, control_time);
+
+            self.update_active();
+        }
+    }
+
     pub fn is_active(&self) -> bool {
         self.is_active
     }
@@ -217,4 +270,103 @@ impl TomDrum {
         self.config.pitch_drop = pitch_drop.clamp(0.0, 1.0);
         self.pitch_start_multiplier = 1.0 + self.config.pitch_drop * 1.0;
     }
-}
\ No newline at end of file
+
+    pub fn set_filter(&mut self, filter: FilterConfig) {
+        self.config.filter = filter;
+        self.filter.set_config(filter);
+    }
+}
+
+const TOM_PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo::new("frequency", 60.0, 400.0, 120.0),
+    ParameterInfo::new("tonal", 0.0, 1.0, 0.8),
+    ParameterInfo::new("punch", 0.0, 1.0, 0.4),
+    ParameterInfo::new("decay", 0.05, 3.0, 0.4),
+    ParameterInfo::new("pitch_drop", 0.0, 1.0, 0.3),
+    ParameterInfo::new("volume", 0.0, 1.0, 0.8),
+    ParameterInfo::new("filter_cutoff", 20.0, 20000.0, 20000.0),
+    ParameterInfo::new("filter_resonance", 0.0, 1.0, 0.0),
+    ParameterInfo::new("filter_env_amount", -1.0, 1.0, 0.0),
+    ParameterInfo::new("filter_env_decay", 0.005, 2.0, 0.1),
+];
+
+impl Instrument for TomDrum {
+    fn trigger(&mut self, time: f64) {
+        TomDrum::trigger(self, time);
+    }
+
+    fn release(&mut self, time: f64) {
+        TomDrum::release(self, time);
+    }
+
+    fn tick(&mut self, current_time: f64) -> f32 {
+        TomDrum::tick(self, current_time)
+    }
+
+    fn process_block(&mut self, output: &mut [f32], start_time: f64) {
+        TomDrum::process_block(self, output, start_time);
+    }
+
+    fn is_active(&self) -> bool {
+        self.is_active
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        TomDrum::set_volume(self, volume);
+    }
+
+    fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        TomDrum::set_retrigger_mode(self, mode);
+    }
+
+    fn retrigger_mode(&self) -> RetriggerMode {
+        self.tonal_oscillator.envelope.retrigger_mode
+    }
+
+    fn parameters(&self) -> &'static [ParameterInfo] {
+        TOM_PARAMETERS
+    }
+
+    fn get_parameter(&self, name: &str) -> Option<f32> {
+        match name {
+            "frequency" => Some(self.config.tom_frequency),
+            "tonal" => Some(self.config.tonal_amount),
+            "punch" => Some(self.config.punch_amount),
+            "decay" => Some(self.config.decay_time),
+            "pitch_drop" => Some(self.config.pitch_drop),
+            "volume" => Some(self.config.volume),
+            _ => self.config.filter.get_parameter(name),
+        }
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) -> bool {
+        match name {
+            "frequency" => self.set_frequency(value),
+            "tonal" => self.set_tonal(value),
+            "punch" => self.set_punch(value),
+            "decay" => self.set_decay(value),
+            "pitch_drop" => self.set_pitch_drop(value),
+            "volume" => self.set_volume(value),
+            _ => {
+                let mut filter = self.config.filter;
+                if !filter.set_parameter(name, value) {
+                    return false;
+                }
+                self.set_filter(filter);
+            }
+        }
+        true
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,276 @@
+//! Polyphony for single-voice instruments
+//! A VoicePool holds several copies of an instrument and sends each trigger to a free one, so a
+//! fast roll lets earlier hits ring on instead of cutting them off. When every voice is busy, the
+//! stealing strategy picks which one to reuse; that voice's retrigger mode handles the handover.
+
+use crate::envelope::RetriggerMode;
+use crate::instruments::instrument::{sample_time, Instrument, ParameterInfo};
+use crate::stage::MAX_BLOCK_SIZE;
+use std::any::Any;
+
+/// Voices a pool gets unless asked for another number
+pub const DEFAULT_POLYPHONY: usize = 4;
+
+/// Base seed for a new pool's voices, each of which gets the next seed up
+const DEFAULT_SEED: u64 = 0x504F_4F4C; // "POOL"
+
+/// Per-sample decay of the level followed for `VoiceStealing::Quietest`, about 23 ms at 44.1 kHz
+const LEVEL_DECAY: f32 = 0.999;
+
+indexed_enum! {
+    /// Which voice a pool reuses when every voice is already sounding
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum VoiceStealing {
+        /// The voice triggered longest ago
+        #[default]
+        Oldest,
+        /// The voice with the lowest recent output level
+        Quietest,
+        /// The voice already playing the same note, even if others are free, like a choke group;
+        /// otherwise the oldest
+        SameNote,
+    }
+}
+
+/// Bookkeeping for one voice of a pool
+#[derive(Debug, Clone, Copy)]
+struct VoiceState {
+    trigger_time: f64,
+    note: u32,
+    /// Recent peak output level
+    level: f32,
+}
+
+/// Several voices of one instrument played as a single instrument
+/// Parameter, volume and retrigger changes go to every voice; reads come from the first voice.
+pub struct VoicePool<T: Instrument> {
+    voices: Vec<T>,
+    states: Vec<VoiceState>,
+    /// Number of voices in use, up to the number the pool was built with
+    polyphony: usize,
+    pub stealing: VoiceStealing,
+}
+
+impl<T: Instrument> VoicePool<T> {
+    /// A pool of `voices` instruments, each built by `make_voice`
+    pub fn new(voices: usize, mut make_voice: impl FnMut() -> T) -> Self {
+        let mut voices: Vec<T> = (0..voices.max(1)).map(|_| make_voice()).collect();
+        // Voices built the same way start with the same seed; give each its own random stream
+        for (index, voice) in voices.iter_mut().enumerate() {
+            voice.set_seed(DEFAULT_SEED.wrapping_add(index as u64));
+        }
+        let states = vec![
+            VoiceState {
+                trigger_time: f64::NEG_INFINITY,
+                note: 0,
+                level: 0.0,
+            };
+            voices.len()
+        ];
+        Self {
+            polyphony: voices.len(),
+            voices,
+            states,
+            stealing: VoiceStealing::default(),
+        }
+    }
+
+    pub fn with_stealing(mut self, stealing: VoiceStealing) -> Self {
+        self.stealing = stealing;
+        self
+    }
+
+    /// Use only the first `voices` voices; the pool never grows past the size it was built with
+    pub fn set_polyphony(&mut self, voices: usize) {
+        self.polyphony = voices.clamp(1, self.voices.len());
+    }
+
+    pub fn polyphony(&self) -> usize {
+        self.polyphony
+    }
+
+    /// Number of voices the pool was built with
+    pub fn capacity(&self) -> usize {
+        self.voices.len()
+    }
+
+    pub fn voices(&self) -> &[T] {
+        &self.voices
+    }
+
+    /// Every voice, e.g. to apply a typed config to all of them
+    pub fn voices_mut(&mut self) -> &mut [T] {
+        &mut self.voices
+    }
+
+    /// Number of voices currently sounding
+    pub fn active_voices(&self) -> usize {
+        self.voices.iter().filter(|voice| voice.is_active()).count()
+    }
+
+    /// Play `note` on a free voice, or steal one if none is free
+    /// Notes only matter to `VoiceStealing::SameNote`; plain triggers all use note 0.
+    pub fn trigger_note(&mut self, time: f64, note: u32) {
+        let index = self.choose_voice(note);
+        self.states[index] = VoiceState {
+            trigger_time: time,
+            note,
+            level: self.states[index].level,
+        };
+        self.voices[index].trigger(time);
+    }
+
+    /// Release only the voices playing `note`
+    pub fn release_note(&mut self, time: f64, note: u32) {
+        for (voice, state) in self.voices.iter_mut().zip(&self.states) {
+            if state.note == note && voice.is_active() {
+                voice.release(time);
+            }
+        }
+    }
+
+    fn choose_voice(&self, note: u32) -> usize {
+        let in_use = 0..self.polyphony;
+        let sounding = |index: &usize| self.voices[*index].is_active();
+
+        if self.stealing == VoiceStealing::SameNote {
+            if let Some(index) = in_use.clone().filter(sounding).find(|&index| self.states[index].note == note) {
+                return index;
+            }
+        }
+        if let Some(index) = in_use.clone().find(|index| !sounding(index)) {
+            return index;
+        }
+
+        match self.stealing {
+            VoiceStealing::Quietest => in_use
+                .min_by(|&a, &b| self.states[a].level.total_cmp(&self.states[b].level))
+                .unwrap_or(0),
+            VoiceStealing::Oldest | VoiceStealing::SameNote => in_use
+                .min_by(|&a, &b| self.states[a].trigger_time.total_cmp(&self.states[b].trigger_time))
+                .unwrap_or(0),
+        }
+    }
+}
+
+impl<T: Instrument + 'static> Instrument for VoicePool<T> {
+    fn trigger(&mut self, time: f64) {
+        self.trigger_note(time, 0);
+    }
+
+    fn release(&mut self, time: f64) {
+        for voice in &mut self.voices {
+            if voice.is_active() {
+                voice.release(time);
+            }
+        }
+    }
+
+    fn tick(&mut self, current_time: f64) -> f32 {
+        let mut output = 0.0;
+        for (voice, state) in self.voices.iter_mut().zip(&mut self.states) {
+            if voice.is_active() {
+                let sample = voice.tick(current_time);
+                state.level = sample.abs().max(state.level * LEVEL_DECAY);
+                output += sample;
+            } else {
+                state.level = 0.0;
+            }
+        }
+        output
+    }
+
+    fn is_active(&self) -> bool {
+        self.voices.iter().any(|voice| voice.is_active())
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        for voice in &mut self.voices {
+            voice.set_volume(volume);
+        }
+    }
+
+    fn sample_rate(&self) -> f32 {
+        self.voices[0].sample_rate()
+    }
+
+    fn set_retrigger_mode(&mut self, mode: RetriggerMode) {
+        for voice in &mut self.voices {
+            voice.set_retrigger_mode(mode);
+        }
+    }
+
+    fn retrigger_mode(&self) -> RetriggerMode {
+        self.voices[0].retrigger_mode()
+    }
+
+    fn set_polyphony(&mut self, voices: usize) {
+        VoicePool::set_polyphony(self, voices);
+    }
+
+    fn polyphony(&self) -> usize {
+        self.polyphony
+    }
+
+    fn set_voice_stealing(&mut self, stealing: VoiceStealing) {
+        self.stealing = stealing;
+    }
+
+    fn voice_stealing(&self) -> VoiceStealing {
+        self.stealing
+    }
+
+    /// Render a block of consecutive samples starting at `start_time`
+    fn process_block(&mut self, output: &mut [f32], start_time: f64) {
+        output.fill(0.0);
+        let mut scratch = [0.0; MAX_BLOCK_SIZE];
+        let sample_rate = self.sample_rate();
+
+        for (chunk_index, chunk) in output.chunks_mut(MAX_BLOCK_SIZE).enumerate() {
+            let chunk_time = sample_time(start_time, chunk_index * MAX_BLOCK_SIZE, sample_rate);
+            let scratch = &mut scratch[..chunk.len()];
+            for (voice, state) in self.voices.iter_mut().zip(&mut self.states) {
+                if !voice.is_active() {
+                    state.level = 0.0;
+                    continue;
+                }
+                voice.process_block(scratch, chunk_time);
+                for (sample, &voice_sample) in chunk.iter_mut().zip(scratch.iter()) {
+                    state.level = voice_sample.abs().max(state.level * LEVEL_DECAY);
+                    *sample += voice_sample;
+                }
+            }
+        }
+    }
+
+    fn parameters(&self) -> &'static [ParameterInfo] {
+        self.voices[0].parameters()
+    }
+
+    fn get_parameter(&self, name: &str) -> Option<f32> {
+        self.voices[0].get_parameter(name)
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) -> bool {
+        let mut found = false;
+        for voice in &mut self.voices {
+            found |= voice.set_parameter(name, value);
+        }
+        found
+    }
+
+    /// Seed each voice differently so overlapping hits don't share a noise stream
+    fn set_seed(&mut self, seed: u64) {
+        for (index, voice) in self.voices.iter_mut().enumerate() {
+            voice.set_seed(seed.wrapping_add(index as u64));
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
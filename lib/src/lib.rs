@@ -1,9 +1,16 @@
 //! Shared audio engine logic for both native (CPAL) and WASM (web)
 
+#[macro_use]
+mod macros;
+
 pub mod audio_state;
 pub mod envelope;
 pub mod filters;
+pub mod modulation;
+pub mod render;
 pub mod stage;
+pub mod track;
+pub mod wav;
 
 // New organized modules
 pub mod instruments;
@@ -16,12 +23,156 @@ pub mod platform;
 // WASM bindings (web)
 #[cfg(feature = "web")]
 pub mod web {
-    use super::envelope::ADSRConfig;
-    use super::instruments::{HiHat, HiHatConfig, KickConfig, KickDrum, SnareConfig, SnareDrum, TomConfig, TomDrum};
+    use super::effects::compressor::{CompressorConfig, DetectorMode};
+    use super::effects::eq::{EqBand, EqBandType, EqSlope, EQ_BANDS};
+    use super::effects::limiter::LimiterMode;
+    use super::effects::pan::PanLaw;
+    use super::effects::reverb::ReverbConfig;
+    use super::envelope::{ADSRConfig, BreakpointEnvelope, EnvelopeCurve, EnvelopeSegment, RetriggerMode};
+    use super::instruments::{
+        FmAlgorithm, FmConfig, FmSynth, HiHat, HiHatConfig, KickConfig, KickDrum, OperatorConfig, SnareConfig, SnareDrum,
+        FilterConfig, TomConfig, TomDrum, VoiceStealing,
+    };
+    use super::filters::FilterType;
+    use super::gen::lfo::{Lfo, LfoMode, LfoRate, LfoShape};
     use super::gen::oscillator::Oscillator;
+    use super::gen::wavetable::Wavetable;
+    use super::modulation::{ModDestination, ModSource};
     use super::stage::Stage;
+    use super::track::Track;
+    use std::sync::Arc;
     use wasm_bindgen::prelude::*;
 
+    // Drum presets by name; unknown names fall back to the default sound
+    fn kick_preset(preset_name: &str) -> KickConfig {
+        match preset_name {
+            "punchy" => KickConfig::punchy(),
+            "deep" => KickConfig::deep(),
+            "tight" => KickConfig::tight(),
+            _ => KickConfig::default(),
+        }
+    }
+
+    fn snare_preset(preset_name: &str) -> SnareConfig {
+        match preset_name {
+            "crispy" => SnareConfig::crispy(),
+            "deep" => SnareConfig::deep(),
+            "tight" => SnareConfig::tight(),
+            "fat" => SnareConfig::fat(),
+            _ => SnareConfig::default(),
+        }
+    }
+
+    fn hihat_preset(preset_name: &str) -> HiHatConfig {
+        match preset_name {
+            "closed_default" => HiHatConfig::closed_default(),
+            "open_default" => HiHatConfig::open_default(),
+            "closed_tight" => HiHatConfig::closed_tight(),
+            "open_bright" => HiHatConfig::open_bright(),
+            "closed_dark" => HiHatConfig::closed_dark(),
+            "open_long" => HiHatConfig::open_long(),
+            _ => HiHatConfig::closed_default(),
+        }
+    }
+
+    fn tom_preset(preset_name: &str) -> TomConfig {
+        match preset_name {
+            "high_tom" => TomConfig::high_tom(),
+            "mid_tom" => TomConfig::mid_tom(),
+            "low_tom" => TomConfig::low_tom(),
+            "floor_tom" => TomConfig::floor_tom(),
+            _ => TomConfig::default(),
+        }
+    }
+
+    fn mod_source(source: u32, index: usize) -> Option<ModSource> {
+        match source {
+            0 => Some(ModSource::Lfo(index)),
+            1 => Some(ModSource::Envelope(index)),
+            2 => Some(ModSource::Velocity),
+            3 => Some(ModSource::StepIndex),
+            4 => Some(ModSource::Random),
+            5 => Some(ModSource::Macro(index)),
+            _ => None,
+        }
+    }
+
+    /// Replace any filter settings given from JavaScript, keeping the rest of `current`
+    /// Filter type: 0 = off, 1 = low-pass, 2 = band-pass, 3 = high-pass, 4 = notch, 5 = ladder
+    fn filter_config(
+        current: FilterConfig,
+        filter_type: Option<usize>,
+        cutoff: Option<f32>,
+        resonance: Option<f32>,
+        envelope_amount: Option<f32>,
+        envelope_decay: Option<f32>,
+    ) -> FilterConfig {
+        FilterConfig::new(
+            filter_type.and_then(FilterType::from_index).unwrap_or(current.filter_type),
+            cutoff.unwrap_or(current.cutoff),
+            resonance.unwrap_or(current.resonance),
+            envelope_amount.unwrap_or(current.envelope_amount),
+            envelope_decay.unwrap_or(current.envelope_decay),
+        )
+    }
+
+    /// Build an EQ band from JavaScript values
+    /// Band type: 0 = bell, 1 = low shelf, 2 = high shelf, 3 = high-pass, 4 = low-pass
+    /// Slope: 0 = 12, 1 = 24, 2 = 36, 3 = 48 dB/octave
+    fn eq_band(band_type: usize, frequency: f32, gain_db: f32, q: f32, slope: usize, enabled: bool) -> EqBand {
+        let band_type = EqBandType::from_index(band_type).unwrap_or_default();
+        let slope = EqSlope::from_index(slope).unwrap_or_default();
+        EqBand {
+            enabled,
+            ..EqBand::new(band_type, frequency, gain_db, q).with_slope(slope)
+        }
+    }
+
+    /// An EQ band as [enabled, band type, frequency, gain dB, Q, slope] for JavaScript
+    fn eq_band_values(band: EqBand) -> Vec<f32> {
+        vec![
+            if band.enabled { 1.0 } else { 0.0 },
+            band.band_type.index() as f32,
+            band.frequency,
+            band.gain_db,
+            band.q,
+            band.slope.index() as f32,
+        ]
+    }
+
+    /// Build compressor settings from JavaScript values; detector: 0 = peak, 1 = RMS
+    #[allow(clippy::too_many_arguments)]
+    fn compressor_config(threshold_db: f32, ratio: f32, knee_db: f32, attack: f32, release: f32, makeup_db: f32, detector: usize, enabled: bool) -> CompressorConfig {
+        let detector = DetectorMode::from_index(detector).unwrap_or_default();
+        CompressorConfig {
+            enabled,
+            ..CompressorConfig::new(threshold_db, ratio, knee_db, attack, release, makeup_db).with_detector(detector)
+        }
+    }
+
+    /// Compressor settings as [enabled, threshold dB, ratio, knee dB, attack, release, makeup dB, detector]
+    fn compressor_values(config: CompressorConfig) -> Vec<f32> {
+        vec![
+            if config.enabled { 1.0 } else { 0.0 },
+            config.threshold_db,
+            config.ratio,
+            config.knee_db,
+            config.attack,
+            config.release,
+            config.makeup_db,
+            config.detector.index() as f32,
+        ]
+    }
+
+    fn fm_preset(preset_name: &str) -> FmConfig {
+        match preset_name {
+            "bell" => FmConfig::bell(),
+            "metallic" => FmConfig::metallic(),
+            "bass" => FmConfig::bass(),
+            _ => FmConfig::electric_piano(),
+        }
+    }
+
     #[wasm_bindgen]
     pub struct WasmOscillator {
         oscillator: Oscillator,
@@ -37,17 +188,17 @@ pub mod web {
         }
 
         #[wasm_bindgen]
-        pub fn trigger(&mut self, time: f32) {
+        pub fn trigger(&mut self, time: f64) {
             self.oscillator.trigger(time);
         }
 
         #[wasm_bindgen]
-        pub fn tick(&mut self, current_time: f32) -> f32 {
+        pub fn tick(&mut self, current_time: f64) -> f32 {
             self.oscillator.tick(current_time)
         }
 
         #[wasm_bindgen]
-        pub fn release(&mut self, time: f32) {
+        pub fn release(&mut self, time: f64) {
             self.oscillator.release(time);
         }
 
@@ -88,9 +239,30 @@ pub mod web {
             self.stage.add(oscillator);
         }
 
+        /// Render one mono sample at the given host time in seconds (e.g. AudioContext.currentTime)
+        #[wasm_bindgen]
+        pub fn tick(&mut self, current_time: f64) -> f32 {
+            self.follow_host_time(current_time);
+            self.stage.tick()
+        }
+
+        /// Render a block of mono samples starting at the given host time in seconds
         #[wasm_bindgen]
-        pub fn tick(&mut self, current_time: f32) -> f32 {
-            self.stage.tick(current_time)
+        pub fn process_block(&mut self, output: &mut [f32], start_time: f64) {
+            self.follow_host_time(start_time);
+            self.stage.process_block(output);
+        }
+
+        /// Render a block of stereo frames into separate left and right buffers of equal length
+        #[wasm_bindgen]
+        pub fn process_block_stereo(&mut self, left: &mut [f32], right: &mut [f32], start_time: f64) {
+            self.follow_host_time(start_time);
+            self.stage.process_block_stereo(left, right);
+        }
+
+        fn follow_host_time(&mut self, time: f64) {
+            let sample_position = (time.max(0.0) * self.stage.sample_rate as f64).round() as u64;
+            self.stage.set_sample_position(sample_position);
         }
 
         #[wasm_bindgen]
@@ -155,6 +327,13 @@ pub mod web {
                 3 => crate::gen::waveform::Waveform::Triangle,
                 4 => crate::gen::waveform::Waveform::RingMod,
                 5 => crate::gen::waveform::Waveform::Noise,
+                6 => crate::gen::waveform::Waveform::PolyBlepSaw,
+                7 => crate::gen::waveform::Waveform::PolyBlepSquare,
+                8 => crate::gen::waveform::Waveform::PolyBlepTriangle,
+                9 => crate::gen::waveform::Waveform::PolyBlepPulse,
+                10 => crate::gen::waveform::Waveform::PinkNoise,
+                11 => crate::gen::waveform::Waveform::BrownNoise,
+                12 => crate::gen::waveform::Waveform::Wavetable,
                 _ => crate::gen::waveform::Waveform::Sine, // Default to sine for invalid values
             };
             self.stage.set_instrument_waveform(index, waveform);
@@ -170,6 +349,13 @@ pub mod web {
                 crate::gen::waveform::Waveform::Triangle => 3,
                 crate::gen::waveform::Waveform::RingMod => 4,
                 crate::gen::waveform::Waveform::Noise => 5,
+                crate::gen::waveform::Waveform::PolyBlepSaw => 6,
+                crate::gen::waveform::Waveform::PolyBlepSquare => 7,
+                crate::gen::waveform::Waveform::PolyBlepTriangle => 8,
+                crate::gen::waveform::Waveform::PolyBlepPulse => 9,
+                crate::gen::waveform::Waveform::PinkNoise => 10,
+                crate::gen::waveform::Waveform::BrownNoise => 11,
+                crate::gen::waveform::Waveform::Wavetable => 12,
             }
         }
 
@@ -184,6 +370,44 @@ pub mod web {
             self.stage.get_instrument_modulator_frequency(index)
         }
 
+        #[wasm_bindgen]
+        pub fn set_instrument_pulse_width(&mut self, index: usize, pulse_width: f32) {
+            self.stage.set_instrument_pulse_width(index, pulse_width);
+        }
+
+        #[wasm_bindgen]
+        pub fn get_instrument_pulse_width(&self, index: usize) -> f32 {
+            self.stage.get_instrument_pulse_width(index)
+        }
+
+        /// Load a wavetable from consecutive single-cycle frames of `frame_size` samples
+        #[wasm_bindgen]
+        pub fn set_instrument_wavetable(&mut self, index: usize, samples: &[f32], frame_size: usize) -> Result<(), JsValue> {
+            let wavetable = Wavetable::from_samples(samples, frame_size)
+                .map_err(|err| JsValue::from_str(&err.to_string()))?;
+            self.stage.set_instrument_wavetable(index, Arc::new(wavetable));
+            Ok(())
+        }
+
+        /// Load a built-in wavetable by name ("basic" or "pulse")
+        #[wasm_bindgen]
+        pub fn load_instrument_wavetable_preset(&mut self, index: usize, preset_name: &str) -> Result<(), JsValue> {
+            let wavetable = Wavetable::builtin(preset_name)
+                .ok_or_else(|| JsValue::from_str(&format!("Unknown wavetable '{}'", preset_name)))?;
+            self.stage.set_instrument_wavetable(index, Arc::new(wavetable));
+            Ok(())
+        }
+
+        #[wasm_bindgen]
+        pub fn set_instrument_wavetable_position(&mut self, index: usize, position: f32) {
+            self.stage.set_instrument_wavetable_position(index, position);
+        }
+
+        #[wasm_bindgen]
+        pub fn get_instrument_wavetable_position(&self, index: usize) -> f32 {
+            self.stage.get_instrument_wavetable_position(index)
+        }
+
         #[wasm_bindgen]
         pub fn set_instrument_enabled(&mut self, index: usize, enabled: bool) {
             self.stage.set_instrument_enabled(index, enabled);
@@ -201,7 +425,7 @@ pub mod web {
         }
         
         #[wasm_bindgen]
-        pub fn sequencer_play_at_time(&mut self, time: f32) {
+        pub fn sequencer_play_at_time(&mut self, time: f64) {
             self.stage.sequencer_play_at_time(time);
         }
 
@@ -221,13 +445,13 @@ pub mod web {
         }
 
         #[wasm_bindgen]
-        pub fn sequencer_set_step(&mut self, instrument: usize, step: usize, enabled: bool) {
-            self.stage.sequencer_set_step(instrument, step, enabled);
+        pub fn sequencer_set_step(&mut self, track: usize, step: usize, enabled: bool) {
+            self.stage.sequencer_set_step(track, step, enabled);
         }
 
         #[wasm_bindgen]
-        pub fn sequencer_get_step(&self, instrument: usize, step: usize) -> bool {
-            self.stage.sequencer_get_step(instrument, step)
+        pub fn sequencer_get_step(&self, track: usize, step: usize) -> bool {
+            self.stage.sequencer_get_step(track, step)
         }
 
         #[wasm_bindgen]
@@ -283,71 +507,53 @@ pub mod web {
             self.stage.set_kick_config(config);
         }
         
+        /// The filter arguments are optional; any left out keep their current values
         #[wasm_bindgen]
-        pub fn set_snare_config(&mut self, frequency: f32, tonal: f32, noise: f32, crack: f32, decay: f32, pitch_drop: f32, volume: f32) {
-            let config = SnareConfig::new(frequency, tonal, noise, crack, decay, pitch_drop, volume);
+        #[allow(clippy::too_many_arguments)]
+        pub fn set_snare_config(&mut self, frequency: f32, tonal: f32, noise: f32, crack: f32, decay: f32, pitch_drop: f32, volume: f32, filter_type: Option<usize>, filter_cutoff: Option<f32>, filter_resonance: Option<f32>, filter_env_amount: Option<f32>, filter_env_decay: Option<f32>) {
+            let filter = filter_config(self.stage.get_snare_config().filter, filter_type, filter_cutoff, filter_resonance, filter_env_amount, filter_env_decay);
+            let config = SnareConfig::new(frequency, tonal, noise, crack, decay, pitch_drop, volume).with_filter(filter);
             self.stage.set_snare_config(config);
         }
         
         #[wasm_bindgen]
-        pub fn set_hihat_config(&mut self, frequency: f32, resonance: f32, brightness: f32, decay: f32, attack: f32, volume: f32, is_open: bool) {
-            let config = HiHatConfig::new(frequency, resonance, brightness, decay, attack, volume, is_open);
+        #[allow(clippy::too_many_arguments)]
+        pub fn set_hihat_config(&mut self, frequency: f32, resonance: f32, brightness: f32, decay: f32, attack: f32, volume: f32, is_open: bool, filter_type: Option<usize>, filter_cutoff: Option<f32>, filter_resonance: Option<f32>, filter_env_amount: Option<f32>, filter_env_decay: Option<f32>) {
+            let filter = filter_config(self.stage.get_hihat_config().filter, filter_type, filter_cutoff, filter_resonance.or(Some(resonance)), filter_env_amount, filter_env_decay);
+            let config = HiHatConfig::new(frequency, resonance, brightness, decay, attack, volume, is_open).with_filter(filter);
             self.stage.set_hihat_config(config);
         }
         
         #[wasm_bindgen]
-        pub fn set_tom_config(&mut self, frequency: f32, tonal: f32, punch: f32, decay: f32, pitch_drop: f32, volume: f32) {
-            let config = TomConfig::new(frequency, tonal, punch, decay, pitch_drop, volume);
+        #[allow(clippy::too_many_arguments)]
+        pub fn set_tom_config(&mut self, frequency: f32, tonal: f32, punch: f32, decay: f32, pitch_drop: f32, volume: f32, filter_type: Option<usize>, filter_cutoff: Option<f32>, filter_resonance: Option<f32>, filter_env_amount: Option<f32>, filter_env_decay: Option<f32>) {
+            let filter = filter_config(self.stage.get_tom_config().filter, filter_type, filter_cutoff, filter_resonance, filter_env_amount, filter_env_decay);
+            let config = TomConfig::new(frequency, tonal, punch, decay, pitch_drop, volume).with_filter(filter);
             self.stage.set_tom_config(config);
         }
         
         // Drum preset loaders
         #[wasm_bindgen]
         pub fn load_kick_preset(&mut self, preset_name: &str) {
-            let config = match preset_name {
-                "punchy" => KickConfig::punchy(),
-                "deep" => KickConfig::deep(),
-                "tight" => KickConfig::tight(),
-                _ => KickConfig::default(),
-            };
+            let config = kick_preset(preset_name);
             self.stage.set_kick_config(config);
         }
         
         #[wasm_bindgen]
         pub fn load_snare_preset(&mut self, preset_name: &str) {
-            let config = match preset_name {
-                "crispy" => SnareConfig::crispy(),
-                "deep" => SnareConfig::deep(),
-                "tight" => SnareConfig::tight(),
-                "fat" => SnareConfig::fat(),
-                _ => SnareConfig::default(),
-            };
+            let config = snare_preset(preset_name);
             self.stage.set_snare_config(config);
         }
         
         #[wasm_bindgen]
         pub fn load_hihat_preset(&mut self, preset_name: &str) {
-            let config = match preset_name {
-                "closed_default" => HiHatConfig::closed_default(),
-                "open_default" => HiHatConfig::open_default(),
-                "closed_tight" => HiHatConfig::closed_tight(),
-                "open_bright" => HiHatConfig::open_bright(),
-                "closed_dark" => HiHatConfig::closed_dark(),
-                "open_long" => HiHatConfig::open_long(),
-                _ => HiHatConfig::closed_default(),
-            };
+            let config = hihat_preset(preset_name);
             self.stage.set_hihat_config(config);
         }
         
         #[wasm_bindgen]
         pub fn load_tom_preset(&mut self, preset_name: &str) {
-            let config = match preset_name {
-                "high_tom" => TomConfig::high_tom(),
-                "mid_tom" => TomConfig::mid_tom(),
-                "low_tom" => TomConfig::low_tom(),
-                "floor_tom" => TomConfig::floor_tom(),
-                _ => TomConfig::default(),
-            };
+            let config = tom_preset(preset_name);
             self.stage.set_tom_config(config);
         }
         
@@ -362,6 +568,71 @@ pub mod web {
             self.stage.get_saturation()
         }
         
+        #[wasm_bindgen]
+        pub fn set_limiter_threshold(&mut self, threshold: f32) {
+            self.stage.set_limiter_threshold(threshold);
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_limiter_threshold(&self) -> f32 {
+            self.stage.get_limiter_threshold()
+        }
+        
+        /// Limiter mode: 0 = lookahead, 1 = hard clip
+        #[wasm_bindgen]
+        pub fn set_limiter_mode(&mut self, mode: u32) {
+            let mode = match mode {
+                1 => LimiterMode::HardClip,
+                _ => LimiterMode::Lookahead,
+            };
+            self.stage.set_limiter_mode(mode);
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_limiter_mode(&self) -> u32 {
+            match self.stage.get_limiter_mode() {
+                LimiterMode::Lookahead => 0,
+                LimiterMode::HardClip => 1,
+            }
+        }
+        
+        /// Attack, release and lookahead are in seconds
+        #[wasm_bindgen]
+        pub fn set_limiter_attack(&mut self, attack: f32) {
+            self.stage.set_limiter_attack(attack);
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_limiter_attack(&self) -> f32 {
+            self.stage.get_limiter_attack()
+        }
+        
+        #[wasm_bindgen]
+        pub fn set_limiter_release(&mut self, release: f32) {
+            self.stage.set_limiter_release(release);
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_limiter_release(&self) -> f32 {
+            self.stage.get_limiter_release()
+        }
+        
+        #[wasm_bindgen]
+        pub fn set_limiter_lookahead(&mut self, lookahead: f32) {
+            self.stage.set_limiter_lookahead(lookahead);
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_limiter_lookahead(&self) -> f32 {
+            self.stage.get_limiter_lookahead()
+        }
+        
+        /// Most gain reduction in dB since the last call, for a meter polled once per frame
+        #[wasm_bindgen]
+        pub fn get_limiter_gain_reduction(&mut self) -> f32 {
+            self.stage.limiter.take_peak_gain_reduction_db()
+        }
+        
         // Individual drum trigger methods
         #[wasm_bindgen]
         pub fn trigger_kick(&mut self) {
@@ -382,6 +653,453 @@ pub mod web {
         pub fn trigger_tom(&mut self) {
             self.stage.trigger_tom();
         }
+        
+        // Track management methods; each add returns the new track's index
+        #[wasm_bindgen]
+        pub fn add_kick_track(&mut self, name: &str, preset_name: &str) -> usize {
+            let kick = KickDrum::with_config(self.stage.sample_rate, kick_preset(preset_name));
+            self.stage.add_track(Track::new(name, Box::new(kick)))
+        }
+        
+        #[wasm_bindgen]
+        pub fn add_snare_track(&mut self, name: &str, preset_name: &str) -> usize {
+            let snare = SnareDrum::with_config(self.stage.sample_rate, snare_preset(preset_name));
+            self.stage.add_track(Track::new(name, Box::new(snare)))
+        }
+        
+        #[wasm_bindgen]
+        pub fn add_hihat_track(&mut self, name: &str, preset_name: &str) -> usize {
+            let hihat = HiHat::with_config(self.stage.sample_rate, hihat_preset(preset_name));
+            self.stage.add_track(Track::new(name, Box::new(hihat)))
+        }
+        
+        #[wasm_bindgen]
+        pub fn add_tom_track(&mut self, name: &str, preset_name: &str) -> usize {
+            let tom = TomDrum::with_config(self.stage.sample_rate, tom_preset(preset_name));
+            self.stage.add_track(Track::new(name, Box::new(tom)))
+        }
+        
+        #[wasm_bindgen]
+        pub fn add_fm_track(&mut self, name: &str, preset_name: &str) -> usize {
+            let fm = FmSynth::with_config(self.stage.sample_rate, fm_preset(preset_name));
+            self.stage.add_track(Track::new(name, Box::new(fm)))
+        }
+        
+        #[wasm_bindgen]
+        pub fn add_oscillator_track(&mut self, name: &str, frequency_hz: f32) -> usize {
+            let oscillator = Oscillator::new(self.stage.sample_rate, frequency_hz);
+            self.stage.add_track(Track::new(name, Box::new(oscillator)))
+        }
+        
+        #[wasm_bindgen]
+        pub fn remove_track(&mut self, index: usize) -> bool {
+            self.stage.remove_track(index).is_some()
+        }
+        
+        #[wasm_bindgen]
+        pub fn move_track(&mut self, from: usize, to: usize) -> bool {
+            self.stage.move_track(from, to)
+        }
+        
+        #[wasm_bindgen]
+        pub fn track_count(&self) -> usize {
+            self.stage.track_count()
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_track_name(&self, index: usize) -> Option<String> {
+            self.stage.track(index).map(|track| track.name.clone())
+        }
+        
+        #[wasm_bindgen]
+        pub fn set_track_name(&mut self, index: usize, name: &str) {
+            if let Some(track) = self.stage.track_mut(index) {
+                track.name = name.to_string();
+            }
+        }
+        
+        #[wasm_bindgen]
+        pub fn set_track_pan(&mut self, index: usize, pan: f32) {
+            self.stage.set_track_pan(index, pan);
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_track_pan(&self, index: usize) -> f32 {
+            self.stage.get_track_pan(index)
+        }
+        
+        /// Retrigger mode: 0 = hard, 1 = legato, 2 = fade out
+        #[wasm_bindgen]
+        pub fn set_track_retrigger_mode(&mut self, index: usize, mode: u32) {
+            let mode = match mode {
+                1 => RetriggerMode::Legato,
+                2 => RetriggerMode::FadeOut,
+                _ => RetriggerMode::Hard,
+            };
+            self.stage.set_track_retrigger_mode(index, mode);
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_track_retrigger_mode(&self, index: usize) -> u32 {
+            match self.stage.get_track_retrigger_mode(index) {
+                Some(RetriggerMode::Legato) => 1,
+                Some(RetriggerMode::FadeOut) => 2,
+                _ => 0,
+            }
+        }
+        
+        #[wasm_bindgen]
+        pub fn set_track_polyphony(&mut self, index: usize, voices: usize) {
+            self.stage.set_track_polyphony(index, voices);
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_track_polyphony(&self, index: usize) -> usize {
+            self.stage.get_track_polyphony(index)
+        }
+        
+        /// Voice stealing: 0 = oldest, 1 = quietest, 2 = same note
+        #[wasm_bindgen]
+        pub fn set_track_voice_stealing(&mut self, index: usize, stealing: usize) {
+            if let Some(stealing) = VoiceStealing::from_index(stealing) {
+                self.stage.set_track_voice_stealing(index, stealing);
+            }
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_track_voice_stealing(&self, index: usize) -> usize {
+            let stealing = self.stage.get_track_voice_stealing(index).unwrap_or_default();
+            VoiceStealing::ALL.iter().position(|&other| other == stealing).unwrap_or(0)
+        }
+        
+        /// Number of bands in each track EQ and the master EQ
+        #[wasm_bindgen]
+        pub fn get_eq_band_count(&self) -> usize {
+            EQ_BANDS
+        }
+        
+        /// Band type: 0 = bell, 1 = low shelf, 2 = high shelf, 3 = high-pass, 4 = low-pass
+        /// Slope (high-pass and low-pass only): 0 = 12, 1 = 24, 2 = 36, 3 = 48 dB/octave
+        #[wasm_bindgen]
+        #[allow(clippy::too_many_arguments)]
+        pub fn set_track_eq_band(&mut self, index: usize, band: usize, band_type: usize, frequency: f32, gain_db: f32, q: f32, slope: usize, enabled: bool) {
+            self.stage.set_track_eq_band(index, band, eq_band(band_type, frequency, gain_db, q, slope, enabled));
+        }
+        
+        /// [enabled, band type, frequency, gain dB, Q, slope], or empty for an unknown track or band
+        #[wasm_bindgen]
+        pub fn get_track_eq_band(&self, index: usize, band: usize) -> Vec<f32> {
+            self.stage.get_track_eq_band(index, band).map(eq_band_values).unwrap_or_default()
+        }
+        
+        #[wasm_bindgen]
+        #[allow(clippy::too_many_arguments)]
+        pub fn set_master_eq_band(&mut self, band: usize, band_type: usize, frequency: f32, gain_db: f32, q: f32, slope: usize, enabled: bool) {
+            self.stage.set_master_eq_band(band, eq_band(band_type, frequency, gain_db, q, slope, enabled));
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_master_eq_band(&self, band: usize) -> Vec<f32> {
+            self.stage.get_master_eq_band(band).map(eq_band_values).unwrap_or_default()
+        }
+        
+        /// Attack and release are in seconds; detector: 0 = peak, 1 = RMS
+        #[wasm_bindgen]
+        #[allow(clippy::too_many_arguments)]
+        pub fn set_track_compressor(&mut self, index: usize, threshold_db: f32, ratio: f32, knee_db: f32, attack: f32, release: f32, makeup_db: f32, detector: usize, enabled: bool) {
+            let config = compressor_config(threshold_db, ratio, knee_db, attack, release, makeup_db, detector, enabled);
+            self.stage.set_track_compressor(index, config);
+        }
+        
+        /// [enabled, threshold dB, ratio, knee dB, attack, release, makeup dB, detector], or empty for an unknown track
+        #[wasm_bindgen]
+        pub fn get_track_compressor(&self, index: usize) -> Vec<f32> {
+            self.stage.get_track_compressor(index).map(compressor_values).unwrap_or_default()
+        }
+        
+        /// Duck a track by another track's output; pass nothing to key it from its own signal
+        #[wasm_bindgen]
+        pub fn set_track_sidechain(&mut self, index: usize, source: Option<usize>) {
+            self.stage.set_track_sidechain(index, source);
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_track_sidechain(&self, index: usize) -> Option<usize> {
+            self.stage.get_track_sidechain(index)
+        }
+        
+        /// Most gain reduction in dB since the last call, for a meter polled once per frame
+        #[wasm_bindgen]
+        pub fn get_track_compressor_gain_reduction(&mut self, index: usize) -> f32 {
+            self.stage
+                .tracks
+                .get_mut(index)
+                .map_or(0.0, |track| track.compressor.take_peak_gain_reduction_db())
+        }
+        
+        #[wasm_bindgen]
+        #[allow(clippy::too_many_arguments)]
+        pub fn set_master_compressor(&mut self, threshold_db: f32, ratio: f32, knee_db: f32, attack: f32, release: f32, makeup_db: f32, detector: usize, enabled: bool) {
+            let config = compressor_config(threshold_db, ratio, knee_db, attack, release, makeup_db, detector, enabled);
+            self.stage.set_master_compressor(config);
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_master_compressor(&self) -> Vec<f32> {
+            compressor_values(self.stage.get_master_compressor())
+        }
+        
+        /// Key the master compressor from a track's output; pass nothing to key it from the mix
+        #[wasm_bindgen]
+        pub fn set_master_sidechain(&mut self, source: Option<usize>) {
+            self.stage.set_master_sidechain(source);
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_master_sidechain(&self) -> Option<usize> {
+            self.stage.get_master_sidechain()
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_master_compressor_gain_reduction(&mut self) -> f32 {
+            self.stage.compressor.take_peak_gain_reduction_db()
+        }
+        
+        /// Size, damping, width and mix run from 0.0 to 1.0; pre-delay is in seconds
+        #[wasm_bindgen]
+        pub fn set_reverb(&mut self, size: f32, damping: f32, pre_delay: f32, width: f32, mix: f32) {
+            self.stage.set_reverb_config(ReverbConfig::new(size, damping, pre_delay, width, mix));
+        }
+        
+        /// [size, damping, pre-delay, width, mix]
+        #[wasm_bindgen]
+        pub fn get_reverb(&self) -> Vec<f32> {
+            let config = self.stage.get_reverb_config();
+            vec![config.size, config.damping, config.pre_delay, config.width, config.mix]
+        }
+        
+        #[wasm_bindgen]
+        pub fn set_track_reverb_send(&mut self, index: usize, amount: f32) {
+            self.stage.set_track_reverb_send(index, amount);
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_track_reverb_send(&self, index: usize) -> f32 {
+            self.stage.get_track_reverb_send(index)
+        }
+        
+        /// Pan law: 0 = balance, 1 = linear, 2 = constant power, 3 = compromise (-4.5 dB)
+        #[wasm_bindgen]
+        pub fn set_pan_law(&mut self, pan_law: u32) {
+            let pan_law = match pan_law {
+                1 => PanLaw::Linear,
+                2 => PanLaw::ConstantPower,
+                3 => PanLaw::Compromise,
+                _ => PanLaw::Balance,
+            };
+            self.stage.set_pan_law(pan_law);
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_pan_law(&self) -> u32 {
+            match self.stage.get_pan_law() {
+                PanLaw::Balance => 0,
+                PanLaw::Linear => 1,
+                PanLaw::ConstantPower => 2,
+                PanLaw::Compromise => 3,
+            }
+        }
+        
+        /// Reseed every track's noise so the same performance renders identically
+        #[wasm_bindgen]
+        pub fn set_seed(&mut self, seed: u32) {
+            self.stage.set_seed(seed as u64);
+        }
+        
+        #[wasm_bindgen]
+        pub fn trigger_track(&mut self, index: usize) {
+            self.stage.trigger_track(index);
+        }
+        
+        #[wasm_bindgen]
+        pub fn release_track(&mut self, index: usize) {
+            self.stage.release_track(index);
+        }
+        
+        /// Names of the parameters the track's instrument exposes
+        #[wasm_bindgen]
+        pub fn get_track_parameter_names(&self, index: usize) -> Vec<String> {
+            self.stage
+                .track(index)
+                .map(|track| track.instrument.parameters().iter().map(|info| info.name.to_string()).collect())
+                .unwrap_or_default()
+        }
+        
+        #[wasm_bindgen]
+        pub fn set_track_parameter(&mut self, index: usize, name: &str, value: f32) -> bool {
+            self.stage.set_track_parameter(index, name, value)
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_track_parameter(&self, index: usize, name: &str) -> Option<f32> {
+            self.stage.get_track_parameter(index, name)
+        }
+        
+        /// Shape: 0 = sine, 1 = triangle, 2 = saw, 3 = square, 4 = sample and hold, 5 = smooth random
+        /// With `synced` the rate is the cycle length in beats, otherwise it is in Hz.
+        #[wasm_bindgen]
+        pub fn add_lfo(&mut self, shape: usize, rate: f32, synced: bool, retrigger: bool) -> usize {
+            let shape = LfoShape::from_index(shape).unwrap_or(LfoShape::Sine);
+            let rate = if synced { LfoRate::Beats(rate) } else { LfoRate::Hertz(rate) };
+            let mode = if retrigger { LfoMode::Retrigger } else { LfoMode::FreeRunning };
+            self.stage.add_lfo(Lfo::new(shape, rate).with_mode(mode))
+        }
+        
+        #[wasm_bindgen]
+        pub fn remove_lfo(&mut self, index: usize) -> bool {
+            self.stage.remove_lfo(index).is_some()
+        }
+        
+        #[wasm_bindgen]
+        pub fn lfo_count(&self) -> usize {
+            self.stage.lfo_count()
+        }
+        
+        #[wasm_bindgen]
+        pub fn set_lfo_shape(&mut self, index: usize, shape: usize) {
+            if let (Some(lfo), Some(shape)) = (self.stage.lfo_mut(index), LfoShape::from_index(shape)) {
+                lfo.shape = shape;
+            }
+        }
+        
+        #[wasm_bindgen]
+        pub fn set_lfo_rate_hz(&mut self, index: usize, frequency: f32) {
+            if let Some(lfo) = self.stage.lfo_mut(index) {
+                lfo.rate = LfoRate::Hertz(frequency);
+            }
+        }
+        
+        /// Tempo-synced rate as the length of one cycle in beats (4.0 = one bar)
+        #[wasm_bindgen]
+        pub fn set_lfo_rate_beats(&mut self, index: usize, beats: f32) {
+            if let Some(lfo) = self.stage.lfo_mut(index) {
+                lfo.rate = LfoRate::Beats(beats);
+            }
+        }
+        
+        #[wasm_bindgen]
+        pub fn set_lfo_retrigger(&mut self, index: usize, retrigger: bool) {
+            if let Some(lfo) = self.stage.lfo_mut(index) {
+                lfo.mode = if retrigger { LfoMode::Retrigger } else { LfoMode::FreeRunning };
+            }
+        }
+        
+        /// Current LFO output from -1.0 to 1.0, e.g. for drawing
+        #[wasm_bindgen]
+        pub fn get_lfo_value(&self, index: usize) -> f32 {
+            self.stage.lfo(index).map_or(0.0, |lfo| lfo.value())
+        }
+        
+        /// Add an envelope that runs on every hit of a track, for use as a modulation source
+        #[wasm_bindgen]
+        pub fn add_mod_envelope(&mut self, track: usize, attack: f32, decay: f32, sustain: f32, release: f32) -> usize {
+            let envelope = BreakpointEnvelope::from_adsr(ADSRConfig::new(attack, decay, sustain, release));
+            self.stage.add_mod_envelope(track, envelope)
+        }
+        
+        /// Add a breakpoint modulation envelope: segment `i` reaches `levels[i]` after `durations[i]` seconds
+        /// Segments `loop_start..=loop_end` repeat for as long as the envelope runs.
+        #[wasm_bindgen]
+        pub fn add_mod_breakpoint_envelope(
+            &mut self,
+            track: usize,
+            durations: Vec<f32>,
+            levels: Vec<f32>,
+            exponential: bool,
+            loop_start: Option<usize>,
+            loop_end: Option<usize>,
+        ) -> usize {
+            let curve = if exponential { EnvelopeCurve::Exponential } else { EnvelopeCurve::Linear };
+            let segments: Vec<EnvelopeSegment> = durations
+                .iter()
+                .zip(levels.iter())
+                .map(|(&duration, &level)| EnvelopeSegment::new(duration, level, curve))
+                .collect();
+            let mut envelope = BreakpointEnvelope::new(&segments);
+            if let (Some(start), Some(end)) = (loop_start, loop_end) {
+                envelope.set_loop(Some((start, end)));
+            }
+            self.stage.add_mod_envelope(track, envelope)
+        }
+        
+        #[wasm_bindgen]
+        pub fn remove_mod_envelope(&mut self, index: usize) -> bool {
+            self.stage.remove_mod_envelope(index)
+        }
+        
+        /// Set a macro knob (0-7) from 0.0 to 1.0
+        #[wasm_bindgen]
+        pub fn set_macro(&mut self, index: usize, value: f32) {
+            self.stage.set_macro(index, value);
+        }
+        
+        #[wasm_bindgen]
+        pub fn get_macro(&self, index: usize) -> f32 {
+            self.stage.get_macro(index)
+        }
+        
+        /// Route a source to a track parameter, or to its pan with the parameter "pan"
+        /// Source: 0 = LFO, 1 = envelope, 2 = velocity, 3 = step index, 4 = random, 5 = macro;
+        /// `source_index` picks the LFO, envelope or macro. Amount is a fraction of the parameter's range.
+        #[wasm_bindgen]
+        pub fn add_mod_route(
+            &mut self,
+            source: u32,
+            source_index: usize,
+            track: usize,
+            parameter: &str,
+            amount: f32,
+        ) -> Option<usize> {
+            let destination = self
+                .stage
+                .parameter_destination(track, parameter)
+                .or_else(|| (parameter == "pan").then_some(ModDestination::Pan(track)))?;
+            self.stage.add_mod_route(mod_source(source, source_index)?, destination, amount)
+        }
+        
+        /// Route a source to a Stage-wide parameter: "saturation" or "limiter_threshold"
+        #[wasm_bindgen]
+        pub fn add_stage_mod_route(&mut self, source: u32, source_index: usize, parameter: &str, amount: f32) -> Option<usize> {
+            let destination = self.stage.stage_destination(parameter)?;
+            self.stage.add_mod_route(mod_source(source, source_index)?, destination, amount)
+        }
+        
+        #[wasm_bindgen]
+        pub fn remove_mod_route(&mut self, index: usize) -> bool {
+            self.stage.remove_mod_route(index).is_some()
+        }
+        
+        #[wasm_bindgen]
+        pub fn set_mod_route_amount(&mut self, index: usize, amount: f32) {
+            self.stage.set_mod_route_amount(index, amount);
+        }
+        
+        #[wasm_bindgen]
+        pub fn mod_route_count(&self) -> usize {
+            self.stage.mod_route_count()
+        }
+        
+        #[wasm_bindgen]
+        pub fn sequencer_set_step_velocity(&mut self, track: usize, step: usize, velocity: f32) {
+            if let Some(track) = self.stage.track_mut(track) {
+                track.set_step_velocity(step, velocity);
+            }
+        }
+        
+        #[wasm_bindgen]
+        pub fn sequencer_get_step_velocity(&self, track: usize, step: usize) -> f32 {
+            self.stage.track(track).map_or(1.0, |track| track.get_step_velocity(step))
+        }
     }
 
     #[wasm_bindgen]
@@ -400,29 +1118,24 @@ pub mod web {
 
         #[wasm_bindgen]
         pub fn new_with_preset(sample_rate: f32, preset_name: &str) -> WasmKickDrum {
-            let config = match preset_name {
-                "punchy" => KickConfig::punchy(),
-                "deep" => KickConfig::deep(),
-                "tight" => KickConfig::tight(),
-                _ => KickConfig::default(),
-            };
+            let config = kick_preset(preset_name);
             WasmKickDrum {
                 kick_drum: KickDrum::with_config(sample_rate, config),
             }
         }
 
         #[wasm_bindgen]
-        pub fn trigger(&mut self, time: f32) {
+        pub fn trigger(&mut self, time: f64) {
             self.kick_drum.trigger(time);
         }
 
         #[wasm_bindgen]
-        pub fn release(&mut self, time: f32) {
+        pub fn release(&mut self, time: f64) {
             self.kick_drum.release(time);
         }
 
         #[wasm_bindgen]
-        pub fn tick(&mut self, current_time: f32) -> f32 {
+        pub fn tick(&mut self, current_time: f64) -> f32 {
             self.kick_drum.tick(current_time)
         }
 
@@ -520,32 +1233,24 @@ pub mod web {
 
         #[wasm_bindgen]
         pub fn new_with_preset(sample_rate: f32, preset_name: &str) -> WasmHiHat {
-            let config = match preset_name {
-                "closed_default" => HiHatConfig::closed_default(),
-                "open_default" => HiHatConfig::open_default(),
-                "closed_tight" => HiHatConfig::closed_tight(),
-                "open_bright" => HiHatConfig::open_bright(),
-                "closed_dark" => HiHatConfig::closed_dark(),
-                "open_long" => HiHatConfig::open_long(),
-                _ => HiHatConfig::closed_default(),
-            };
+            let config = hihat_preset(preset_name);
             WasmHiHat {
                 hihat: HiHat::with_config(sample_rate, config),
             }
         }
 
         #[wasm_bindgen]
-        pub fn trigger(&mut self, time: f32) {
+        pub fn trigger(&mut self, time: f64) {
             self.hihat.trigger(time);
         }
 
         #[wasm_bindgen]
-        pub fn release(&mut self, time: f32) {
+        pub fn release(&mut self, time: f64) {
             self.hihat.release(time);
         }
 
         #[wasm_bindgen]
-        pub fn tick(&mut self, current_time: f32) -> f32 {
+        pub fn tick(&mut self, current_time: f64) -> f32 {
             self.hihat.tick(current_time)
         }
 
@@ -590,6 +1295,7 @@ pub mod web {
         }
 
         #[wasm_bindgen]
+        #[allow(clippy::too_many_arguments)]
         pub fn set_config(
             &mut self,
             base_frequency: f32,
@@ -599,7 +1305,20 @@ pub mod web {
             attack_time: f32,
             volume: f32,
             is_open: bool,
+            filter_type: Option<usize>,
+            filter_cutoff: Option<f32>,
+            filter_resonance: Option<f32>,
+            filter_env_amount: Option<f32>,
+            filter_env_decay: Option<f32>,
         ) {
+            let filter = filter_config(
+                self.hihat.config.filter,
+                filter_type,
+                filter_cutoff,
+                filter_resonance.or(Some(resonance)),
+                filter_env_amount,
+                filter_env_decay,
+            );
             let config = HiHatConfig::new(
                 base_frequency,
                 resonance,
@@ -608,7 +1327,8 @@ pub mod web {
                 attack_time,
                 volume,
                 is_open,
-            );
+            )
+            .with_filter(filter);
             self.hihat.set_config(config);
         }
     }
@@ -629,30 +1349,24 @@ pub mod web {
 
         #[wasm_bindgen]
         pub fn new_with_preset(sample_rate: f32, preset_name: &str) -> WasmSnareDrum {
-            let config = match preset_name {
-                "crispy" => SnareConfig::crispy(),
-                "deep" => SnareConfig::deep(),
-                "tight" => SnareConfig::tight(),
-                "fat" => SnareConfig::fat(),
-                _ => SnareConfig::default(),
-            };
+            let config = snare_preset(preset_name);
             WasmSnareDrum {
                 snare_drum: SnareDrum::with_config(sample_rate, config),
             }
         }
 
         #[wasm_bindgen]
-        pub fn trigger(&mut self, time: f32) {
+        pub fn trigger(&mut self, time: f64) {
             self.snare_drum.trigger(time);
         }
 
         #[wasm_bindgen]
-        pub fn release(&mut self, time: f32) {
+        pub fn release(&mut self, time: f64) {
             self.snare_drum.release(time);
         }
 
         #[wasm_bindgen]
-        pub fn tick(&mut self, current_time: f32) -> f32 {
+        pub fn tick(&mut self, current_time: f64) -> f32 {
             self.snare_drum.tick(current_time)
         }
 
@@ -697,6 +1411,7 @@ pub mod web {
         }
 
         #[wasm_bindgen]
+        #[allow(clippy::too_many_arguments)]
         pub fn set_config(
             &mut self,
             snare_frequency: f32,
@@ -706,7 +1421,20 @@ pub mod web {
             decay_time: f32,
             pitch_drop: f32,
             volume: f32,
+            filter_type: Option<usize>,
+            filter_cutoff: Option<f32>,
+            filter_resonance: Option<f32>,
+            filter_env_amount: Option<f32>,
+            filter_env_decay: Option<f32>,
         ) {
+            let filter = filter_config(
+                self.snare_drum.config.filter,
+                filter_type,
+                filter_cutoff,
+                filter_resonance,
+                filter_env_amount,
+                filter_env_decay,
+            );
             let config = SnareConfig::new(
                 snare_frequency,
                 tonal_amount,
@@ -715,7 +1443,8 @@ pub mod web {
                 decay_time,
                 pitch_drop,
                 volume,
-            );
+            )
+            .with_filter(filter);
             self.snare_drum.set_config(config);
         }
     }
@@ -736,30 +1465,24 @@ pub mod web {
 
         #[wasm_bindgen]
         pub fn new_with_preset(sample_rate: f32, preset_name: &str) -> WasmTomDrum {
-            let config = match preset_name {
-                "high_tom" => TomConfig::high_tom(),
-                "mid_tom" => TomConfig::mid_tom(),
-                "low_tom" => TomConfig::low_tom(),
-                "floor_tom" => TomConfig::floor_tom(),
-                _ => TomConfig::default(),
-            };
+            let config = tom_preset(preset_name);
             WasmTomDrum {
                 tom_drum: TomDrum::with_config(sample_rate, config),
             }
         }
 
         #[wasm_bindgen]
-        pub fn trigger(&mut self, time: f32) {
+        pub fn trigger(&mut self, time: f64) {
             self.tom_drum.trigger(time);
         }
 
         #[wasm_bindgen]
-        pub fn release(&mut self, time: f32) {
+        pub fn release(&mut self, time: f64) {
             self.tom_drum.release(time);
         }
 
         #[wasm_bindgen]
-        pub fn tick(&mut self, current_time: f32) -> f32 {
+        pub fn tick(&mut self, current_time: f64) -> f32 {
             self.tom_drum.tick(current_time)
         }
 
@@ -799,6 +1522,7 @@ pub mod web {
         }
 
         #[wasm_bindgen]
+        #[allow(clippy::too_many_arguments)]
         pub fn set_config(
             &mut self,
             tom_frequency: f32,
@@ -807,7 +1531,20 @@ pub mod web {
             decay_time: f32,
             pitch_drop: f32,
             volume: f32,
+            filter_type: Option<usize>,
+            filter_cutoff: Option<f32>,
+            filter_resonance: Option<f32>,
+            filter_env_amount: Option<f32>,
+            filter_env_decay: Option<f32>,
         ) {
+            let filter = filter_config(
+                self.tom_drum.config.filter,
+                filter_type,
+                filter_cutoff,
+                filter_resonance,
+                filter_env_amount,
+                filter_env_decay,
+            );
             let config = TomConfig::new(
                 tom_frequency,
                 tonal_amount,
@@ -815,8 +1552,86 @@ pub mod web {
                 decay_time,
                 pitch_drop,
                 volume,
-            );
+            )
+            .with_filter(filter);
             self.tom_drum.set_config(config);
         }
     }
+    #[wasm_bindgen]
+    pub struct WasmFmSynth {
+        fm_synth: FmSynth,
+    }
+
+    #[wasm_bindgen]
+    impl WasmFmSynth {
+        #[wasm_bindgen(constructor)]
+        pub fn new(sample_rate: f32) -> WasmFmSynth {
+            WasmFmSynth {
+                fm_synth: FmSynth::new(sample_rate),
+            }
+        }
+
+        /// Presets: "electric_piano", "bell", "metallic", "bass"
+        #[wasm_bindgen]
+        pub fn new_with_preset(sample_rate: f32, preset_name: &str) -> WasmFmSynth {
+            WasmFmSynth {
+                fm_synth: FmSynth::with_config(sample_rate, fm_preset(preset_name)),
+            }
+        }
+
+        #[wasm_bindgen]
+        pub fn trigger(&mut self, time: f64) {
+            self.fm_synth.trigger(time);
+        }
+
+        #[wasm_bindgen]
+        pub fn release(&mut self, time: f64) {
+            self.fm_synth.release(time);
+        }
+
+        #[wasm_bindgen]
+        pub fn tick(&mut self, current_time: f64) -> f32 {
+            self.fm_synth.tick(current_time)
+        }
+
+        #[wasm_bindgen]
+        pub fn is_active(&self) -> bool {
+            self.fm_synth.is_active()
+        }
+
+        #[wasm_bindgen]
+        pub fn set_volume(&mut self, volume: f32) {
+            self.fm_synth.set_volume(volume);
+        }
+
+        #[wasm_bindgen]
+        pub fn set_frequency(&mut self, frequency: f32) {
+            self.fm_synth.set_frequency(frequency);
+        }
+
+        /// Algorithm: 0 = stack, 1 = two stacks, 2 = three pairs, 3 = many to one, 4 = one to many, 5 = additive
+        #[wasm_bindgen]
+        pub fn set_algorithm(&mut self, algorithm: usize) {
+            if let Some(algorithm) = FmAlgorithm::from_index(algorithm) {
+                self.fm_synth.set_algorithm(algorithm);
+            }
+        }
+
+        #[wasm_bindgen]
+        #[allow(clippy::too_many_arguments)]
+        pub fn set_operator(
+            &mut self,
+            index: usize,
+            ratio: f32,
+            level: f32,
+            feedback: f32,
+            attack: f32,
+            decay: f32,
+            sustain: f32,
+            release: f32,
+        ) {
+            let envelope = ADSRConfig::new(attack, decay, sustain, release);
+            self.fm_synth.set_operator(index, OperatorConfig::new(ratio, level, feedback, envelope));
+        }
+    }
 }
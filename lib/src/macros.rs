@@ -0,0 +1,36 @@
+//! Small helper macros shared across the crate
+
+/// Define a fieldless enum whose variants can be listed and looked up by position
+/// Adds `ALL` (every variant in declaration order), `from_index` and `index`, which the
+/// bindings use to pass settings around as plain numbers.
+macro_rules! indexed_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident
+            ),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant
+            ),+
+        }
+
+        impl $name {
+            pub const ALL: [$name; [$(stringify!($variant)),+].len()] = [$($name::$variant),+];
+
+            pub fn from_index(index: usize) -> Option<Self> {
+                Self::ALL.get(index).copied()
+            }
+
+            pub fn index(self) -> usize {
+                self as usize
+            }
+        }
+    };
+}
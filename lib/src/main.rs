@@ -6,14 +6,14 @@ use std::io::{self, Write};
 
 // Import the platform abstraction and audio engine
 use libgooey::gen::oscillator::Oscillator;
-use libgooey::platform::{AudioEngine, AudioOutput, CpalOutput};
+use libgooey::platform::{AudioEngine, AudioOutput, CpalOutput, StageCommand};
 
 // Native binary entry point for the oscillator engine
 
 #[cfg(feature = "native")]
 fn main() -> anyhow::Result<()> {
     // Create the audio engine
-    let audio_engine = AudioEngine::new(44100.0);
+    let mut audio_engine = AudioEngine::new(44100.0);
 
     // Configure the stage with an oscillator
     audio_engine.with_stage(|stage| {
@@ -29,7 +29,10 @@ fn main() -> anyhow::Result<()> {
     // Create and configure the CPAL output
     let mut cpal_output = CpalOutput::new();
     cpal_output.initialize(44100.0)?;
-    cpal_output.create_stream_with_stage(audio_engine.stage(), audio_engine.audio_state())?;
+    let processor = audio_engine
+        .take_processor()
+        .ok_or_else(|| anyhow::anyhow!("Stage processor already taken"))?;
+    cpal_output.create_stream(processor)?;
 
     // Start the audio stream
     cpal_output.start()?;
@@ -49,9 +52,7 @@ fn main() -> anyhow::Result<()> {
             }
             "2" => {
                 println!("Triggering kick!");
-                let mut stage = audio_engine.stage_mut();
-                stage.trigger_kick();
-                // audio_engine.trigger_kick();
+                let _ = audio_engine.send(StageCommand::TriggerKick);
             }
             "q" => {
                 println!("Quitting...");
@@ -0,0 +1,381 @@
+//! Modulation matrix: sources routed with an amount to named parameters
+//! The Stage evaluates every route once per control block and writes the result through the
+//! same setters the UI uses. Each modulated destination keeps an unmodulated base value, and the
+//! routes on it add their offsets around that base.
+
+use crate::envelope::BreakpointEnvelope;
+use crate::gen::lfo::{Lfo, LfoMode};
+use crate::gen::noise::Rng;
+use crate::track::STEPS_PER_PATTERN;
+
+/// Number of macro knobs available as modulation sources
+pub const MACRO_COUNT: usize = 8;
+
+/// Where a route's modulation comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModSource {
+    /// An LFO by index, -1.0 to 1.0
+    Lfo(usize),
+    /// A modulation envelope by index, 0.0 to 1.0
+    Envelope(usize),
+    /// Velocity of the latest hit on the destination's track, 0.0 to 1.0
+    Velocity,
+    /// Position in the bar, 0.0 on the first step to 1.0 on the last
+    StepIndex,
+    /// A new random value for every hit on the destination's track, -1.0 to 1.0
+    Random,
+    /// A macro knob by index, 0.0 to 1.0
+    Macro(usize),
+}
+
+/// What a route modulates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModDestination {
+    /// A named parameter of a track's instrument
+    Parameter { track: usize, name: &'static str },
+    /// A track's pan position
+    Pan(usize),
+    /// A named Stage-wide parameter, see `STAGE_PARAMETERS`
+    Stage(&'static str),
+}
+
+impl ModDestination {
+    /// The track this destination belongs to, if any
+    pub fn track(&self) -> Option<usize> {
+        match *self {
+            ModDestination::Parameter { track, .. } | ModDestination::Pan(track) => Some(track),
+            ModDestination::Stage(_) => None,
+        }
+    }
+
+    fn track_mut(&mut self) -> Option<&mut usize> {
+        match self {
+            ModDestination::Parameter { track, .. } | ModDestination::Pan(track) => Some(track),
+            ModDestination::Stage(_) => None,
+        }
+    }
+
+    /// Whether hits on `track` count as hits for this destination
+    /// Stage-wide destinations follow every hit.
+    fn follows(&self, track: usize) -> bool {
+        self.track().is_none_or(|own| own == track)
+    }
+}
+
+/// One connection in the matrix
+#[derive(Debug, Clone, Copy)]
+pub struct ModRoute {
+    pub source: ModSource,
+    pub destination: ModDestination,
+    /// Modulation amount as a fraction of the destination's range, from -1.0 to 1.0
+    pub amount: f32,
+    /// Unmodulated value of the destination
+    base: f32,
+    min: f32,
+    max: f32,
+    /// Per-hit source values, refreshed whenever the destination's track is hit
+    velocity: f32,
+    random: f32,
+}
+
+impl ModRoute {
+    pub fn new(source: ModSource, destination: ModDestination, amount: f32) -> Self {
+        Self {
+            source,
+            destination,
+            amount: amount.clamp(-1.0, 1.0),
+            base: 0.0,
+            min: 0.0,
+            max: 1.0,
+            velocity: 1.0,
+            random: 0.0,
+        }
+    }
+
+    pub fn base(&self) -> f32 {
+        self.base
+    }
+
+    /// Offset from the base for a source value
+    fn offset(&self, source_value: f32) -> f32 {
+        source_value * self.amount * (self.max - self.min)
+    }
+}
+
+/// An envelope that runs whenever its track is hit, used only as a modulation source
+#[derive(Debug, Clone, Copy)]
+pub struct ModEnvelope {
+    pub envelope: BreakpointEnvelope,
+    /// Track whose hits trigger the envelope; None leaves it idle
+    pub track: Option<usize>,
+    level: f32,
+}
+
+impl ModEnvelope {
+    pub fn new(track: usize, envelope: BreakpointEnvelope) -> Self {
+        Self {
+            envelope,
+            track: Some(track),
+            level: 0.0,
+        }
+    }
+
+    /// Level at the start of the current control block
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+}
+
+/// Modulation sources and the routes from them to destinations
+pub struct ModMatrix {
+    pub lfos: Vec<Lfo>,
+    pub envelopes: Vec<ModEnvelope>,
+    /// Macro knob positions, 0.0 to 1.0
+    pub macros: [f32; MACRO_COUNT],
+    routes: Vec<ModRoute>,
+    /// Step played most recently
+    step: usize,
+    rng: Rng,
+}
+
+impl ModMatrix {
+    pub fn new() -> Self {
+        Self {
+            lfos: Vec::new(),
+            envelopes: Vec::new(),
+            macros: [0.0; MACRO_COUNT],
+            routes: Vec::new(),
+            step: 0,
+            rng: Rng::new(0),
+        }
+    }
+
+    pub fn routes(&self) -> &[ModRoute] {
+        &self.routes
+    }
+
+    pub fn route(&self, index: usize) -> Option<&ModRoute> {
+        self.routes.get(index)
+    }
+
+    /// Add a route whose destination spans `min..=max` and currently sits at `base`
+    pub(crate) fn add_route(&mut self, mut route: ModRoute, base: f32, min: f32, max: f32) -> usize {
+        route.base = base;
+        route.min = min;
+        route.max = max;
+        self.routes.push(route);
+        self.routes.len() - 1
+    }
+
+    pub(crate) fn remove_route(&mut self, index: usize) -> Option<ModRoute> {
+        if index < self.routes.len() {
+            Some(self.routes.remove(index))
+        } else {
+            None
+        }
+    }
+
+    pub fn set_amount(&mut self, index: usize, amount: f32) {
+        if let Some(route) = self.routes.get_mut(index) {
+            route.amount = amount.clamp(-1.0, 1.0);
+        }
+    }
+
+    pub fn set_macro(&mut self, index: usize, value: f32) {
+        if let Some(slot) = self.macros.get_mut(index) {
+            *slot = value.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Whether any route modulates `destination`
+    pub fn is_modulated(&self, destination: &ModDestination) -> bool {
+        self.routes.iter().any(|route| route.destination == *destination)
+    }
+
+    /// Unmodulated value of a destination, if any route modulates it
+    pub fn base(&self, destination: &ModDestination) -> Option<f32> {
+        self.routes
+            .iter()
+            .find(|route| route.destination == *destination)
+            .map(|route| route.base)
+    }
+
+    /// Move the unmodulated value of a destination
+    pub(crate) fn set_base(&mut self, destination: &ModDestination, value: f32) {
+        for route in &mut self.routes {
+            if route.destination == *destination {
+                route.base = value;
+            }
+        }
+    }
+
+    /// Modulated value of the destination of route `index`, summing every route on it
+    /// Only the first route on each destination reports it, so each is written once per block.
+    pub(crate) fn destination_value(&self, index: usize) -> Option<(ModDestination, f32)> {
+        let route = self.routes.get(index)?;
+        if self.routes[..index].iter().any(|other| other.destination == route.destination) {
+            return None;
+        }
+        let offset: f32 = self.routes[index..]
+            .iter()
+            .filter(|other| other.destination == route.destination)
+            .map(|other| other.offset(self.source_value(other)))
+            .sum();
+        Some((route.destination, (route.base + offset).clamp(route.min, route.max)))
+    }
+
+    /// Current value of a route's source
+    fn source_value(&self, route: &ModRoute) -> f32 {
+        match route.source {
+            ModSource::Lfo(index) => self.lfos.get(index).map_or(0.0, |lfo| lfo.value()),
+            ModSource::Envelope(index) => self.envelopes.get(index).map_or(0.0, |envelope| envelope.level),
+            ModSource::Velocity => route.velocity,
+            ModSource::StepIndex => self.step as f32 / (STEPS_PER_PATTERN - 1) as f32,
+            ModSource::Random => route.random,
+            ModSource::Macro(index) => self.macros.get(index).copied().unwrap_or(0.0),
+        }
+    }
+
+    /// Record that the sequencer has played `step`
+    pub(crate) fn on_step(&mut self, step: usize) {
+        self.step = step;
+    }
+
+    /// Update the per-hit sources after `track` was hit at `time`
+    pub(crate) fn on_trigger(&mut self, track: usize, velocity: f32, time: f64) {
+        // One random value per hit, so the stream does not depend on how many routes there are
+        let random = self.rng.next_bipolar();
+        for route in &mut self.routes {
+            if route.destination.follows(track) {
+                route.velocity = velocity;
+                route.random = random;
+            }
+        }
+
+        // Retriggering LFOs restart once per hit on anything they modulate
+        for (index, lfo) in self.lfos.iter_mut().enumerate() {
+            let follows = self
+                .routes
+                .iter()
+                .any(|route| route.source == ModSource::Lfo(index) && route.destination.follows(track));
+            if follows && lfo.mode == LfoMode::Retrigger {
+                lfo.reset();
+            }
+        }
+
+        for envelope in &mut self.envelopes {
+            if envelope.track == Some(track) {
+                envelope.envelope.trigger(time);
+            }
+        }
+    }
+
+    /// Sample the envelopes for a control block starting at `time`
+    pub(crate) fn update(&mut self, time: f64) {
+        for envelope in &mut self.envelopes {
+            envelope.level = envelope.envelope.get_amplitude(time);
+        }
+    }
+
+    /// Move the LFOs on by one control block
+    pub(crate) fn advance(&mut self, seconds: f64, bpm: f32) {
+        for lfo in &mut self.lfos {
+            lfo.advance(seconds, bpm);
+        }
+    }
+
+    /// Restart the random sources from a seed
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(!seed);
+        for (index, lfo) in self.lfos.iter_mut().enumerate() {
+            lfo.set_seed(!seed.wrapping_add(index as u64 + 1));
+        }
+    }
+
+    /// Shift track indices at or after `index` up by one after a track was inserted there
+    pub(crate) fn track_inserted(&mut self, index: usize) {
+        let shift = |track: &mut usize| {
+            if *track >= index {
+                *track += 1;
+            }
+        };
+        for route in &mut self.routes {
+            if let Some(track) = route.destination.track_mut() {
+                shift(track);
+            }
+        }
+        for envelope in &mut self.envelopes {
+            if let Some(track) = envelope.track.as_mut() {
+                shift(track);
+            }
+        }
+    }
+
+    /// Drop routes to a removed track and shift later track indices down
+    pub(crate) fn track_removed(&mut self, index: usize) {
+        self.routes.retain(|route| route.destination.track() != Some(index));
+        let shift = |track: &mut usize| {
+            if *track > index {
+                *track -= 1;
+            }
+        };
+        for route in &mut self.routes {
+            if let Some(track) = route.destination.track_mut() {
+                shift(track);
+            }
+        }
+        for envelope in &mut self.envelopes {
+            if envelope.track == Some(index) {
+                envelope.track = None;
+            } else if let Some(track) = envelope.track.as_mut() {
+                shift(track);
+            }
+        }
+    }
+
+    /// Follow a track that moved from `from` to `to`
+    pub(crate) fn track_moved(&mut self, from: usize, to: usize) {
+        let remap = |track: &mut usize| {
+            *track = if *track == from {
+                to
+            } else if from < to && *track > from && *track <= to {
+                *track - 1
+            } else if to < from && *track >= to && *track < from {
+                *track + 1
+            } else {
+                *track
+            };
+        };
+        for route in &mut self.routes {
+            if let Some(track) = route.destination.track_mut() {
+                remap(track);
+            }
+        }
+        for envelope in &mut self.envelopes {
+            if let Some(track) = envelope.track.as_mut() {
+                remap(track);
+            }
+        }
+    }
+
+    /// Shift source indices down after the LFO or envelope at `index` was removed
+    /// Routes from the removed source must already be gone.
+    pub(crate) fn source_removed(&mut self, removed: ModSource) {
+        for route in &mut self.routes {
+            match (&mut route.source, removed) {
+                (ModSource::Lfo(index), ModSource::Lfo(gone)) | (ModSource::Envelope(index), ModSource::Envelope(gone))
+                    if *index > gone =>
+                {
+                    *index -= 1;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Default for ModMatrix {
+    fn default() -> Self {
+        Self::new()
+    }
+}
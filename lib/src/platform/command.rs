@@ -0,0 +1,268 @@
+//! Commands sent from the control thread to the audio thread, and snapshots sent back
+//! The audio side owns the Stage outright; everything else talks to it through these channels.
+
+use super::spsc::{Consumer, Producer};
+use super::triple_buffer::Writer;
+use crate::effects::compressor::CompressorConfig;
+use crate::effects::eq::EqBand;
+use crate::effects::limiter::LimiterMode;
+use crate::effects::pan::PanLaw;
+use crate::effects::reverb::ReverbConfig;
+use crate::envelope::RetriggerMode;
+use crate::gen::lfo::{LfoMode, LfoRate, LfoShape};
+use crate::instruments::{HiHatConfig, KickConfig, SnareConfig, TomConfig, VoiceStealing};
+use crate::stage::Stage;
+use crate::track::Track;
+
+/// A change to apply to the Stage at the start of the next audio buffer
+#[derive(Debug)]
+pub enum StageCommand {
+    TriggerAll,
+    ReleaseAll,
+    TriggerInstrument(usize),
+    ReleaseInstrument(usize),
+    SetInstrumentVolume { index: usize, volume: f32 },
+    TriggerKick,
+    TriggerSnare,
+    TriggerHiHat,
+    TriggerTom,
+    TriggerTrack(usize),
+    ReleaseTrack(usize),
+    /// Build the track on the control thread; it is only moved on the audio thread
+    AddTrack(Box<Track>),
+    InsertTrack { index: usize, track: Box<Track> },
+    /// The removed track is handed back to the control thread to be freed
+    RemoveTrack(usize),
+    MoveTrack { from: usize, to: usize },
+    SetTrackPan { index: usize, pan: f32 },
+    SetPanLaw(PanLaw),
+    SetTrackRetriggerMode { index: usize, mode: RetriggerMode },
+    SetTrackPolyphony { index: usize, voices: usize },
+    SetTrackVoiceStealing { index: usize, stealing: VoiceStealing },
+    SetTrackEqBand { index: usize, band: usize, settings: EqBand },
+    SetMasterEqBand { band: usize, settings: EqBand },
+    SetTrackCompressor { index: usize, config: CompressorConfig },
+    SetTrackSidechain { index: usize, source: Option<usize> },
+    SetMasterCompressor(CompressorConfig),
+    SetMasterSidechain(Option<usize>),
+    SetReverb(ReverbConfig),
+    SetTrackReverbSend { index: usize, amount: f32 },
+    SetLfoShape { index: usize, shape: LfoShape },
+    SetLfoRate { index: usize, rate: LfoRate },
+    SetLfoMode { index: usize, mode: LfoMode },
+    SetModRouteAmount { index: usize, amount: f32 },
+    SetMacro { index: usize, value: f32 },
+    SetKickConfig(KickConfig),
+    SetSnareConfig(SnareConfig),
+    SetHiHatConfig(HiHatConfig),
+    SetTomConfig(TomConfig),
+    SequencerPlay,
+    SequencerStop,
+    SequencerReset,
+    SequencerClearAll,
+    SequencerSetDefaultPatterns,
+    SequencerSetStep { track: usize, step: usize, enabled: bool },
+    SequencerSetBpm(f32),
+    SetSaturation(f32),
+    SetLimiterThreshold(f32),
+    SetLimiterMode(LimiterMode),
+    SetLimiterAttack(f32),
+    SetLimiterRelease(f32),
+    SetLimiterLookahead(f32),
+}
+
+impl StageCommand {
+    /// Apply this command to the stage
+    pub fn apply(self, stage: &mut Stage) {
+        match self {
+            StageCommand::TriggerAll => stage.trigger_all(),
+            StageCommand::ReleaseAll => stage.release_all(),
+            StageCommand::TriggerInstrument(index) => stage.trigger_instrument(index),
+            StageCommand::ReleaseInstrument(index) => stage.release_instrument(index),
+            StageCommand::SetInstrumentVolume { index, volume } => stage.set_instrument_volume(index, volume),
+            StageCommand::TriggerKick => stage.trigger_kick(),
+            StageCommand::TriggerSnare => stage.trigger_snare(),
+            StageCommand::TriggerHiHat => stage.trigger_hihat(),
+            StageCommand::TriggerTom => stage.trigger_tom(),
+            StageCommand::TriggerTrack(index) => stage.trigger_track(index),
+            StageCommand::ReleaseTrack(index) => stage.release_track(index),
+            StageCommand::AddTrack(track) => {
+                stage.push_track(track);
+            }
+            StageCommand::InsertTrack { index, track } => {
+                stage.insert_boxed_track(index, track);
+            }
+            StageCommand::RemoveTrack(index) => {
+                stage.remove_track(index);
+            }
+            StageCommand::MoveTrack { from, to } => {
+                stage.move_track(from, to);
+            }
+            StageCommand::SetTrackPan { index, pan } => stage.set_track_pan(index, pan),
+            StageCommand::SetPanLaw(pan_law) => stage.set_pan_law(pan_law),
+            StageCommand::SetTrackRetriggerMode { index, mode } => stage.set_track_retrigger_mode(index, mode),
+            StageCommand::SetTrackPolyphony { index, voices } => stage.set_track_polyphony(index, voices),
+            StageCommand::SetTrackVoiceStealing { index, stealing } => stage.set_track_voice_stealing(index, stealing),
+            StageCommand::SetTrackEqBand { index, band, settings } => stage.set_track_eq_band(index, band, settings),
+            StageCommand::SetMasterEqBand { band, settings } => stage.set_master_eq_band(band, settings),
+            StageCommand::SetTrackCompressor { index, config } => stage.set_track_compressor(index, config),
+            StageCommand::SetTrackSidechain { index, source } => stage.set_track_sidechain(index, source),
+            StageCommand::SetMasterCompressor(config) => stage.set_master_compressor(config),
+            StageCommand::SetMasterSidechain(source) => stage.set_master_sidechain(source),
+            StageCommand::SetReverb(config) => stage.set_reverb_config(config),
+            StageCommand::SetTrackReverbSend { index, amount } => stage.set_track_reverb_send(index, amount),
+            StageCommand::SetLfoShape { index, shape } => {
+                if let Some(lfo) = stage.lfo_mut(index) {
+                    lfo.shape = shape;
+                }
+            }
+            StageCommand::SetLfoRate { index, rate } => {
+                if let Some(lfo) = stage.lfo_mut(index) {
+                    lfo.rate = rate;
+                }
+            }
+            StageCommand::SetLfoMode { index, mode } => {
+                if let Some(lfo) = stage.lfo_mut(index) {
+                    lfo.mode = mode;
+                }
+            }
+            StageCommand::SetModRouteAmount { index, amount } => stage.set_mod_route_amount(index, amount),
+            StageCommand::SetMacro { index, value } => stage.set_macro(index, value),
+            StageCommand::SetKickConfig(config) => stage.set_kick_config(config),
+            StageCommand::SetSnareConfig(config) => stage.set_snare_config(config),
+            StageCommand::SetHiHatConfig(config) => stage.set_hihat_config(config),
+            StageCommand::SetTomConfig(config) => stage.set_tom_config(config),
+            StageCommand::SequencerPlay => stage.sequencer_play(),
+            StageCommand::SequencerStop => stage.sequencer_stop(),
+            StageCommand::SequencerReset => stage.sequencer_reset(),
+            StageCommand::SequencerClearAll => stage.sequencer_clear_all(),
+            StageCommand::SequencerSetDefaultPatterns => stage.sequencer_set_default_patterns(),
+            StageCommand::SequencerSetStep { track, step, enabled } => {
+                stage.sequencer_set_step(track, step, enabled)
+            }
+            StageCommand::SequencerSetBpm(bpm) => stage.sequencer_set_bpm(bpm),
+            StageCommand::SetSaturation(saturation) => stage.set_saturation(saturation),
+            StageCommand::SetLimiterThreshold(threshold) => stage.set_limiter_threshold(threshold),
+            StageCommand::SetLimiterMode(mode) => stage.set_limiter_mode(mode),
+            StageCommand::SetLimiterAttack(attack) => stage.set_limiter_attack(attack),
+            StageCommand::SetLimiterRelease(release) => stage.set_limiter_release(release),
+            StageCommand::SetLimiterLookahead(lookahead) => stage.set_limiter_lookahead(lookahead),
+        }
+    }
+}
+
+/// Playback state published by the audio thread after each buffer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StageSnapshot {
+    pub sample_position: u64,
+    pub current_step: usize,
+    pub is_playing: bool,
+    pub bpm: f32,
+    /// Highest absolute sample value in either channel in the last buffer
+    pub peak_level: f32,
+    /// Most gain reduction the master limiter applied in the last buffer, in dB
+    pub gain_reduction_db: f32,
+}
+
+impl StageSnapshot {
+    fn capture(stage: &mut Stage, peak_level: f32) -> Self {
+        Self {
+            sample_position: stage.sample_position(),
+            current_step: stage.sequencer_get_current_step(),
+            is_playing: stage.sequencer_is_playing(),
+            bpm: stage.sequencer_get_bpm(),
+            peak_level,
+            gain_reduction_db: stage.limiter.take_peak_gain_reduction_db(),
+        }
+    }
+}
+
+/// Audio-thread side of the engine: owns the Stage and services the command queue
+pub struct StageProcessor {
+    stage: Stage,
+    commands: Consumer<StageCommand>,
+    snapshots: Writer<StageSnapshot>,
+    /// Tracks going back to the control thread, so they are never freed here
+    retired: Producer<Box<Track>>,
+    peak_level: f32,
+}
+
+impl StageProcessor {
+    pub(crate) fn new(
+        stage: Stage,
+        commands: Consumer<StageCommand>,
+        snapshots: Writer<StageSnapshot>,
+        retired: Producer<Box<Track>>,
+    ) -> Self {
+        Self {
+            stage,
+            commands,
+            snapshots,
+            retired,
+            peak_level: 0.0,
+        }
+    }
+
+    pub fn stage(&self) -> &Stage {
+        &self.stage
+    }
+
+    pub fn stage_mut(&mut self) -> &mut Stage {
+        &mut self.stage
+    }
+
+    /// Apply every pending command; call at the start of each audio buffer
+    pub fn apply_commands(&mut self) {
+        while let Some(command) = self.commands.pop() {
+            match command {
+                StageCommand::AddTrack(track) => self.insert_track(usize::MAX, track),
+                StageCommand::InsertTrack { index, track } => self.insert_track(index, track),
+                StageCommand::RemoveTrack(index) => {
+                    if let Some(track) = self.stage.remove_boxed_track(index) {
+                        self.retire(track);
+                    }
+                }
+                command => command.apply(&mut self.stage),
+            }
+        }
+    }
+
+    fn insert_track(&mut self, index: usize, track: Box<Track>) {
+        // Growing the track list would allocate, so once it is full new tracks are handed back
+        if self.stage.tracks.len() < self.stage.tracks.capacity() {
+            self.stage.insert_boxed_track(index, track);
+        } else {
+            self.retire(track);
+        }
+    }
+
+    /// Hand a track back to the control thread to be freed there
+    fn retire(&mut self, track: Box<Track>) {
+        // Cannot fail: each retired track comes from one command, the queue is as large as the
+        // command queue, and the engine empties it before sending every command
+        let _ = self.retired.push(track);
+    }
+
+    /// Render stereo frames into `left` and `right`, tracking the peak level for the next snapshot
+    pub fn render(&mut self, left: &mut [f32], right: &mut [f32]) {
+        self.stage.process_block_stereo(left, right);
+        for sample in left.iter().chain(right.iter()) {
+            self.peak_level = self.peak_level.max(sample.abs());
+        }
+    }
+
+    /// Publish the current state to the control thread; call at the end of each audio buffer
+    pub fn publish_snapshot(&mut self) {
+        let snapshot = StageSnapshot::capture(&mut self.stage, self.peak_level);
+        // Replaces any snapshot the control thread has not read yet
+        self.snapshots.write(snapshot);
+        self.peak_level = 0.0;
+    }
+
+    /// Process one complete buffer: apply commands, render, then publish a snapshot
+    pub fn process_block(&mut self, left: &mut [f32], right: &mut [f32]) {
+        self.apply_commands();
+        self.render(left, right);
+        self.publish_snapshot();
+    }
+}
@@ -3,9 +3,8 @@ use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
     SizedSample, FromSample, Sample, Stream, Device, StreamConfig,
 };
-use super::{AudioOutput, AudioState};
-use crate::stage::Stage;
-use std::sync::{Arc, Mutex};
+use super::{AudioOutput, StageProcessor};
+use crate::stage::MAX_BLOCK_SIZE;
 use std::time::Instant;
 
 #[cfg(feature = "native")]
@@ -16,7 +15,6 @@ pub struct CpalOutput {
     sample_rate: f32,
     is_active: bool,
     start_time: Option<Instant>,
-    sample_counter: Arc<Mutex<u64>>,
 }
 
 #[cfg(feature = "native")]
@@ -29,32 +27,27 @@ impl CpalOutput {
             sample_rate: 44100.0,
             is_active: false,
             start_time: None,
-            sample_counter: Arc::new(Mutex::new(0)),
         }
     }
     
-    /// Create a stream with stage and audio state
-    pub fn create_stream_with_stage(
-        &mut self,
-        stage: Arc<Mutex<Stage>>,
-        audio_state: Arc<Mutex<AudioState>>,
-    ) -> Result<(), anyhow::Error> {
+    /// Create a stream that renders the stage owned by the given processor
+    /// The processor moves into the audio callback; control it through the AudioEngine's command queue.
+    pub fn create_stream(&mut self, processor: StageProcessor) -> Result<(), anyhow::Error> {
         let device = self.device.as_ref().ok_or_else(|| anyhow::anyhow!("Device not initialized"))?;
         let config = self.config.as_ref().ok_or_else(|| anyhow::anyhow!("Config not initialized"))?;
         
         let supported_config = device.default_output_config()?;
-        let sample_counter = self.sample_counter.clone();
         let stream = match supported_config.sample_format() {
-            cpal::SampleFormat::I8 => Self::make_stream::<i8>(device, config, stage, audio_state, sample_counter)?,
-            cpal::SampleFormat::I16 => Self::make_stream::<i16>(device, config, stage, audio_state, sample_counter)?,
-            cpal::SampleFormat::I32 => Self::make_stream::<i32>(device, config, stage, audio_state, sample_counter)?,
-            cpal::SampleFormat::I64 => Self::make_stream::<i64>(device, config, stage, audio_state, sample_counter)?,
-            cpal::SampleFormat::U8 => Self::make_stream::<u8>(device, config, stage, audio_state, sample_counter)?,
-            cpal::SampleFormat::U16 => Self::make_stream::<u16>(device, config, stage, audio_state, sample_counter)?,
-            cpal::SampleFormat::U32 => Self::make_stream::<u32>(device, config, stage, audio_state, sample_counter)?,
-            cpal::SampleFormat::U64 => Self::make_stream::<u64>(device, config, stage, audio_state, sample_counter)?,
-            cpal::SampleFormat::F32 => Self::make_stream::<f32>(device, config, stage, audio_state, sample_counter)?,
-            cpal::SampleFormat::F64 => Self::make_stream::<f64>(device, config, stage, audio_state, sample_counter)?,
+            cpal::SampleFormat::I8 => Self::make_stream::<i8>(device, config, processor)?,
+            cpal::SampleFormat::I16 => Self::make_stream::<i16>(device, config, processor)?,
+            cpal::SampleFormat::I32 => Self::make_stream::<i32>(device, config, processor)?,
+            cpal::SampleFormat::I64 => Self::make_stream::<i64>(device, config, processor)?,
+            cpal::SampleFormat::U8 => Self::make_stream::<u8>(device, config, processor)?,
+            cpal::SampleFormat::U16 => Self::make_stream::<u16>(device, config, processor)?,
+            cpal::SampleFormat::U32 => Self::make_stream::<u32>(device, config, processor)?,
+            cpal::SampleFormat::U64 => Self::make_stream::<u64>(device, config, processor)?,
+            cpal::SampleFormat::F32 => Self::make_stream::<f32>(device, config, processor)?,
+            cpal::SampleFormat::F64 => Self::make_stream::<f64>(device, config, processor)?,
             sample_format => return Err(anyhow::anyhow!("Unsupported sample format '{}'", sample_format)),
         };
         
@@ -87,43 +80,19 @@ impl CpalOutput {
     fn make_stream<T>(
         device: &Device,
         config: &StreamConfig,
-        stage: Arc<Mutex<Stage>>,
-        audio_state: Arc<Mutex<AudioState>>,
-        sample_counter: Arc<Mutex<u64>>,
+        mut processor: StageProcessor,
     ) -> Result<Stream, anyhow::Error>
     where
         T: SizedSample + FromSample<f32>,
     {
         let num_channels = config.channels as usize;
-        let sample_rate = config.sample_rate.0 as f64; // Use f64 for precision
-        let sample_duration = 1.0 / sample_rate;
         
         let err_fn = |err| eprintln!("Error building output sound stream: {}", err);
-        let start_time = Instant::now();
         
         let stream = device.build_output_stream(
             config,
             move |output: &mut [T], _: &cpal::OutputCallbackInfo| {
-                // Check if we should trigger the stage
-                {
-                    let mut state = audio_state.lock().unwrap();
-                    if state.should_trigger {
-                        let current_time = start_time.elapsed().as_secs_f32();
-                        let mut stage_guard = stage.lock().unwrap();
-                        stage_guard.trigger_all();
-                        state.should_trigger = false;
-                        state.trigger_time = current_time;
-                        println!("Triggered at {:.2}s", current_time);
-                    }
-                }
-                
-                Self::process_frame(
-                    output,
-                    &stage,
-                    num_channels,
-                    &sample_counter,
-                    sample_rate,
-                );
+                Self::process_frame(output, &mut processor, num_channels);
             },
             err_fn,
             None,
@@ -133,40 +102,41 @@ impl CpalOutput {
     }
     
     /// Process a single frame of audio data
+    /// The stage keeps its own sample clock, so timing stays sample accurate across buffers.
+    /// Stereo goes to the first two channels; a mono device gets the mix of both.
     fn process_frame<SampleType>(
         output: &mut [SampleType],
-        stage: &Arc<Mutex<Stage>>,
+        processor: &mut StageProcessor,
         num_channels: usize,
-        sample_counter: &Arc<Mutex<u64>>,
-        sample_rate: f64,
     ) where
         SampleType: Sample + FromSample<f32>,
     {
-        let frames_to_process = output.len() / num_channels;
-        
-        // Get the current sample counter and increment it atomically
-        let start_sample = {
-            let mut counter = sample_counter.lock().unwrap();
-            let current = *counter;
-            *counter += frames_to_process as u64;
-            current
-        };
-        
-        // Lock the stage once for the entire buffer
-        let mut stage_guard = stage.lock().unwrap();
+        // Apply queued control changes once, at the start of the buffer
+        processor.apply_commands();
+        let mut left = [0.0; MAX_BLOCK_SIZE];
+        let mut right = [0.0; MAX_BLOCK_SIZE];
         
-        for (frame_index, frame) in output.chunks_mut(num_channels).enumerate() {
-            // Calculate precise time using sample-based timing like Web Audio
-            let current_sample = start_sample + frame_index as u64;
-            let current_time = current_sample as f64 / sample_rate;
+        for chunk in output.chunks_mut(MAX_BLOCK_SIZE * num_channels) {
+            let frames = chunk.len() / num_channels;
+            processor.render(&mut left[..frames], &mut right[..frames]);
             
-            let value: SampleType = SampleType::from_sample(stage_guard.tick(current_time as f32));
-            
-            // Copy the same value to all channels
-            for sample in frame.iter_mut() {
-                *sample = value;
+            for (index, frame) in chunk.chunks_mut(num_channels).enumerate() {
+                match frame {
+                    [mono] => *mono = SampleType::from_sample((left[index] + right[index]) * 0.5),
+                    [l, r, rest @ ..] => {
+                        *l = SampleType::from_sample(left[index]);
+                        *r = SampleType::from_sample(right[index]);
+                        // Any further channels stay silent
+                        for sample in rest.iter_mut() {
+                            *sample = SampleType::EQUILIBRIUM;
+                        }
+                    }
+                    [] => {}
+                }
             }
         }
+        
+        processor.publish_snapshot();
     }
 }
 
@@ -180,14 +150,12 @@ impl AudioOutput for CpalOutput {
     
     fn start(&mut self) -> Result<(), anyhow::Error> {
         if let Some(stream) = &self.stream {
-            // Reset sample counter when starting
-            *self.sample_counter.lock().unwrap() = 0;
             stream.play()?;
             self.is_active = true;
             self.start_time = Some(Instant::now());
             println!("Audio stream started at sample rate: {}", self.sample_rate);
         } else {
-            return Err(anyhow::anyhow!("Stream not created. Call create_stream_with_stage first."));
+            return Err(anyhow::anyhow!("Stream not created. Call create_stream first."));
         }
         
         Ok(())
@@ -3,7 +3,8 @@
 /// (native CPAL, web audio, iOS, etc.)
 
 use crate::stage::Stage;
-use std::sync::{Arc, Mutex};
+use crate::track::Track;
+use self::spsc::{Consumer, Producer};
 
 /// Trait for platform-specific audio output implementations
 pub trait AudioOutput {
@@ -23,52 +24,59 @@ pub trait AudioOutput {
     fn is_active(&self) -> bool;
 }
 
-/// Shared audio state for communication between main thread and audio callback
-pub struct AudioState {
-    pub should_trigger: bool,
-    pub trigger_time: f32,
-}
-
-impl AudioState {
-    pub fn new() -> Self {
-        Self {
-            should_trigger: false,
-            trigger_time: 0.0,
-        }
-    }
-}
+/// Capacity of the control-to-audio command queue, and of the queue returning removed tracks
+const COMMAND_QUEUE_CAPACITY: usize = 256;
 
 /// Audio engine that connects a Stage to platform-specific audio output
+/// The Stage lives in a StageProcessor on the audio thread; the engine only talks to it
+/// through wait-free channels, so control changes can never block audio rendering.
 pub struct AudioEngine {
-    stage: Arc<Mutex<Stage>>,
-    audio_state: Arc<Mutex<AudioState>>,
+    processor: Option<StageProcessor>,
+    commands: Producer<StageCommand>,
+    snapshots: triple_buffer::Reader<StageSnapshot>,
+    retired: Consumer<Box<Track>>,
     sample_rate: f32,
 }
 
 impl AudioEngine {
     /// Create a new audio engine with the given sample rate
     pub fn new(sample_rate: f32) -> Self {
+        let (commands, command_receiver) = spsc::channel(COMMAND_QUEUE_CAPACITY);
+        let (snapshot_sender, snapshots) = triple_buffer::channel(StageSnapshot::default());
+        let (retired_sender, retired) = spsc::channel(COMMAND_QUEUE_CAPACITY);
+        let stage = Stage::new(sample_rate);
+
         Self {
-            stage: Arc::new(Mutex::new(Stage::new(sample_rate))),
-            audio_state: Arc::new(Mutex::new(AudioState::new())),
+            processor: Some(StageProcessor::new(stage, command_receiver, snapshot_sender, retired_sender)),
+            commands,
+            snapshots,
+            retired,
             sample_rate,
         }
     }
     
-    /// Get the stage for use with audio output
-    pub fn stage(&self) -> Arc<Mutex<Stage>> {
-        self.stage.clone()
+    /// Hand the audio-thread side of the engine to an audio output
+    /// Returns None if it has already been taken.
+    pub fn take_processor(&mut self) -> Option<StageProcessor> {
+        self.processor.take()
     }
     
-    /// Get the audio state for triggering from other threads
-    pub fn audio_state(&self) -> Arc<Mutex<AudioState>> {
-        self.audio_state.clone()
+    /// Queue a command for the audio thread, handing it back if the queue is full
+    pub fn send(&mut self, command: StageCommand) -> Result<(), StageCommand> {
+        // Free tracks the audio thread has removed, which also keeps room to return more
+        while self.retired.pop().is_some() {}
+        self.commands.push(command)
     }
     
     /// Trigger all instruments in the stage
-    pub fn trigger_all(&self) {
-        let mut state = self.audio_state.lock().unwrap();
-        state.should_trigger = true;
+    pub fn trigger_all(&mut self) {
+        // A full queue means the audio thread is stalled; dropping a trigger is the safe choice
+        let _ = self.send(StageCommand::TriggerAll);
+    }
+    
+    /// Get the most recent state published by the audio thread
+    pub fn snapshot(&mut self) -> StageSnapshot {
+        *self.snapshots.read()
     }
     
     /// Get the current sample rate
@@ -76,21 +84,24 @@ impl AudioEngine {
         self.sample_rate
     }
     
-    /// Modify the stage (for configuration)
-    pub fn with_stage<F, R>(&self, f: F) -> R
+    /// Modify the stage directly (for configuration before the processor is taken)
+    /// Returns None once the stage has moved to the audio thread; use `send` from then on.
+    pub fn with_stage<F, R>(&mut self, f: F) -> Option<R>
     where
         F: FnOnce(&mut Stage) -> R,
     {
-        let mut stage = self.stage.lock().unwrap();
-        f(&mut stage)
-    }
-    
-    /// Get a locked reference to the stage for direct access
-    pub fn stage_mut(&self) -> std::sync::MutexGuard<Stage> {
-        self.stage.lock().unwrap()
+        self.processor
+            .as_mut()
+            .map(|processor| f(processor.stage_mut()))
     }
 }
 
+pub mod command;
+pub mod spsc;
+pub mod triple_buffer;
+
+pub use self::command::{StageCommand, StageProcessor, StageSnapshot};
+
 // Platform-specific implementations
 #[cfg(feature = "native")]
 pub mod cpal_output;
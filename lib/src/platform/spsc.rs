@@ -0,0 +1,233 @@
+//! Wait-free single-producer/single-consumer ring buffer
+//! Used to pass commands into the audio callback without locks,
+//! so the control thread can never block audio rendering.
+
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+struct Shared<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    mask: usize,
+    /// Total number of items popped (only written by the consumer)
+    head: AtomicUsize,
+    /// Total number of items pushed (only written by the producer)
+    tail: AtomicUsize,
+}
+
+// Safety: each slot is accessed by exactly one side at a time, handed over through
+// the release/acquire ordering on `head` and `tail`.
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        // Drop any items that were pushed but never popped
+        let head = *self.head.get_mut();
+        let tail = *self.tail.get_mut();
+        let mut index = head;
+        while index != tail {
+            unsafe { self.slots[index & self.mask].get_mut().assume_init_drop() };
+            index = index.wrapping_add(1);
+        }
+    }
+}
+
+/// Sending half of the queue, owned by one thread
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// Receiving half of the queue, owned by one thread
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// Create a queue holding at least `capacity` items (rounded up to a power of two)
+/// All memory is allocated here, so pushing and popping never allocate.
+pub fn channel<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    let capacity = capacity.max(1).next_power_of_two();
+    let slots = (0..capacity)
+        .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+        .collect();
+
+    let shared = Arc::new(Shared {
+        slots,
+        mask: capacity - 1,
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+    });
+
+    (
+        Producer {
+            shared: shared.clone(),
+        },
+        Consumer { shared },
+    )
+}
+
+impl<T> Producer<T> {
+    /// Push an item, handing it back if the queue is full
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        let shared = &*self.shared;
+        let tail = shared.tail.load(Ordering::Relaxed);
+        let head = shared.head.load(Ordering::Acquire);
+
+        if tail.wrapping_sub(head) > shared.mask {
+            return Err(value);
+        }
+
+        unsafe { (*shared.slots[tail & shared.mask].get()).write(value) };
+        shared.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Maximum number of items the queue can hold
+    pub fn capacity(&self) -> usize {
+        self.shared.mask + 1
+    }
+}
+
+impl<T> Consumer<T> {
+    /// Pop the oldest item, if any
+    pub fn pop(&mut self) -> Option<T> {
+        let shared = &*self.shared;
+        let head = shared.head.load(Ordering::Relaxed);
+        let tail = shared.tail.load(Ordering::Acquire);
+
+        if head == tail {
+            return None;
+        }
+
+        let value = unsafe { (*shared.slots[head & shared.mask].get()).assume_init_read() };
+        shared.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+
+    /// Check whether there is nothing waiting to be popped
+    pub fn is_empty(&self) -> bool {
+        let shared = &*self.shared;
+        shared.head.load(Ordering::Relaxed) == shared.tail.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn capacity_rounds_up_to_a_power_of_two() {
+        let (producer, _consumer) = channel::<u32>(5);
+        assert_eq!(producer.capacity(), 8);
+        let (producer, _consumer) = channel::<u32>(0);
+        assert_eq!(producer.capacity(), 1);
+    }
+
+    #[test]
+    fn empty_queue_pops_nothing() {
+        let (_producer, mut consumer) = channel::<u32>(4);
+        assert!(consumer.is_empty());
+        assert_eq!(consumer.pop(), None);
+    }
+
+    #[test]
+    fn full_queue_hands_the_item_back() {
+        let (mut producer, mut consumer) = channel(4);
+        for value in 0..4 {
+            assert_eq!(producer.push(value), Ok(()));
+        }
+        assert_eq!(producer.push(4), Err(4));
+
+        assert_eq!(consumer.pop(), Some(0));
+        assert_eq!(producer.push(4), Ok(()));
+        assert_eq!(producer.push(5), Err(5));
+    }
+
+    #[test]
+    fn items_keep_their_order_across_wraparound() {
+        let (mut producer, mut consumer) = channel(4);
+        let mut next = 0;
+        let mut expected = 0;
+        // Many times round the ring, with the fill level varying
+        for round in 0..100 {
+            for _ in 0..round % 4 + 1 {
+                producer.push(next).unwrap();
+                next += 1;
+            }
+            while let Some(value) = consumer.pop() {
+                assert_eq!(value, expected);
+                expected += 1;
+            }
+        }
+        assert_eq!(expected, next);
+    }
+
+    #[test]
+    fn indices_survive_counter_overflow() {
+        let (mut producer, mut consumer) = channel(4);
+        producer.shared.head.store(usize::MAX - 1, Ordering::Relaxed);
+        producer.shared.tail.store(usize::MAX - 1, Ordering::Relaxed);
+        for value in 0..4 {
+            producer.push(value).unwrap();
+        }
+        assert_eq!(producer.push(4), Err(4));
+        for value in 0..4 {
+            assert_eq!(consumer.pop(), Some(value));
+        }
+        assert_eq!(consumer.pop(), None);
+    }
+
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn items_left_in_the_queue_are_dropped() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (mut producer, mut consumer) = channel(8);
+        for _ in 0..5 {
+            assert!(producer.push(Counted(drops.clone())).is_ok());
+        }
+        drop(consumer.pop());
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+
+        drop(producer);
+        drop(consumer);
+        assert_eq!(drops.load(Ordering::Relaxed), 5);
+    }
+
+    #[test]
+    fn every_item_arrives_once_and_in_order_across_threads() {
+        const COUNT: u64 = 100_000;
+        let (mut producer, mut consumer) = channel(16);
+
+        let handle = std::thread::spawn(move || {
+            for value in 0..COUNT {
+                let mut item = value;
+                while let Err(rejected) = producer.push(item) {
+                    item = rejected;
+                    std::thread::yield_now();
+                }
+            }
+        });
+
+        let mut expected = 0;
+        while expected < COUNT {
+            match consumer.pop() {
+                Some(value) => {
+                    assert_eq!(value, expected);
+                    expected += 1;
+                }
+                None => std::thread::yield_now(),
+            }
+        }
+        handle.join().unwrap();
+        assert!(consumer.is_empty());
+    }
+}
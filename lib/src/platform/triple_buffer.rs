@@ -0,0 +1,134 @@
+//! Wait-free single-value channel where the latest write always wins
+//! Used to publish snapshots from the audio callback: the writer never blocks or fails, and
+//! the reader always sees the newest value no matter how long it went without reading.
+
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Set on the shared index when it holds a value the reader has not seen yet
+const FRESH: usize = 0b100;
+
+const INDEX_MASK: usize = 0b011;
+
+struct Shared<T> {
+    /// One buffer each for the writer and reader, plus one in flight between them
+    buffers: [UnsafeCell<T>; 3],
+    /// Index of the in-flight buffer, tagged with `FRESH`
+    back: AtomicUsize,
+}
+
+// Safety: each buffer is owned by exactly one side at a time; ownership of the in-flight
+// buffer changes hands through the acquire/release swap on `back`.
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+/// Writing half, owned by one thread
+pub struct Writer<T> {
+    shared: Arc<Shared<T>>,
+    index: usize,
+}
+
+/// Reading half, owned by one thread
+pub struct Reader<T> {
+    shared: Arc<Shared<T>>,
+    index: usize,
+}
+
+/// Create a channel whose reader sees `initial` until the first write
+/// All memory is allocated here, so writing and reading never allocate.
+pub fn channel<T: Clone>(initial: T) -> (Writer<T>, Reader<T>) {
+    let shared = Arc::new(Shared {
+        buffers: [
+            UnsafeCell::new(initial.clone()),
+            UnsafeCell::new(initial.clone()),
+            UnsafeCell::new(initial),
+        ],
+        back: AtomicUsize::new(1),
+    });
+
+    (
+        Writer {
+            shared: shared.clone(),
+            index: 0,
+        },
+        Reader { shared, index: 2 },
+    )
+}
+
+impl<T> Writer<T> {
+    /// Publish a value, replacing any the reader has not picked up yet
+    pub fn write(&mut self, value: T) {
+        let shared = &*self.shared;
+        unsafe { *shared.buffers[self.index].get() = value };
+        let previous = shared.back.swap(self.index | FRESH, Ordering::AcqRel);
+        self.index = previous & INDEX_MASK;
+    }
+}
+
+impl<T> Reader<T> {
+    /// Get the most recently written value
+    pub fn read(&mut self) -> &T {
+        let shared = &*self.shared;
+        if self.has_update() {
+            let previous = shared.back.swap(self.index, Ordering::AcqRel);
+            self.index = previous & INDEX_MASK;
+        }
+        unsafe { &*shared.buffers[self.index].get() }
+    }
+
+    /// Check whether a value has been written since the last read
+    pub fn has_update(&self) -> bool {
+        self.shared.back.load(Ordering::Relaxed) & FRESH != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reader_sees_initial_value_until_first_write() {
+        let (_writer, mut reader) = channel(7);
+        assert!(!reader.has_update());
+        assert_eq!(*reader.read(), 7);
+    }
+
+    #[test]
+    fn latest_write_wins() {
+        let (mut writer, mut reader) = channel(0);
+        for value in 1..=100 {
+            writer.write(value);
+        }
+        assert!(reader.has_update());
+        assert_eq!(*reader.read(), 100);
+        assert!(!reader.has_update());
+        assert_eq!(*reader.read(), 100);
+
+        writer.write(101);
+        assert_eq!(*reader.read(), 101);
+    }
+
+    #[test]
+    fn values_never_go_backwards_across_threads() {
+        const COUNT: u64 = 100_000;
+        let (mut writer, mut reader) = channel((0u64, 0u64));
+
+        let handle = std::thread::spawn(move || {
+            for value in 1..=COUNT {
+                // Both halves must always match, or the reader saw a torn write
+                writer.write((value, value * 3));
+            }
+        });
+
+        let mut last = 0;
+        while last < COUNT {
+            let (value, check) = *reader.read();
+            assert_eq!(check, value * 3);
+            assert!(value >= last);
+            last = value;
+            std::thread::yield_now();
+        }
+        handle.join().unwrap();
+    }
+}
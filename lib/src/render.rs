@@ -0,0 +1,181 @@
+//! Offline (faster-than-realtime) rendering of a Stage to a sample buffer
+//! This drives the Stage's own sample clock exactly as the realtime outputs do,
+//! so patterns can be rendered on machines without an audio device. The master limiter's
+//! lookahead delay is rendered ahead and dropped, so a bounce starts exactly on the first step.
+
+use crate::stage::{Stage, MAX_BLOCK_SIZE};
+
+/// Renders a configured Stage into interleaved sample frames without an audio device
+/// Use one renderer per stage, since it remembers how much limiter latency it has dropped.
+pub struct OfflineRenderer {
+    channels: usize,
+    /// Frames of limiter latency already rendered and dropped
+    latency_skipped: usize,
+}
+
+impl OfflineRenderer {
+    /// Create a renderer producing `channels` interleaved channels per frame
+    /// One channel gives a mono mix; otherwise the first two channels carry left and right.
+    pub fn new(channels: usize) -> Self {
+        Self {
+            channels: channels.max(1),
+            latency_skipped: 0,
+        }
+    }
+
+    /// Number of interleaved channels in each rendered frame
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Render a fixed number of frames, continuing from the stage's current sample position
+    /// The first call also renders the limiter's latency and drops it, so the output lines up with
+    /// the sequencer; later calls pick up where the previous one stopped.
+    pub fn render_frames(&mut self, stage: &mut Stage, frames: usize) -> Vec<f32> {
+        let mut output = Vec::with_capacity(frames * self.channels);
+        let mut left = [0.0; MAX_BLOCK_SIZE];
+        let mut right = [0.0; MAX_BLOCK_SIZE];
+        let mut skip = stage.limiter.latency_samples().saturating_sub(self.latency_skipped);
+        self.latency_skipped += skip;
+        let mut remaining = frames + skip;
+
+        while remaining > 0 {
+            let block_len = remaining.min(MAX_BLOCK_SIZE);
+            stage.process_block_stereo(&mut left[..block_len], &mut right[..block_len]);
+
+            for (&l, &r) in left[..block_len].iter().zip(&right[..block_len]) {
+                if skip > 0 {
+                    // Still inside the limiter's delay: output from before the first frame
+                    skip -= 1;
+                    continue;
+                }
+                if self.channels == 1 {
+                    output.push((l + r) * 0.5);
+                } else {
+                    output.push(l);
+                    output.push(r);
+                    // Any further channels stay silent
+                    output.extend(std::iter::repeat_n(0.0, self.channels - 2));
+                }
+            }
+
+            remaining -= block_len;
+        }
+
+        output
+    }
+
+    /// Render the given duration in seconds
+    pub fn render_seconds(&mut self, stage: &mut Stage, seconds: f64) -> Vec<f32> {
+        let frames = (seconds.max(0.0) * stage.sample_rate as f64).round() as usize;
+        self.render_frames(stage, frames)
+    }
+
+    /// Render the given number of 16-step bars at the sequencer's current BPM
+    pub fn render_bars(&mut self, stage: &mut Stage, bars: u32) -> Vec<f32> {
+        let seconds = stage.sequencer.bar_duration() * bars as f64;
+        self.render_seconds(stage, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::limiter::LimiterMode;
+    use crate::instruments::{Instrument, ParameterInfo};
+    use crate::track::Track;
+    use std::any::Any;
+
+    const SAMPLE_RATE: f32 = 44100.0;
+
+    /// Sounds a constant level for a few samples from the moment it is triggered
+    struct Click {
+        remaining: usize,
+    }
+
+    impl Instrument for Click {
+        fn trigger(&mut self, _time: f64) {
+            self.remaining = 16;
+        }
+
+        fn release(&mut self, _time: f64) {}
+
+        fn tick(&mut self, _current_time: f64) -> f32 {
+            if self.remaining == 0 {
+                return 0.0;
+            }
+            self.remaining -= 1;
+            0.5
+        }
+
+        fn is_active(&self) -> bool {
+            self.remaining > 0
+        }
+
+        fn set_volume(&mut self, _volume: f32) {}
+
+        fn sample_rate(&self) -> f32 {
+            SAMPLE_RATE
+        }
+
+        fn parameters(&self) -> &'static [ParameterInfo] {
+            &[]
+        }
+
+        fn get_parameter(&self, _name: &str) -> Option<f32> {
+            None
+        }
+
+        fn set_parameter(&mut self, _name: &str, _value: f32) -> bool {
+            false
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    fn groove() -> Stage {
+        let mut stage = Stage::new(SAMPLE_RATE);
+        stage.set_seed(7);
+        stage.sequencer_set_default_patterns();
+        stage.sequencer_play();
+        stage
+    }
+
+    #[test]
+    fn first_step_lands_on_first_frame() {
+        for lookahead in [None, Some(0.0), Some(0.005), Some(0.02)] {
+            let mut stage = Stage::new(SAMPLE_RATE);
+            stage.sequencer_clear_all();
+            let track = stage.add_track(Track::new("click", Box::new(Click { remaining: 0 })));
+            stage.sequencer_set_step(track, 0, true);
+            match lookahead {
+                Some(lookahead) => stage.set_limiter_lookahead(lookahead),
+                None => stage.set_limiter_mode(LimiterMode::HardClip),
+            }
+            stage.sequencer_play();
+
+            let output = OfflineRenderer::new(1).render_frames(&mut stage, 1024);
+            assert_eq!(output.len(), 1024);
+            let first = output.iter().position(|&sample| sample != 0.0);
+            assert_eq!(first, Some(0), "lookahead {lookahead:?}");
+        }
+    }
+
+    #[test]
+    fn consecutive_renders_match_one_long_render() {
+        let whole = OfflineRenderer::new(2).render_frames(&mut groove(), 30000);
+
+        let mut stage = groove();
+        let mut renderer = OfflineRenderer::new(2);
+        let mut parts = renderer.render_frames(&mut stage, 10007);
+        parts.extend(renderer.render_frames(&mut stage, 30000 - 10007));
+        assert_eq!(parts.len(), whole.len());
+        assert!(parts == whole, "split render differs from one long render");
+    }
+}
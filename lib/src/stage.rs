@@ -1,64 +1,122 @@
-use crate::envelope::ADSRConfig;
+use crate::envelope::{ADSRConfig, BreakpointEnvelope, RetriggerMode};
+use crate::gen::lfo::Lfo;
 use crate::gen::oscillator::Oscillator;
-use crate::instruments::{KickDrum, KickConfig, SnareDrum, SnareConfig, HiHat, HiHatConfig, TomDrum, TomConfig};
-use crate::effects::limiter::BrickWallLimiter;
+use crate::instruments::{Instrument, ParameterInfo, KickDrum, KickConfig, SnareDrum, SnareConfig, HiHat, HiHatConfig, TomDrum, TomConfig, VoicePool, VoiceStealing, DEFAULT_POLYPHONY};
+use crate::effects::compressor::{Compressor, CompressorConfig};
+use crate::effects::eq::{EqBand, ParametricEq};
+use crate::effects::limiter::{LimiterMode, LookaheadLimiter};
+use crate::effects::pan::PanLaw;
+use crate::effects::reverb::{Reverb, ReverbConfig};
+use crate::modulation::{ModDestination, ModEnvelope, ModMatrix, ModRoute, ModSource};
+use crate::track::{Track, STEPS_PER_PATTERN};
+
+/// Largest number of samples rendered in one internal pass of `Stage::process_block`
+pub const MAX_BLOCK_SIZE: usize = 256;
+
+/// Tracks that can be added on top of the default kit without the track list reallocating,
+/// so the audio thread can take new tracks from the command queue
+pub const TRACK_CAPACITY: usize = 64;
+
+/// Samples between modulation updates while any LFO or route exists
+/// Updates fall on multiples of this counted from sample zero, whatever the host block size.
+pub const CONTROL_BLOCK_SIZE: usize = 64;
+
+/// Stage-wide parameters that can be modulated
+pub const STAGE_PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo::new("saturation", 0.0, 1.0, 0.0),
+    ParameterInfo::new("limiter_threshold", 0.0, 1.0, 1.0),
+];
+
+const PAN_PARAMETER: ParameterInfo = ParameterInfo::new("pan", -1.0, 1.0, 0.0);
 
 pub struct Stage {
     pub sample_rate: f32,
     pub instruments: Vec<Oscillator>, // Keep for backward compatibility
-    pub limiter: BrickWallLimiter,
+    pub limiter: LookaheadLimiter,
     pub sequencer: Sequencer,
     
-    // Drum instruments for sequencer
-    pub kick: KickDrum,
-    pub snare: SnareDrum,
-    pub hihat: HiHat,
-    pub tom: TomDrum,
+    // Master equaliser and compressor, applied to the mix before saturation and limiting
+    pub eq: ParametricEq,
+    pub compressor: Compressor,
+    // Track whose output drives the master compressor instead of the mix
+    pub compressor_sidechain: Option<usize>,
+    
+    // Shared reverb fed by each track's send, returned into the mix ahead of the master effects
+    pub reverb: Reverb,
+    
+    // Sequenced tracks, each with its own instrument and pattern
+    // Boxed so whole tracks can be handed to and from the audio thread without copying
+    pub tracks: Vec<Box<Track>>,
+    
+    // How track pan positions map to left/right gains
+    pub pan_law: PanLaw,
     
     // Harmonic distortion settings
     pub saturation: f32, // 0.0 to 1.0, where 0.0 is no distortion
     
-    // Current time tracking
-    current_time: f32,
+    // Modulation sources and where they are routed
+    pub modulation: ModMatrix,
+    
+    // Clock: absolute position of the next sample to be rendered
+    sample_position: u64,
 }
 
-/// A 16-step drum sequencer that manages pattern playback for multiple instruments
+/// A 16-step sequencer clock; the patterns it plays live on each `Track`
 #[derive(Debug, Clone)]
 pub struct Sequencer {
-    /// 16-step patterns for each instrument (4 instruments, 16 steps each)
-    patterns: [[bool; 16]; 4],
     /// Current step (0-15)
     current_step: usize,
     /// Whether the sequencer is playing
     is_playing: bool,
     /// BPM (beats per minute)
     bpm: f32,
-    /// Time of the last step in seconds
-    last_step_time: f32,
+    /// Sample rate used to convert step intervals into sample positions
+    sample_rate: f64,
+    /// Sample position that step timing is measured from
+    step_origin_sample: u64,
+    /// Number of steps triggered since `step_origin_sample`
+    steps_since_origin: u64,
+    /// Sample position of the last triggered step
+    last_step_sample: u64,
     /// Time interval between steps in seconds
-    step_interval: f32,
+    step_interval: f64,
 }
 
 impl Stage {
     pub fn new(sample_rate: f32) -> Self {
-        Self {
+        let mut stage = Self {
             sample_rate,
             instruments: Vec::new(),
-            limiter: BrickWallLimiter::new(1.0), // Default threshold at 1.0 to prevent clipping
-            sequencer: Sequencer::new(),
+            limiter: LookaheadLimiter::new(sample_rate, 1.0), // Default threshold at 1.0 to prevent clipping
+            sequencer: Sequencer::new(sample_rate),
+            eq: ParametricEq::new(sample_rate),
+            compressor: Compressor::new(sample_rate, CompressorConfig::disabled()),
+            compressor_sidechain: None,
+            reverb: Reverb::new(sample_rate, ReverbConfig::default()),
             
-            // Initialize drum instruments with default presets
-            kick: KickDrum::with_config(sample_rate, KickConfig::default()),
-            snare: SnareDrum::with_config(sample_rate, SnareConfig::default()),
-            hihat: HiHat::with_config(sample_rate, HiHatConfig::closed_default()),
-            tom: TomDrum::with_config(sample_rate, TomConfig::default()),
+            // Start with the classic drum kit, one track per drum
+            tracks: vec![
+                Box::new(Track::new("kick", Box::new(KickDrum::with_config(sample_rate, KickConfig::default())))),
+                Box::new(Track::new("snare", Box::new(SnareDrum::with_config(sample_rate, SnareConfig::default())))),
+                // Hats and toms are pooled so open hats and rolls can overlap
+                Box::new(Track::new("hihat", Box::new(VoicePool::new(DEFAULT_POLYPHONY, || HiHat::with_config(sample_rate, HiHatConfig::closed_default()))))),
+                Box::new(Track::new("tom", Box::new(VoicePool::new(DEFAULT_POLYPHONY, || TomDrum::with_config(sample_rate, TomConfig::default()))))),
+            ],
+            pan_law: PanLaw::default(),
             
             // Initialize harmonic distortion
             saturation: 0.0, // No distortion by default
             
-            // Initialize current time
-            current_time: 0.0,
+            modulation: ModMatrix::new(),
+            
+            // Initialize the clock
+            sample_position: 0,
+        };
+        for track in &mut stage.tracks {
+            track.set_sample_rate(sample_rate);
         }
+        stage.tracks.reserve(TRACK_CAPACITY);
+        stage
     }
 
     pub fn add(&mut self, mut instrument: Oscillator) {
@@ -67,76 +125,677 @@ impl Stage {
         self.instruments.push(instrument);
     }
 
-    pub fn tick(&mut self, current_time: f32) -> f32 {
-        // Update internal time tracking
-        self.current_time = current_time;
-        
-        // Update sequencer and trigger instruments if needed
-        if self.sequencer.is_playing {
-            self.sequencer.update(current_time);
+    /// Append a track, returning its index
+    /// The track's instrument should use the stage's sample rate.
+    pub fn add_track(&mut self, track: Track) -> usize {
+        self.push_track(Box::new(track))
+    }
 
-            // Check if we should trigger instruments on the current step
-            if self.sequencer.should_trigger_step(current_time) {
-                let current_step = self.sequencer.current_step;
+    pub(crate) fn push_track(&mut self, mut track: Box<Track>) -> usize {
+        track.set_sample_rate(self.sample_rate);
+        self.tracks.push(track);
+        self.tracks.len() - 1
+    }
+
+    /// Insert a track at `index`, shifting later tracks down (clamped to the end of the list)
+    pub fn insert_track(&mut self, index: usize, track: Track) -> usize {
+        self.insert_boxed_track(index, Box::new(track))
+    }
+
+    pub(crate) fn insert_boxed_track(&mut self, index: usize, mut track: Box<Track>) -> usize {
+        track.set_sample_rate(self.sample_rate);
+        let index = index.min(self.tracks.len());
+        self.tracks.insert(index, track);
+
+        // Keep routes and sidechains pointing at the same tracks
+        self.modulation.track_inserted(index);
+        self.remap_sidechains(|source| Some(if source >= index { source + 1 } else { source }));
+        index
+    }
+
+    /// Remove a track along with its pattern
+    pub fn remove_track(&mut self, index: usize) -> Option<Track> {
+        self.remove_boxed_track(index).map(|track| *track)
+    }
+
+    pub(crate) fn remove_boxed_track(&mut self, index: usize) -> Option<Box<Track>> {
+        if index < self.tracks.len() {
+            // Routes to the removed track go with it; later tracks shift down
+            self.modulation.track_removed(index);
+            let track = self.tracks.remove(index);
+            self.remap_sidechains(|source| match source.cmp(&index) {
+                std::cmp::Ordering::Less => Some(source),
+                std::cmp::Ordering::Equal => None,
+                std::cmp::Ordering::Greater => Some(source - 1),
+            });
+            Some(track)
+        } else {
+            None
+        }
+    }
+
+    /// Move a track to a new position, keeping its pattern and instrument together
+    pub fn move_track(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.tracks.len() || to >= self.tracks.len() {
+            return false;
+        }
+        let track = self.tracks.remove(from);
+        self.tracks.insert(to, track);
+
+        // Keep routes and sidechains pointing at the same tracks
+        self.modulation.track_moved(from, to);
+        self.remap_sidechains(|source| {
+            Some(if source == from {
+                to
+            } else if from < to && source > from && source <= to {
+                source - 1
+            } else if to < from && source >= to && source < from {
+                source + 1
+            } else {
+                source
+            })
+        });
+        true
+    }
+
+    /// Update every sidechain source after the track list changed; `None` disconnects it
+    fn remap_sidechains(&mut self, remap: impl Fn(usize) -> Option<usize>) {
+        for track in &mut self.tracks {
+            track.sidechain = track.sidechain.and_then(&remap);
+        }
+        self.compressor_sidechain = self.compressor_sidechain.and_then(&remap);
+    }
+
+    pub fn track_count(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn track(&self, index: usize) -> Option<&Track> {
+        self.tracks.get(index).map(|track| &**track)
+    }
+
+    pub fn track_mut(&mut self, index: usize) -> Option<&mut Track> {
+        self.tracks.get_mut(index).map(|track| &mut **track)
+    }
+
+    /// Trigger a track's instrument now
+    pub fn trigger_track(&mut self, index: usize) {
+        let time = self.current_time();
+        if let Some(track) = self.tracks.get_mut(index) {
+            track.instrument.trigger(time);
+            self.modulation.on_trigger(index, 1.0, time);
+        }
+    }
+
+    /// Release a track's instrument now
+    pub fn release_track(&mut self, index: usize) {
+        let time = self.current_time();
+        if let Some(track) = self.tracks.get_mut(index) {
+            track.instrument.release(time);
+        }
+    }
+
+    /// Set a track's stereo position from -1.0 (hard left) to 1.0 (hard right)
+    pub fn set_track_pan(&mut self, index: usize, pan: f32) {
+        if let Some(track) = self.tracks.get_mut(index) {
+            track.set_pan(pan);
+            self.modulation.set_base(&ModDestination::Pan(index), track.pan);
+        }
+    }
+
+    /// Choose what retriggering a track's instrument does while it is still sounding
+    pub fn set_track_retrigger_mode(&mut self, index: usize, mode: RetriggerMode) {
+        if let Some(track) = self.tracks.get_mut(index) {
+            track.instrument.set_retrigger_mode(mode);
+        }
+    }
+
+    pub fn get_track_retrigger_mode(&self, index: usize) -> Option<RetriggerMode> {
+        self.tracks.get(index).map(|track| track.instrument.retrigger_mode())
+    }
+
+    /// A track's pan position, before any modulation
+    pub fn get_track_pan(&self, index: usize) -> f32 {
+        let destination = ModDestination::Pan(index);
+        self.modulation
+            .base(&destination)
+            .unwrap_or_else(|| self.tracks.get(index).map_or(0.0, |track| track.pan))
+    }
+
+    pub fn set_pan_law(&mut self, pan_law: PanLaw) {
+        self.pan_law = pan_law;
+    }
+
+    pub fn get_pan_law(&self) -> PanLaw {
+        self.pan_law
+    }
+
+    /// Reseed the random sources of every track so a render can be reproduced exactly
+    /// Each track gets its own stream derived from `seed` and its position.
+    pub fn set_seed(&mut self, seed: u64) {
+        for (index, track) in self.tracks.iter_mut().enumerate() {
+            track.instrument.set_seed(seed.wrapping_add((index as u64) << 32));
+        }
+        self.modulation.set_seed(seed);
+    }
+
+    /// Set a named parameter on a track's instrument, returning false if either does not exist
+    /// A modulated parameter keeps moving around the new value.
+    pub fn set_track_parameter(&mut self, index: usize, name: &str, value: f32) -> bool {
+        let Some(track) = self.tracks.get_mut(index) else {
+            return false;
+        };
+        if !track.instrument.set_parameter(name, value) {
+            return false;
+        }
+        if let Some(destination) = self.parameter_destination(index, name) {
+            // Read back so the base reflects any clamping the instrument applied
+            let value = self.read_destination(&destination).unwrap_or(value);
+            self.modulation.set_base(&destination, value);
+        }
+        true
+    }
+
+    /// Current value of a named parameter on a track's instrument, before any modulation
+    pub fn get_track_parameter(&self, index: usize, name: &str) -> Option<f32> {
+        let destination = self.parameter_destination(index, name)?;
+        self.modulation.base(&destination).or_else(|| self.read_destination(&destination))
+    }
+
+    /// Index of the first track whose instrument is a `T` or a pool of them
+    pub fn find_track<T: Instrument + 'static>(&self) -> Option<usize> {
+        self.tracks.iter().position(|track| track.instrument_as::<T>().is_some())
+    }
+
+    /// The first instrument of type `T` on any track
+    pub fn first_instrument<T: Instrument + 'static>(&self) -> Option<&T> {
+        self.tracks.iter().find_map(|track| track.instrument_as::<T>())
+    }
+
+    /// The first instrument of type `T` on any track, for modification
+    /// Pooled instruments are skipped; use `first_instruments_mut` to reach every voice.
+    pub fn first_instrument_mut<T: Instrument + 'static>(&mut self) -> Option<&mut T> {
+        self.tracks.iter_mut().find_map(|track| track.instrument_as_mut::<T>())
+    }
+
+    /// Every voice of the first track playing a `T`, whether it is pooled or not
+    pub fn first_instruments_mut<T: Instrument + 'static>(&mut self) -> Option<&mut [T]> {
+        self.tracks.iter_mut().find_map(|track| track.instruments_as_mut::<T>())
+    }
+
+    /// Limit how many hits of a track's instrument can ring at once
+    pub fn set_track_polyphony(&mut self, index: usize, voices: usize) {
+        if let Some(track) = self.tracks.get_mut(index) {
+            track.instrument.set_polyphony(voices);
+        }
+    }
+
+    pub fn get_track_polyphony(&self, index: usize) -> usize {
+        self.tracks.get(index).map_or(1, |track| track.instrument.polyphony())
+    }
+
+    pub fn set_track_voice_stealing(&mut self, index: usize, stealing: VoiceStealing) {
+        if let Some(track) = self.tracks.get_mut(index) {
+            track.instrument.set_voice_stealing(stealing);
+        }
+    }
+
+    pub fn get_track_voice_stealing(&self, index: usize) -> Option<VoiceStealing> {
+        self.tracks.get(index).map(|track| track.instrument.voice_stealing())
+    }
+
+    /// Change one band of a track's EQ
+    pub fn set_track_eq_band(&mut self, index: usize, band: usize, settings: EqBand) {
+        if let Some(track) = self.tracks.get_mut(index) {
+            track.eq.set_band(band, settings);
+        }
+    }
+
+    pub fn get_track_eq_band(&self, index: usize, band: usize) -> Option<EqBand> {
+        self.tracks.get(index).and_then(|track| track.eq.band(band))
+    }
+
+    /// Set a track's compressor; use a disabled config to switch it off
+    pub fn set_track_compressor(&mut self, index: usize, config: CompressorConfig) {
+        if let Some(track) = self.tracks.get_mut(index) {
+            track.compressor.set_config(config);
+        }
+    }
+
+    pub fn get_track_compressor(&self, index: usize) -> Option<CompressorConfig> {
+        self.tracks.get(index).map(|track| track.compressor.config())
+    }
+
+    /// Key a track's compressor from another track's output, or from its own with `None`
+    pub fn set_track_sidechain(&mut self, index: usize, source: Option<usize>) {
+        let source = source.filter(|&source| source < self.tracks.len() && source != index);
+        if let Some(track) = self.tracks.get_mut(index) {
+            track.sidechain = source;
+        }
+    }
+
+    pub fn get_track_sidechain(&self, index: usize) -> Option<usize> {
+        self.tracks.get(index).and_then(|track| track.sidechain)
+    }
+
+    pub fn set_master_compressor(&mut self, config: CompressorConfig) {
+        self.compressor.set_config(config);
+    }
+
+    pub fn get_master_compressor(&self) -> CompressorConfig {
+        self.compressor.config()
+    }
+
+    /// Key the master compressor from a track's output, or from the mix with `None`
+    pub fn set_master_sidechain(&mut self, source: Option<usize>) {
+        self.compressor_sidechain = source.filter(|&source| source < self.tracks.len());
+    }
+
+    pub fn get_master_sidechain(&self) -> Option<usize> {
+        self.compressor_sidechain
+    }
+
+    pub fn set_reverb_config(&mut self, config: ReverbConfig) {
+        self.reverb.set_config(config);
+    }
+
+    pub fn get_reverb_config(&self) -> ReverbConfig {
+        self.reverb.config()
+    }
+
+    /// Set how much of a track goes to the reverb, from 0.0 to 1.0
+    pub fn set_track_reverb_send(&mut self, index: usize, amount: f32) {
+        if let Some(track) = self.tracks.get_mut(index) {
+            track.set_reverb_send(amount);
+        }
+    }
+
+    pub fn get_track_reverb_send(&self, index: usize) -> f32 {
+        self.tracks.get(index).map_or(0.0, |track| track.reverb_send)
+    }
+
+    /// Change one band of the master EQ
+    pub fn set_master_eq_band(&mut self, band: usize, settings: EqBand) {
+        self.eq.set_band(band, settings);
+    }
+
+    pub fn get_master_eq_band(&self, band: usize) -> Option<EqBand> {
+        self.eq.band(band)
+    }
+
+    /// Destination for a named parameter of a track's instrument, if it exists
+    pub fn parameter_destination(&self, track: usize, name: &str) -> Option<ModDestination> {
+        let info = self.tracks.get(track)?.instrument.parameters().iter().find(|info| info.name == name)?;
+        Some(ModDestination::Parameter { track, name: info.name })
+    }
+
+    /// Destination for a named Stage-wide parameter, if it exists
+    pub fn stage_destination(&self, name: &str) -> Option<ModDestination> {
+        let info = STAGE_PARAMETERS.iter().find(|info| info.name == name)?;
+        Some(ModDestination::Stage(info.name))
+    }
+
+    /// Add an LFO, returning its index; route it with `add_mod_route` and `ModSource::Lfo`
+    pub fn add_lfo(&mut self, lfo: Lfo) -> usize {
+        self.modulation.lfos.push(lfo);
+        self.modulation.lfos.len() - 1
+    }
+
+    /// Remove an LFO along with its routes, leaving the destinations at their unmodulated values
+    pub fn remove_lfo(&mut self, index: usize) -> Option<Lfo> {
+        if index >= self.modulation.lfos.len() {
+            return None;
+        }
+        self.remove_routes_from(ModSource::Lfo(index));
+        Some(self.modulation.lfos.remove(index))
+    }
+
+    pub fn lfo_count(&self) -> usize {
+        self.modulation.lfos.len()
+    }
+
+    pub fn lfo(&self, index: usize) -> Option<&Lfo> {
+        self.modulation.lfos.get(index)
+    }
+
+    pub fn lfo_mut(&mut self, index: usize) -> Option<&mut Lfo> {
+        self.modulation.lfos.get_mut(index)
+    }
+
+    /// Add a modulation envelope that runs on every hit of `track`, returning its index
+    /// Use `BreakpointEnvelope::from_adsr` for a plain ADSR shape.
+    pub fn add_mod_envelope(&mut self, track: usize, envelope: BreakpointEnvelope) -> usize {
+        self.modulation.envelopes.push(ModEnvelope::new(track, envelope));
+        self.modulation.envelopes.len() - 1
+    }
+
+    /// Remove a modulation envelope along with its routes
+    pub fn remove_mod_envelope(&mut self, index: usize) -> bool {
+        if index >= self.modulation.envelopes.len() {
+            return false;
+        }
+        self.remove_routes_from(ModSource::Envelope(index));
+        self.modulation.envelopes.remove(index);
+        true
+    }
+
+    /// Set a macro knob from 0.0 to 1.0
+    pub fn set_macro(&mut self, index: usize, value: f32) {
+        self.modulation.set_macro(index, value);
+    }
+
+    pub fn get_macro(&self, index: usize) -> f32 {
+        self.modulation.macros.get(index).copied().unwrap_or(0.0)
+    }
+
+    /// Route a source to a destination, returning the route index
+    /// `amount` is a fraction of the destination's range (-1.0 to 1.0), applied around its current value.
+    /// Returns None if the source or destination does not exist.
+    pub fn add_mod_route(&mut self, source: ModSource, destination: ModDestination, amount: f32) -> Option<usize> {
+        let source_exists = match source {
+            ModSource::Lfo(index) => index < self.modulation.lfos.len(),
+            ModSource::Envelope(index) => index < self.modulation.envelopes.len(),
+            ModSource::Macro(index) => index < self.modulation.macros.len(),
+            ModSource::Velocity | ModSource::StepIndex | ModSource::Random => true,
+        };
+        if !source_exists {
+            return None;
+        }
+        let info = self.destination_info(&destination)?;
+        let base = self
+            .modulation
+            .base(&destination)
+            .or_else(|| self.read_destination(&destination))
+            .unwrap_or(info.default);
+        let route = ModRoute::new(source, destination, amount);
+        Some(self.modulation.add_route(route, base, info.min, info.max))
+    }
+
+    /// Remove a route, returning its destination to the unmodulated value
+    pub fn remove_mod_route(&mut self, index: usize) -> Option<ModRoute> {
+        let route = self.modulation.remove_route(index)?;
+        // Only restore once the last route on this destination is gone
+        if !self.modulation.is_modulated(&route.destination) {
+            self.write_destination(&route.destination, route.base());
+        }
+        Some(route)
+    }
+
+    pub fn set_mod_route_amount(&mut self, index: usize, amount: f32) {
+        self.modulation.set_amount(index, amount);
+    }
+
+    pub fn mod_route_count(&self) -> usize {
+        self.modulation.routes().len()
+    }
+
+    pub fn mod_route(&self, index: usize) -> Option<&ModRoute> {
+        self.modulation.route(index)
+    }
+
+    /// Remove every route from `source` and shift the indices of later sources of its kind
+    fn remove_routes_from(&mut self, source: ModSource) {
+        while let Some(index) = self.modulation.routes().iter().position(|route| route.source == source) {
+            self.remove_mod_route(index);
+        }
+        self.modulation.source_removed(source);
+    }
+
+    /// Range and default of a destination, if it exists
+    fn destination_info(&self, destination: &ModDestination) -> Option<ParameterInfo> {
+        match *destination {
+            ModDestination::Parameter { track, name } => {
+                self.tracks.get(track)?.instrument.parameters().iter().find(|info| info.name == name).copied()
+            }
+            ModDestination::Pan(track) => self.tracks.get(track).map(|_| PAN_PARAMETER),
+            ModDestination::Stage(name) => STAGE_PARAMETERS.iter().find(|info| info.name == name).copied(),
+        }
+    }
 
-                // Trigger drum instruments based on patterns
-                // Pattern 0: Kick
-                if self.sequencer.patterns[0][current_step] {
-                    self.kick.trigger(current_time);
+    /// Current value of a destination, including any modulation
+    fn read_destination(&self, destination: &ModDestination) -> Option<f32> {
+        match *destination {
+            ModDestination::Parameter { track, name } => self.tracks.get(track)?.instrument.get_parameter(name),
+            ModDestination::Pan(track) => self.tracks.get(track).map(|track| track.pan),
+            ModDestination::Stage("saturation") => Some(self.saturation),
+            ModDestination::Stage("limiter_threshold") => Some(self.limiter.threshold),
+            ModDestination::Stage(_) => None,
+        }
+    }
+
+    fn write_destination(&mut self, destination: &ModDestination, value: f32) {
+        match *destination {
+            ModDestination::Parameter { track, name } => {
+                if let Some(track) = self.tracks.get_mut(track) {
+                    track.instrument.set_parameter(name, value);
                 }
-                // Pattern 1: Snare
-                if self.sequencer.patterns[1][current_step] {
-                    self.snare.trigger(current_time);
+            }
+            ModDestination::Pan(track) => {
+                if let Some(track) = self.tracks.get_mut(track) {
+                    track.set_pan(value);
                 }
-                // Pattern 2: Hi-hat
-                if self.sequencer.patterns[2][current_step] {
-                    self.hihat.trigger(current_time);
+            }
+            ModDestination::Stage("saturation") => self.saturation = value.clamp(0.0, 1.0),
+            ModDestination::Stage("limiter_threshold") => self.limiter.threshold = value,
+            ModDestination::Stage(_) => {}
+        }
+    }
+
+    /// Write every modulated destination for the control block about to be rendered
+    fn update_modulation(&mut self) {
+        self.modulation.update(self.current_time());
+        for index in 0..self.modulation.routes().len() {
+            if let Some((destination, value)) = self.modulation.destination_value(index) {
+                self.write_destination(&destination, value);
+            }
+        }
+
+        let seconds = CONTROL_BLOCK_SIZE as f64 / self.sample_rate as f64;
+        self.modulation.advance(seconds, self.sequencer.bpm);
+    }
+
+    /// Render the next sample as a mono mix and advance the stage clock by one sample
+    pub fn tick(&mut self) -> f32 {
+        let mut output = [0.0];
+        self.process_block(&mut output);
+        output[0]
+    }
+
+    /// Render the next stereo frame and advance the stage clock by one sample
+    pub fn tick_stereo(&mut self) -> (f32, f32) {
+        let mut left = [0.0];
+        let mut right = [0.0];
+        self.process_block_stereo(&mut left, &mut right);
+        (left[0], right[0])
+    }
+
+    /// Render a block of consecutive samples as a mono mix of the stereo output
+    pub fn process_block(&mut self, output: &mut [f32]) {
+        let mut left = [0.0; MAX_BLOCK_SIZE];
+        let mut right = [0.0; MAX_BLOCK_SIZE];
+
+        for chunk in output.chunks_mut(MAX_BLOCK_SIZE) {
+            let left = &mut left[..chunk.len()];
+            let right = &mut right[..chunk.len()];
+            self.process_block_stereo(left, right);
+
+            for ((sample, l), r) in chunk.iter_mut().zip(left.iter()).zip(right.iter()) {
+                *sample = (l + r) * 0.5;
+            }
+        }
+    }
+
+    /// Render a block of consecutive stereo frames from the current clock position
+    /// Both buffers must be the same length. Sequencer checks, saturation and limiting run once
+    /// per sub-block rather than per sample, and instruments that are not sounding are skipped entirely.
+    pub fn process_block_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        debug_assert_eq!(left.len(), right.len());
+        let len = left.len().min(right.len());
+        let mut offset = 0;
+
+        while offset < len {
+            // Trigger instruments if a step is due at the start of this sub-block
+            self.update_sequencer();
+
+            // End the sub-block where the next step is due so triggers stay sample accurate
+            let mut block_len = (len - offset).min(MAX_BLOCK_SIZE);
+            if self.sequencer.is_playing {
+                let samples_until_step = self.sequencer.samples_until_next_step(self.sample_position);
+                block_len = block_len.min(samples_until_step.min(MAX_BLOCK_SIZE as u64) as usize);
+            }
+
+            // Modulation updates on a fixed grid of control blocks counted from sample zero, so it
+            // lands on the same samples whatever size of blocks the host asks for
+            if !self.modulation.lfos.is_empty() || !self.modulation.routes().is_empty() {
+                let grid_offset = (self.sample_position % CONTROL_BLOCK_SIZE as u64) as usize;
+                block_len = block_len.min(CONTROL_BLOCK_SIZE - grid_offset);
+                if grid_offset == 0 {
+                    self.update_modulation();
                 }
-                // Pattern 3: Tom
-                if self.sequencer.patterns[3][current_step] {
-                    self.tom.trigger(current_time);
+            }
+
+            let block_time = self.current_time();
+            let range = offset..offset + block_len;
+            self.render_block(&mut left[range.clone()], &mut right[range], block_time);
+
+            self.sample_position += block_len as u64;
+            offset += block_len;
+        }
+    }
+
+    /// Absolute position of the next sample to be rendered
+    pub fn sample_position(&self) -> u64 {
+        self.sample_position
+    }
+
+    /// Move the stage clock, e.g. to follow a host clock
+    pub fn set_sample_position(&mut self, sample_position: u64) {
+        self.sample_position = sample_position;
+    }
+
+    /// Time of the next sample to be rendered in seconds
+    pub fn current_time(&self) -> f64 {
+        self.sample_position as f64 / self.sample_rate as f64
+    }
+
+    fn update_sequencer(&mut self) {
+        // Update sequencer and trigger instruments if needed
+        if self.sequencer.is_playing {
+            // Check if we should trigger instruments on the current step
+            if self.sequencer.should_trigger_step(self.sample_position) {
+                let current_time = self.current_time();
+                let current_step = self.sequencer.current_step;
+
+                // Trigger each track whose pattern has the current step set
+                self.modulation.on_step(current_step);
+                for index in 0..self.tracks.len() {
+                    if self.tracks[index].get_step(current_step) {
+                        self.tracks[index].instrument.trigger(current_time);
+                        let velocity = self.tracks[index].get_step_velocity(current_step);
+                        self.modulation.on_trigger(index, velocity, current_time);
+                    }
                 }
 
                 // Basic oscillators are NOT triggered by the sequencer
                 // They should only be triggered manually via "Trigger all instruments" button
 
                 // Mark that we've processed this step
-                self.sequencer.last_step_time = current_time;
-                self.sequencer.advance_step();
+                self.sequencer.advance_step(self.sample_position);
             }
         }
+    }
 
-        let mut output = 0.0;
-        
-        // Add drum instrument outputs
-        output += self.kick.tick(current_time);
-        output += self.snare.tick(current_time);
-        output += self.hihat.tick(current_time);
-        output += self.tom.tick(current_time);
-        
-        // Add legacy instruments for backward compatibility
+    /// Mix all sounding instruments into the stereo buffers and apply the master effects
+    fn render_block(&mut self, left: &mut [f32], right: &mut [f32], start_time: f64) {
+        let mut scratch = [0.0; MAX_BLOCK_SIZE];
+        let scratch = &mut scratch[..left.len()];
+        left.fill(0.0);
+        right.fill(0.0);
+
+        // Render every track before mixing so any of them can key another's compressor
+        let len = left.len();
+        for track in &mut self.tracks {
+            track.render(len, start_time);
+        }
+
+        // Add track outputs at their pan positions, collecting reverb sends on the way
+        let mut send = [0.0; MAX_BLOCK_SIZE];
+        let send = &mut send[..len];
+        let mut send_active = false;
+        let pan_law = self.pan_law;
+        for index in 0..self.tracks.len() {
+            let track = &self.tracks[index];
+            // A silent track's compressor still follows its key so ducking stays in step
+            if !track.is_sounding() && !track.compressor.is_enabled() {
+                continue;
+            }
+            scratch.copy_from_slice(track.output(len));
+            let mut compressor = track.compressor;
+            if compressor.is_enabled() {
+                let key = track.sidechain.filter(|&source| source < self.tracks.len()).unwrap_or(index);
+                compressor.process_block_keyed(scratch, self.tracks[key].output(len));
+            }
+            let sounding = track.is_sounding();
+            let (left_gain, right_gain) = pan_law.gains(track.pan);
+            self.tracks[index].compressor = compressor;
+            if sounding {
+                mix_into(left, scratch, left_gain);
+                mix_into(right, scratch, right_gain);
+                let reverb_send = self.tracks[index].reverb_send;
+                if reverb_send > 0.0 {
+                    mix_into(send, scratch, reverb_send);
+                    send_active = true;
+                }
+            }
+        }
+
+        // Keep running while the tail rings out after the sends stop
+        if send_active || !self.reverb.is_idle() {
+            self.reverb.process_block(send, left, right);
+        }
+
+        // Add legacy instruments for backward compatibility, centered
+        let (left_gain, right_gain) = pan_law.gains(0.0);
         for instrument in &mut self.instruments {
-            output += instrument.tick(current_time);
+            if instrument.is_active() {
+                instrument.process_block(scratch, start_time);
+                mix_into(left, scratch, left_gain);
+                mix_into(right, scratch, right_gain);
+            }
         }
-        
+
+        if self.eq.is_active() {
+            self.eq.process_block_stereo(left, right);
+        }
+        if self.compressor.is_enabled() {
+            match self.compressor_sidechain.filter(|&source| source < self.tracks.len()) {
+                Some(source) => self.compressor.process_block_stereo_keyed(left, right, self.tracks[source].output(len)),
+                None => self.compressor.process_block_stereo(left, right),
+            }
+        }
+
         // Apply harmonic distortion if enabled
         if self.saturation > 0.0 {
-            output = self.apply_harmonic_distortion(output);
+            for sample in left.iter_mut().chain(right.iter_mut()) {
+                *sample = self.apply_harmonic_distortion(*sample);
+            }
         }
-        
+
         // Apply limiter to the combined output
-        self.limiter.process(output)
+        self.limiter.process_block_stereo(left, right);
     }
 
     pub fn trigger_all(&mut self) {
+        let time = self.current_time();
         for instrument in &mut self.instruments {
-            instrument.trigger(self.current_time);
+            instrument.trigger(time);
         }
     }
 
     pub fn trigger_instrument(&mut self, index: usize) {
+        let time = self.current_time();
         if let Some(instrument) = self.instruments.get_mut(index) {
-            instrument.trigger(self.current_time);
+            instrument.trigger(time);
         }
     }
 
@@ -155,14 +814,16 @@ impl Stage {
     }
 
     pub fn release_instrument(&mut self, index: usize) {
+        let time = self.current_time();
         if let Some(instrument) = self.instruments.get_mut(index) {
-            instrument.release(self.current_time);
+            instrument.release(time);
         }
     }
 
     pub fn release_all(&mut self) {
+        let time = self.current_time();
         for instrument in &mut self.instruments {
-            instrument.release(self.current_time);
+            instrument.release(time);
         }
     }
 
@@ -214,6 +875,40 @@ impl Stage {
         }
     }
 
+    pub fn set_instrument_pulse_width(&mut self, index: usize, pulse_width: f32) {
+        if let Some(instrument) = self.instruments.get_mut(index) {
+            instrument.set_pulse_width(pulse_width);
+        }
+    }
+
+    pub fn get_instrument_pulse_width(&self, index: usize) -> f32 {
+        if let Some(instrument) = self.instruments.get(index) {
+            instrument.pulse_width
+        } else {
+            0.0
+        }
+    }
+
+    pub fn set_instrument_wavetable(&mut self, index: usize, wavetable: std::sync::Arc<crate::gen::wavetable::Wavetable>) {
+        if let Some(instrument) = self.instruments.get_mut(index) {
+            instrument.set_wavetable(wavetable);
+        }
+    }
+
+    pub fn set_instrument_wavetable_position(&mut self, index: usize, position: f32) {
+        if let Some(instrument) = self.instruments.get_mut(index) {
+            instrument.set_wavetable_position(position);
+        }
+    }
+
+    pub fn get_instrument_wavetable_position(&self, index: usize) -> f32 {
+        if let Some(instrument) = self.instruments.get(index) {
+            instrument.wavetable_position
+        } else {
+            0.0
+        }
+    }
+
     pub fn set_instrument_enabled(&mut self, index: usize, enabled: bool) {
         if let Some(instrument) = self.instruments.get_mut(index) {
             instrument.set_enabled(enabled);
@@ -231,24 +926,74 @@ impl Stage {
     /// Set the limiter threshold (typically 0.0 to 1.0)
     pub fn set_limiter_threshold(&mut self, threshold: f32) {
         self.limiter.threshold = threshold;
+        self.modulation.set_base(&ModDestination::Stage("limiter_threshold"), threshold);
     }
 
-    /// Get the current limiter threshold
+    /// Get the current limiter threshold, before any modulation
     pub fn get_limiter_threshold(&self) -> f32 {
-        self.limiter.threshold
+        self.modulation
+            .base(&ModDestination::Stage("limiter_threshold"))
+            .unwrap_or(self.limiter.threshold)
+    }
+
+    /// Choose between the lookahead limiter and a plain hard clip
+    pub fn set_limiter_mode(&mut self, mode: LimiterMode) {
+        self.limiter.set_mode(mode);
+    }
+
+    pub fn get_limiter_mode(&self) -> LimiterMode {
+        self.limiter.mode()
+    }
+
+    /// Set the limiter attack time in seconds
+    pub fn set_limiter_attack(&mut self, attack: f32) {
+        self.limiter.set_attack(attack);
+    }
+
+    pub fn get_limiter_attack(&self) -> f32 {
+        self.limiter.attack()
+    }
+
+    /// Set the limiter release time in seconds
+    pub fn set_limiter_release(&mut self, release: f32) {
+        self.limiter.set_release(release);
+    }
+
+    pub fn get_limiter_release(&self) -> f32 {
+        self.limiter.release()
+    }
+
+    /// Set how far ahead the limiter looks in seconds; the output is delayed by the same amount
+    pub fn set_limiter_lookahead(&mut self, lookahead: f32) {
+        self.limiter.set_lookahead(lookahead);
+    }
+
+    pub fn get_limiter_lookahead(&self) -> f32 {
+        self.limiter.lookahead()
     }
 
     // Sequencer control methods
 
-    /// Start the sequencer
+    /// Start the sequencer, playing the current step on the next rendered sample
     pub fn sequencer_play(&mut self) {
-        // Initialize with proper timing - use a small offset to ensure proper initialization
-        self.sequencer.play_at_time(0.001);
+        self.sequencer.play_at_sample(self.sample_position);
+        self.reset_synced_lfos();
     }
     
-    /// Start the sequencer with a specific time
-    pub fn sequencer_play_at_time(&mut self, time: f32) {
-        self.sequencer.play_at_time(time);
+    /// Start the sequencer with the current step due at a specific time in seconds
+    pub fn sequencer_play_at_time(&mut self, time: f64) {
+        let sample = (time.max(0.0) * self.sample_rate as f64).round() as u64;
+        self.sequencer.play_at_sample(sample);
+        self.reset_synced_lfos();
+    }
+
+    /// Line tempo-synced LFOs up with the start of playback
+    fn reset_synced_lfos(&mut self) {
+        for lfo in &mut self.modulation.lfos {
+            if lfo.rate.is_synced() {
+                lfo.reset();
+            }
+        }
     }
 
     /// Stop the sequencer
@@ -261,19 +1006,23 @@ impl Stage {
         self.sequencer.reset();
     }
 
-    /// Clear all patterns
+    /// Clear the patterns of every track
     pub fn sequencer_clear_all(&mut self) {
-        self.sequencer.clear_all();
+        for track in &mut self.tracks {
+            track.clear_pattern();
+        }
     }
 
-    /// Set a step for a specific instrument
-    pub fn sequencer_set_step(&mut self, instrument: usize, step: usize, enabled: bool) {
-        self.sequencer.set_step(instrument, step, enabled);
+    /// Set a step for a specific track
+    pub fn sequencer_set_step(&mut self, track: usize, step: usize, enabled: bool) {
+        if let Some(track) = self.tracks.get_mut(track) {
+            track.set_step(step, enabled);
+        }
     }
 
-    /// Get a step for a specific instrument
-    pub fn sequencer_get_step(&self, instrument: usize, step: usize) -> bool {
-        self.sequencer.get_step(instrument, step)
+    /// Get a step for a specific track
+    pub fn sequencer_get_step(&self, track: usize, step: usize) -> bool {
+        self.tracks.get(track).is_some_and(|track| track.get_step(step))
     }
 
     /// Set the BPM
@@ -296,65 +1045,73 @@ impl Stage {
         self.sequencer.is_playing
     }
     
-    /// Set up default test patterns for the drums
+    /// Set up default test patterns on the first track of each drum type
     pub fn sequencer_set_default_patterns(&mut self) {
         // Clear existing patterns
-        self.sequencer.clear_all();
+        self.sequencer_clear_all();
         
         // Kick: On beats 1, 5, 9, 13 (quarter notes)
-        self.sequencer.set_step(0, 0, true);  // Beat 1
-        self.sequencer.set_step(0, 4, true);  // Beat 5
-        self.sequencer.set_step(0, 8, true);  // Beat 9
-        self.sequencer.set_step(0, 12, true); // Beat 13
+        self.set_default_pattern::<KickDrum>(&[0, 4, 8, 12]);
         
         // Snare: On beats 5, 13 (backbeat)
-        self.sequencer.set_step(1, 4, true);  // Beat 5
-        self.sequencer.set_step(1, 12, true); // Beat 13
+        self.set_default_pattern::<SnareDrum>(&[4, 12]);
         
         // Hi-hat: On off-beats (8th notes)
-        for i in 0..16 {
-            if i % 2 == 1 {
-                self.sequencer.set_step(2, i, true);
-            }
-        }
+        self.set_default_pattern::<HiHat>(&[1, 3, 5, 7, 9, 11, 13, 15]);
         
         // Tom: Sparse pattern on beats 7, 15
-        self.sequencer.set_step(3, 6, true);  // Beat 7
-        self.sequencer.set_step(3, 14, true); // Beat 15
+        self.set_default_pattern::<TomDrum>(&[6, 14]);
+    }
+
+    fn set_default_pattern<T: Instrument + 'static>(&mut self, steps: &[usize]) {
+        if let Some(index) = self.find_track::<T>() {
+            for &step in steps {
+                self.tracks[index].set_step(step, true);
+            }
+        }
     }
     
     /// Get drum instrument configurations
+    /// These address the first track holding that kind of drum, falling back to the default config.
     pub fn get_kick_config(&self) -> KickConfig {
-        self.kick.config
+        self.first_instrument::<KickDrum>().map(|kick| kick.config).unwrap_or_else(KickConfig::default)
     }
     
     pub fn get_snare_config(&self) -> SnareConfig {
-        self.snare.config
+        self.first_instrument::<SnareDrum>().map(|snare| snare.config).unwrap_or_else(SnareConfig::default)
     }
     
     pub fn get_hihat_config(&self) -> HiHatConfig {
-        self.hihat.config
+        self.first_instrument::<HiHat>().map(|hihat| hihat.config).unwrap_or_else(HiHatConfig::closed_default)
     }
     
     pub fn get_tom_config(&self) -> TomConfig {
-        self.tom.config
+        self.first_instrument::<TomDrum>().map(|tom| tom.config).unwrap_or_else(TomConfig::default)
     }
     
     /// Set drum instrument configurations
     pub fn set_kick_config(&mut self, config: KickConfig) {
-        self.kick.set_config(config);
+        for kick in self.first_instruments_mut::<KickDrum>().into_iter().flatten() {
+            kick.set_config(config);
+        }
     }
     
     pub fn set_snare_config(&mut self, config: SnareConfig) {
-        self.snare.set_config(config);
+        for snare in self.first_instruments_mut::<SnareDrum>().into_iter().flatten() {
+            snare.set_config(config);
+        }
     }
     
     pub fn set_hihat_config(&mut self, config: HiHatConfig) {
-        self.hihat.set_config(config);
+        for hihat in self.first_instruments_mut::<HiHat>().into_iter().flatten() {
+            hihat.set_config(config);
+        }
     }
     
     pub fn set_tom_config(&mut self, config: TomConfig) {
-        self.tom.set_config(config);
+        for tom in self.first_instruments_mut::<TomDrum>().into_iter().flatten() {
+            tom.set_config(config);
+        }
     }
     
     /// Apply harmonic distortion using soft clipping
@@ -374,42 +1131,55 @@ impl Stage {
     /// Set the saturation level (0.0 to 1.0)
     pub fn set_saturation(&mut self, saturation: f32) {
         self.saturation = saturation.clamp(0.0, 1.0);
+        self.modulation.set_base(&ModDestination::Stage("saturation"), self.saturation);
     }
     
-    /// Get the current saturation level
+    /// Get the current saturation level, before any modulation
     pub fn get_saturation(&self) -> f32 {
-        self.saturation
+        self.modulation
+            .base(&ModDestination::Stage("saturation"))
+            .unwrap_or(self.saturation)
     }
     
     /// Trigger the kick drum
     pub fn trigger_kick(&mut self) {
-        self.kick.trigger(self.current_time);
+        if let Some(index) = self.find_track::<KickDrum>() {
+            self.trigger_track(index);
+        }
     }
     
     /// Trigger the snare drum
     pub fn trigger_snare(&mut self) {
-        self.snare.trigger(self.current_time);
+        if let Some(index) = self.find_track::<SnareDrum>() {
+            self.trigger_track(index);
+        }
     }
     
     /// Trigger the hi-hat
     pub fn trigger_hihat(&mut self) {
-        self.hihat.trigger(self.current_time);
+        if let Some(index) = self.find_track::<HiHat>() {
+            self.trigger_track(index);
+        }
     }
     
     /// Trigger the tom drum
     pub fn trigger_tom(&mut self) {
-        self.tom.trigger(self.current_time);
+        if let Some(index) = self.find_track::<TomDrum>() {
+            self.trigger_track(index);
+        }
     }
 }
 
 impl Sequencer {
-    pub fn new() -> Self {
+    pub fn new(sample_rate: f32) -> Self {
         Self {
-            patterns: [[false; 16]; 4],
             current_step: 0,
             is_playing: false,
             bpm: 120.0,
-            last_step_time: 0.0,
+            sample_rate: sample_rate as f64,
+            step_origin_sample: 0,
+            steps_since_origin: 0,
+            last_step_sample: 0,
             step_interval: 60.0 / (120.0 * 4.0), // 16th notes at 120 BPM
         }
     }
@@ -418,57 +1188,122 @@ impl Sequencer {
         self.is_playing = true;
     }
     
-    pub fn play_at_time(&mut self, time: f32) {
+    /// Start playing with the current step due at the given sample position
+    pub fn play_at_sample(&mut self, sample_position: u64) {
         self.is_playing = true;
-        self.last_step_time = time;
+        self.step_origin_sample = sample_position;
+        self.steps_since_origin = 0;
     }
 
     pub fn stop(&mut self) {
         self.is_playing = false;
     }
 
+    /// Return to step 0, due immediately
     pub fn reset(&mut self) {
         self.current_step = 0;
-        self.last_step_time = 0.0;
+        self.step_origin_sample = 0;
+        self.steps_since_origin = 0;
     }
 
-    pub fn clear_all(&mut self) {
-        self.patterns = [[false; 16]; 4];
-    }
+    pub fn set_bpm(&mut self, bpm: f32) {
+        // Clamp BPM to reasonable range
+        self.bpm = bpm.max(60.0).min(180.0);
+        // Recalculate step interval (16th notes)
+        self.step_interval = 60.0 / (self.bpm as f64 * 4.0);
 
-    pub fn set_step(&mut self, instrument: usize, step: usize, enabled: bool) {
-        if instrument < 4 && step < 16 {
-            self.patterns[instrument][step] = enabled;
+        // Measure the new tempo from the last step so the next one keeps its place in the groove
+        if self.steps_since_origin > 0 {
+            self.step_origin_sample = self.last_step_sample;
+            self.steps_since_origin = 1;
         }
     }
 
-    pub fn get_step(&self, instrument: usize, step: usize) -> bool {
-        if instrument < 4 && step < 16 {
-            self.patterns[instrument][step]
-        } else {
-            false
-        }
+    /// Time interval between steps in samples
+    pub fn step_interval_samples(&self) -> f64 {
+        self.step_interval * self.sample_rate
     }
 
-    pub fn set_bpm(&mut self, bpm: f32) {
-        // Clamp BPM to reasonable range
-        self.bpm = bpm.max(60.0).min(180.0);
-        // Recalculate step interval (16th notes)
-        self.step_interval = 60.0 / (self.bpm * 4.0);
+    /// Sample position at which the current step is due
+    pub fn next_step_sample(&self) -> u64 {
+        // Derive each step from the origin rather than the previous step so rounding never accumulates
+        let offset = (self.steps_since_origin as f64 * self.step_interval_samples()).round() as u64;
+        self.step_origin_sample + offset
+    }
+
+    pub fn should_trigger_step(&self, sample_position: u64) -> bool {
+        // Check if the clock has reached the next step
+        sample_position >= self.next_step_sample()
     }
 
-    pub fn update(&mut self, current_time: f32) {
-        // This method is called from tick() to update internal state
-        // The actual triggering logic is handled in tick()
+    /// Number of samples from `sample_position` until the next step is due (at least 1)
+    pub fn samples_until_next_step(&self, sample_position: u64) -> u64 {
+        self.next_step_sample().saturating_sub(sample_position).max(1)
     }
 
-    pub fn should_trigger_step(&self, current_time: f32) -> bool {
-        // Check if enough time has passed for the next step
-        current_time - self.last_step_time >= self.step_interval
+    /// Duration of one 16-step bar in seconds at the current BPM
+    pub fn bar_duration(&self) -> f64 {
+        self.step_interval * STEPS_PER_PATTERN as f64
     }
 
-    pub fn advance_step(&mut self) {
-        self.current_step = (self.current_step + 1) % 16;
+    /// Move to the next step after the current one was triggered at `sample_position`
+    pub fn advance_step(&mut self, sample_position: u64) {
+        self.current_step = (self.current_step + 1) % STEPS_PER_PATTERN;
+        self.last_step_sample = sample_position;
+        self.steps_since_origin += 1;
+
+        // If the clock jumped ahead, restart the timing from here instead of catching up step by step
+        if self.next_step_sample() <= sample_position {
+            self.step_origin_sample = sample_position;
+            self.steps_since_origin = 1;
+        }
+    }
+}
+
+/// Add each sample of `input`, scaled by `gain`, to the matching sample of `output`
+pub(crate) fn mix_into(output: &mut [f32], input: &[f32], gain: f32) {
+    for (out, sample) in output.iter_mut().zip(input) {
+        *out += sample * gain;
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::lfo::{LfoRate, LfoShape};
+
+    const SAMPLE_RATE: f32 = 44100.0;
+
+    fn modulated_groove() -> Stage {
+        let mut stage = Stage::new(SAMPLE_RATE);
+        stage.set_seed(3);
+        stage.sequencer_set_default_patterns();
+        let lfo = stage.add_lfo(Lfo::new(LfoShape::Sine, LfoRate::Hertz(3.0)));
+        let frequency = ModDestination::Parameter { track: 0, name: "frequency" };
+        let decay = ModDestination::Parameter { track: 2, name: "decay" };
+        stage.add_mod_route(ModSource::Lfo(lfo), frequency, 0.5).unwrap();
+        stage.add_mod_route(ModSource::Random, decay, 0.5).unwrap();
+        stage.sequencer_play();
+        stage
+    }
+
+    fn render(block: usize) -> Vec<f32> {
+        let mut stage = modulated_groove();
+        let mut left = vec![0.0; 22050];
+        let mut right = vec![0.0; 22050];
+        for (left, right) in left.chunks_mut(block).zip(right.chunks_mut(block)) {
+            stage.process_block_stereo(left, right);
+        }
+        left.extend(right);
+        left
+    }
+
+    #[test]
+    fn modulation_does_not_depend_on_host_block_size() {
+        let reference = render(256);
+        assert!(reference.iter().any(|&sample| sample != 0.0));
+        for block in [1, 7, 64, 1000] {
+            assert!(render(block) == reference, "{block}-frame blocks differ from 256-frame blocks");
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::effects::compressor::{Compressor, CompressorConfig};
+use crate::effects::eq::ParametricEq;
+use crate::instruments::{Instrument, VoicePool};
+use crate::stage::MAX_BLOCK_SIZE;
+
+/// Number of steps in a track's pattern
+pub const STEPS_PER_PATTERN: usize = 16;
+
+/// Sample rate a new track's EQ starts at, until the Stage it joins sets its own
+const DEFAULT_SAMPLE_RATE: f32 = 44100.0;
+
+/// Level below which the EQ's tail after the instrument stops is treated as finished
+const EQ_SILENCE: f32 = 1e-5;
+
+/// One lane of the Stage: an instrument and the sequencer pattern that plays it
+pub struct Track {
+    pub name: String,
+    pub instrument: Box<dyn Instrument>,
+    pub pattern: [bool; STEPS_PER_PATTERN],
+    /// Velocity of each step from 0.0 to 1.0, available as a modulation source
+    pub velocities: [f32; STEPS_PER_PATTERN],
+    /// Stereo position from -1.0 (hard left) to 1.0 (hard right)
+    pub pan: f32,
+    /// Equaliser applied to the instrument's output before panning
+    pub eq: ParametricEq,
+    /// Compressor applied after the EQ
+    pub compressor: Compressor,
+    /// Track whose output drives the compressor instead of this track's own, for ducking
+    pub sidechain: Option<usize>,
+    /// Level sent to the Stage's reverb from 0.0 to 1.0, taken after the compressor
+    pub reverb_send: f32,
+    /// This track's instrument and EQ output for the block being rendered
+    output: [f32; MAX_BLOCK_SIZE],
+    /// Whether that block holds any sound, including the EQ's tail
+    sounding: bool,
+    /// Set while the EQ may still be ringing after the instrument stopped
+    eq_ringing: bool,
+}
+
+impl std::fmt::Debug for Track {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Track")
+            .field("name", &self.name)
+            .field("pattern", &self.pattern)
+            .field("pan", &self.pan)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Track {
+    /// Create a centered track with an empty pattern
+    /// The instrument should be built at the same sample rate as the Stage it is added to.
+    pub fn new(name: impl Into<String>, instrument: Box<dyn Instrument>) -> Self {
+        Self {
+            name: name.into(),
+            instrument,
+            pattern: [false; STEPS_PER_PATTERN],
+            velocities: [1.0; STEPS_PER_PATTERN],
+            pan: 0.0,
+            eq: ParametricEq::new(DEFAULT_SAMPLE_RATE),
+            compressor: Compressor::new(DEFAULT_SAMPLE_RATE, CompressorConfig::disabled()),
+            sidechain: None,
+            reverb_send: 0.0,
+            output: [0.0; MAX_BLOCK_SIZE],
+            sounding: false,
+            eq_ringing: false,
+        }
+    }
+
+    /// Follow the Stage's sample rate for the track's effects
+    pub(crate) fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.eq.set_sample_rate(sample_rate);
+        if sample_rate != self.compressor.sample_rate {
+            self.compressor = Compressor::new(sample_rate, self.compressor.config());
+        }
+    }
+
+    /// Render `len` samples of the instrument through the EQ into the track's buffer
+    /// After the instrument stops, the EQ keeps running on silence until its tail dies away.
+    pub(crate) fn render(&mut self, len: usize, start_time: f64) {
+        let output = &mut self.output[..len];
+        let playing = self.instrument.is_active();
+        if playing {
+            self.instrument.process_block(output, start_time);
+        } else {
+            output.fill(0.0);
+            if !self.eq_ringing {
+                self.sounding = false;
+                return;
+            }
+        }
+        if self.eq.is_active() {
+            self.eq.process_block(output);
+        }
+
+        if playing {
+            self.eq_ringing = self.eq.is_active();
+        } else {
+            let peak = output.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            if peak < EQ_SILENCE {
+                // Clear what's left so the next hit doesn't start from stale filter state
+                self.eq.reset();
+                self.eq_ringing = false;
+            }
+        }
+        self.sounding = true;
+    }
+
+    /// Whether the instrument produced sound in the last rendered block
+    pub(crate) fn is_sounding(&self) -> bool {
+        self.sounding
+    }
+
+    /// The last rendered block, before compression
+    pub(crate) fn output(&self, len: usize) -> &[f32] {
+        &self.output[..len]
+    }
+
+    pub fn set_reverb_send(&mut self, amount: f32) {
+        self.reverb_send = amount.clamp(0.0, 1.0);
+    }
+
+    pub fn set_pan(&mut self, pan: f32) {
+        self.pan = pan.clamp(-1.0, 1.0);
+    }
+
+    pub fn set_step(&mut self, step: usize, enabled: bool) {
+        if let Some(slot) = self.pattern.get_mut(step) {
+            *slot = enabled;
+        }
+    }
+
+    pub fn get_step(&self, step: usize) -> bool {
+        self.pattern.get(step).copied().unwrap_or(false)
+    }
+
+    pub fn set_step_velocity(&mut self, step: usize, velocity: f32) {
+        if let Some(slot) = self.velocities.get_mut(step) {
+            *slot = velocity.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn get_step_velocity(&self, step: usize) -> f32 {
+        self.velocities.get(step).copied().unwrap_or(1.0)
+    }
+
+    /// Turn every step off and return velocities to full
+    pub fn clear_pattern(&mut self) {
+        self.pattern = [false; STEPS_PER_PATTERN];
+        self.velocities = [1.0; STEPS_PER_PATTERN];
+    }
+
+    /// Get the instrument as its concrete type, if it is a `T`
+    /// For a pool of `T` voices this is the first voice.
+    pub fn instrument_as<T: Instrument + 'static>(&self) -> Option<&T> {
+        let instrument = self.instrument.as_any();
+        instrument
+            .downcast_ref::<T>()
+            .or_else(|| instrument.downcast_ref::<VoicePool<T>>().map(|pool| &pool.voices()[0]))
+    }
+
+    /// Get the instrument as its concrete type for modification, if it is a `T`
+    pub fn instrument_as_mut<T: Instrument + 'static>(&mut self) -> Option<&mut T> {
+        self.instrument.as_any_mut().downcast_mut::<T>()
+    }
+
+    /// Every voice of the instrument as its concrete type: one for a plain `T`, all of them for a pool
+    pub fn instruments_as_mut<T: Instrument + 'static>(&mut self) -> Option<&mut [T]> {
+        if self.instrument.as_any().is::<T>() {
+            return self.instrument_as_mut::<T>().map(std::slice::from_mut);
+        }
+        self.instrument
+            .as_any_mut()
+            .downcast_mut::<VoicePool<T>>()
+            .map(|pool| pool.voices_mut())
+    }
+}
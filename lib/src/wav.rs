@@ -0,0 +1,388 @@
+//! WAV file import and export
+//! Writes interleaved f32 frames (e.g. from the OfflineRenderer) as 16-bit or 24-bit PCM,
+//! or 32-bit IEEE float WAV files, and reads the same formats back (e.g. wavetables).
+
+use crate::stage::Stage;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+/// Full-scale values of the integer formats; writing and reading share them so levels round-trip
+const INT16_SCALE: f32 = i16::MAX as f32;
+const INT24_SCALE: f32 = 8_388_607.0;
+
+/// Sample encoding used in the WAV data chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WavFormat {
+    Int16,
+    Int24,
+    Float32,
+}
+
+impl WavFormat {
+    fn bits_per_sample(self) -> u16 {
+        match self {
+            WavFormat::Int16 => 16,
+            WavFormat::Int24 => 24,
+            WavFormat::Float32 => 32,
+        }
+    }
+
+    fn format_tag(self) -> u16 {
+        match self {
+            WavFormat::Int16 | WavFormat::Int24 => 1, // WAVE_FORMAT_PCM
+            WavFormat::Float32 => 3,                  // WAVE_FORMAT_IEEE_FLOAT
+        }
+    }
+
+    fn from_tag_and_bits(format_tag: u16, bits_per_sample: u16) -> Option<Self> {
+        match (format_tag, bits_per_sample) {
+            (1, 16) => Some(WavFormat::Int16),
+            (1, 24) => Some(WavFormat::Int24),
+            (3, 32) => Some(WavFormat::Float32),
+            _ => None,
+        }
+    }
+}
+
+/// Layout of the audio written to a WAV file
+#[derive(Debug, Clone, Copy)]
+pub struct WavSpec {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub format: WavFormat,
+}
+
+impl WavSpec {
+    pub fn new(channels: u16, sample_rate: u32, format: WavFormat) -> Self {
+        Self {
+            channels: channels.max(1),
+            sample_rate,
+            format,
+        }
+    }
+
+    /// Spec matching the given stage's sample rate
+    pub fn for_stage(stage: &Stage, channels: u16, format: WavFormat) -> Self {
+        Self::new(channels, stage.sample_rate.round() as u32, format)
+    }
+
+    fn block_align(&self) -> u16 {
+        self.channels * (self.format.bits_per_sample() / 8)
+    }
+}
+
+/// Write interleaved samples to any writer as a complete WAV file
+pub fn write_wav<W: Write>(writer: &mut W, samples: &[f32], spec: WavSpec) -> Result<(), anyhow::Error> {
+    if !samples.len().is_multiple_of(spec.channels as usize) {
+        return Err(anyhow::anyhow!(
+            "Sample count {} is not a multiple of the channel count {}",
+            samples.len(),
+            spec.channels
+        ));
+    }
+
+    let bytes_per_sample = (spec.format.bits_per_sample() / 8) as u64;
+    let data_size = samples.len() as u64 * bytes_per_sample;
+    let is_float = spec.format == WavFormat::Float32;
+
+    // Non-PCM formats carry a cbSize field and a fact chunk
+    let fmt_size: u32 = if is_float { 18 } else { 16 };
+    let fact_size: u64 = if is_float { 12 } else { 0 };
+    // Chunks are padded to an even length, e.g. 24-bit audio with an odd sample count
+    let pad_size = data_size & 1;
+    let riff_size = 4 + (8 + fmt_size as u64) + fact_size + 8 + data_size + pad_size;
+    if riff_size > u32::MAX as u64 {
+        return Err(anyhow::anyhow!("Rendered audio is too long for a WAV file"));
+    }
+
+    // RIFF header
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(riff_size as u32).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    // Format chunk
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&fmt_size.to_le_bytes())?;
+    writer.write_all(&spec.format.format_tag().to_le_bytes())?;
+    writer.write_all(&spec.channels.to_le_bytes())?;
+    writer.write_all(&spec.sample_rate.to_le_bytes())?;
+    let byte_rate = spec.sample_rate * spec.block_align() as u32;
+    writer.write_all(&byte_rate.to_le_bytes())?;
+    writer.write_all(&spec.block_align().to_le_bytes())?;
+    writer.write_all(&spec.format.bits_per_sample().to_le_bytes())?;
+    if is_float {
+        writer.write_all(&0u16.to_le_bytes())?;
+
+        let frame_count = (samples.len() / spec.channels as usize) as u32;
+        writer.write_all(b"fact")?;
+        writer.write_all(&4u32.to_le_bytes())?;
+        writer.write_all(&frame_count.to_le_bytes())?;
+    }
+
+    // Data chunk
+    writer.write_all(b"data")?;
+    writer.write_all(&(data_size as u32).to_le_bytes())?;
+    for &sample in samples {
+        match spec.format {
+            WavFormat::Int16 => {
+                let value = (sample.clamp(-1.0, 1.0) * INT16_SCALE).round() as i16;
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            WavFormat::Int24 => {
+                let value = (sample.clamp(-1.0, 1.0) * INT24_SCALE).round() as i32;
+                writer.write_all(&value.to_le_bytes()[..3])?;
+            }
+            WavFormat::Float32 => {
+                writer.write_all(&sample.to_le_bytes())?;
+            }
+        }
+    }
+    if pad_size != 0 {
+        writer.write_all(&[0])?;
+    }
+
+    Ok(())
+}
+
+/// Write interleaved samples to a WAV file at the given path
+pub fn write_wav_file<P: AsRef<Path>>(path: P, samples: &[f32], spec: WavSpec) -> Result<(), anyhow::Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_wav(&mut writer, samples, spec)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read a complete WAV file from any reader
+/// Returns the interleaved samples scaled to -1.0..1.0 and the file's layout. The most negative
+/// integer value, which has no positive counterpart, reads as -1.0.
+pub fn read_wav<R: Read>(reader: &mut R) -> Result<(Vec<f32>, WavSpec), anyhow::Error> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(anyhow::anyhow!("Not a RIFF/WAVE file"));
+    }
+
+    let mut spec = None;
+    let mut data = None;
+    let mut offset = 12;
+
+    // Walk the chunks, skipping any we do not need (LIST, fact, cue, ...)
+    while offset <= bytes.len().saturating_sub(8) {
+        let id = &bytes[offset..offset + 4];
+        let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into()?) as usize;
+        let body_start = offset + 8;
+        // A corrupt size can run past the end of the address space on 32-bit targets
+        let body_end = body_start
+            .checked_add(size)
+            .ok_or_else(|| anyhow::anyhow!("WAV chunk size {} is out of range", size))?;
+        let body = &bytes[body_start..body_end.min(bytes.len())];
+
+        match id {
+            b"fmt " => spec = Some(parse_fmt_chunk(body)?),
+            b"data" => data = Some(body),
+            _ => {}
+        }
+
+        // Chunks are padded to an even length
+        offset = body_end
+            .checked_add(size & 1)
+            .ok_or_else(|| anyhow::anyhow!("WAV chunk size {} is out of range", size))?;
+    }
+
+    let spec = spec.ok_or_else(|| anyhow::anyhow!("WAV file has no fmt chunk"))?;
+    let data = data.ok_or_else(|| anyhow::anyhow!("WAV file has no data chunk"))?;
+
+    let samples = match spec.format {
+        WavFormat::Int16 => data
+            .chunks_exact(2)
+            .map(|b| (i16::from_le_bytes([b[0], b[1]]) as f32 / INT16_SCALE).max(-1.0))
+            .collect(),
+        WavFormat::Int24 => data
+            .chunks_exact(3)
+            .map(|b| ((i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / INT24_SCALE).max(-1.0))
+            .collect(),
+        WavFormat::Float32 => data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    };
+
+    Ok((samples, spec))
+}
+
+/// Read a WAV file from the given path
+pub fn read_wav_file<P: AsRef<Path>>(path: P) -> Result<(Vec<f32>, WavSpec), anyhow::Error> {
+    let mut file = File::open(path)?;
+    read_wav(&mut file)
+}
+
+fn parse_fmt_chunk(body: &[u8]) -> Result<WavSpec, anyhow::Error> {
+    if body.len() < 16 {
+        return Err(anyhow::anyhow!("WAV fmt chunk is too short"));
+    }
+
+    let read_u16 = |at: usize| u16::from_le_bytes([body[at], body[at + 1]]);
+    let mut format_tag = read_u16(0);
+    let channels = read_u16(2);
+    let sample_rate = u32::from_le_bytes(body[4..8].try_into()?);
+    let bits_per_sample = read_u16(14);
+
+    // WAVE_FORMAT_EXTENSIBLE keeps the real format tag at the start of the sub-format GUID
+    if format_tag == 0xFFFE && body.len() >= 26 {
+        format_tag = read_u16(24);
+    }
+
+    let format = WavFormat::from_tag_and_bits(format_tag, bits_per_sample).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Unsupported WAV format (tag {}, {} bits); use 16/24-bit PCM or 32-bit float",
+            format_tag,
+            bits_per_sample
+        )
+    })?;
+
+    if channels == 0 {
+        return Err(anyhow::anyhow!("WAV file has no channels"));
+    }
+
+    Ok(WavSpec::new(channels, sample_rate, format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(samples: &[f32], spec: WavSpec) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_wav(&mut bytes, samples, spec).unwrap();
+        bytes
+    }
+
+    fn round_trip(samples: &[f32], spec: WavSpec, tolerance: f32) {
+        let bytes = encode(samples, spec);
+        assert_eq!(bytes.len() % 2, 0, "RIFF data must stay word aligned");
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize, bytes.len() - 8);
+
+        let (read, read_spec) = read_wav(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read_spec.channels, spec.channels);
+        assert_eq!(read_spec.sample_rate, spec.sample_rate);
+        assert_eq!(read_spec.format, spec.format);
+        assert_eq!(read.len(), samples.len());
+        for (&original, &read) in samples.iter().zip(&read) {
+            assert!((original - read).abs() <= tolerance, "{original} read back as {read}");
+        }
+    }
+
+    fn test_signal(len: usize) -> Vec<f32> {
+        let mut samples: Vec<f32> = (0..len).map(|index| (index as f32 * 0.37).sin() * 0.8).collect();
+        // Full scale in both directions must survive
+        samples[0] = 1.0;
+        samples[1] = -1.0;
+        samples
+    }
+
+    #[test]
+    fn integer_formats_round_trip_within_one_step() {
+        for channels in [1, 2] {
+            let samples = test_signal(64 * channels as usize);
+            round_trip(&samples, WavSpec::new(channels, 44100, WavFormat::Int16), 0.5 / INT16_SCALE);
+            round_trip(&samples, WavSpec::new(channels, 48000, WavFormat::Int24), 0.5 / INT24_SCALE);
+        }
+    }
+
+    #[test]
+    fn float_round_trips_exactly() {
+        for channels in [1, 2] {
+            let samples = test_signal(64 * channels as usize);
+            round_trip(&samples, WavSpec::new(channels, 96000, WavFormat::Float32), 0.0);
+        }
+    }
+
+    #[test]
+    fn odd_length_24_bit_data_is_padded() {
+        let samples = test_signal(5);
+        let bytes = encode(&samples, WavSpec::new(1, 44100, WavFormat::Int24));
+        // 44-byte header, 15 bytes of samples and one pad byte
+        assert_eq!(bytes.len(), 44 + 15 + 1);
+        assert_eq!(bytes[bytes.len() - 1], 0);
+        round_trip(&samples, WavSpec::new(1, 44100, WavFormat::Int24), 0.5 / INT24_SCALE);
+    }
+
+    #[test]
+    fn most_negative_integer_reads_as_minus_one() {
+        let mut bytes = encode(&[0.0, 0.0], WavSpec::new(1, 44100, WavFormat::Int16));
+        let data = bytes.len() - 4;
+        bytes[data..].copy_from_slice(&[0x00, 0x80, 0xFF, 0x7F]);
+        let (samples, _) = read_wav(&mut bytes.as_slice()).unwrap();
+        assert_eq!(samples, vec![-1.0, 1.0]);
+    }
+
+    #[test]
+    fn reads_extensible_header() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(4 + 8 + 40 + 8 + 8u32).to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&40u32.to_le_bytes());
+        bytes.extend_from_slice(&0xFFFEu16.to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&48000u32.to_le_bytes());
+        bytes.extend_from_slice(&(48000u32 * 4).to_le_bytes());
+        bytes.extend_from_slice(&4u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        // cbSize, valid bits and channel mask, then the PCM sub-format GUID
+        bytes.extend_from_slice(&22u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(&[
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+        ]);
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&8u32.to_le_bytes());
+        for value in [i16::MAX, 0, 0, i16::MAX] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let (samples, spec) = read_wav(&mut bytes.as_slice()).unwrap();
+        assert_eq!(spec.channels, 2);
+        assert_eq!(spec.sample_rate, 48000);
+        assert_eq!(spec.format, WavFormat::Int16);
+        assert_eq!(samples, vec![1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn oversized_data_chunk_reads_what_is_there() {
+        let samples = test_signal(8);
+        let mut bytes = encode(&samples, WavSpec::new(2, 44100, WavFormat::Float32));
+        // Claim far more data than the file holds, as a truncated download would
+        let size_at = bytes.len() - samples.len() * 4 - 4;
+        bytes[size_at..size_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let (read, _) = read_wav(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read, samples);
+
+        // Cut off mid-sample: only whole samples come back
+        bytes.truncate(bytes.len() - 2);
+        let (read, _) = read_wav(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read, samples[..samples.len() - 1]);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(read_wav(&mut &b"RIFF\0\0\0\0WAVX"[..]).is_err());
+
+        let bytes = encode(&[0.5; 4], WavSpec::new(1, 44100, WavFormat::Int16));
+        // A fmt chunk too short to hold a format
+        let mut short_fmt = bytes.clone();
+        short_fmt[16..20].copy_from_slice(&8u32.to_le_bytes());
+        assert!(read_wav(&mut short_fmt.as_slice()).is_err());
+
+        // No data chunk at all
+        let header_only = &bytes[..36];
+        assert!(read_wav(&mut &header_only[..]).is_err());
+
+        // Samples that don't fill whole frames can't be written
+        assert!(write_wav(&mut Vec::new(), &[0.0; 3], WavSpec::new(2, 44100, WavFormat::Int16)).is_err());
+    }
+}
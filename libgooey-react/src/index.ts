@@ -5,6 +5,8 @@ export interface WasmStage {
   free(): void;
   add_oscillator(sample_rate: number, frequency_hz: number): void;
   tick(current_time: number): number;
+  process_block(output: Float32Array, start_time: number): void;
+  process_block_stereo(left: Float32Array, right: Float32Array, start_time: number): void;
   trigger_all(): void;
   trigger_instrument(index: number): void;
   set_instrument_volume(index: number, volume: number): void;
@@ -24,6 +26,12 @@ export interface WasmStage {
   get_instrument_waveform(index: number): number;
   set_instrument_modulator_frequency(index: number, frequency_hz: number): void;
   get_instrument_modulator_frequency(index: number): number;
+  set_instrument_pulse_width(index: number, pulse_width: number): void;
+  get_instrument_pulse_width(index: number): number;
+  set_instrument_wavetable(index: number, samples: Float32Array, frame_size: number): void;
+  load_instrument_wavetable_preset(index: number, preset_name: string): void;
+  set_instrument_wavetable_position(index: number, position: number): void;
+  get_instrument_wavetable_position(index: number): number;
   set_instrument_enabled(index: number, enabled: boolean): void;
   is_instrument_enabled(index: number): boolean;
   sequencer_play(): void;
@@ -31,8 +39,8 @@ export interface WasmStage {
   sequencer_stop(): void;
   sequencer_reset(): void;
   sequencer_clear_all(): void;
-  sequencer_set_step(instrument: number, step: number, enabled: boolean): void;
-  sequencer_get_step(instrument: number, step: number): boolean;
+  sequencer_set_step(track: number, step: number, enabled: boolean): void;
+  sequencer_get_step(track: number, step: number): boolean;
   sequencer_set_bpm(bpm: number): void;
   sequencer_get_bpm(): number;
   sequencer_get_current_step(): number;
@@ -58,7 +66,12 @@ export interface WasmStage {
     crack: number,
     decay: number,
     pitch_drop: number,
-    volume: number
+    volume: number,
+    filter_type?: number,
+    filter_cutoff?: number,
+    filter_resonance?: number,
+    filter_env_amount?: number,
+    filter_env_decay?: number
   ): void;
   set_hihat_config(
     frequency: number,
@@ -67,7 +80,12 @@ export interface WasmStage {
     decay: number,
     attack: number,
     volume: number,
-    is_open: boolean
+    is_open: boolean,
+    filter_type?: number,
+    filter_cutoff?: number,
+    filter_resonance?: number,
+    filter_env_amount?: number,
+    filter_env_decay?: number
   ): void;
   set_tom_config(
     frequency: number,
@@ -75,7 +93,12 @@ export interface WasmStage {
     punch: number,
     decay: number,
     pitch_drop: number,
-    volume: number
+    volume: number,
+    filter_type?: number,
+    filter_cutoff?: number,
+    filter_resonance?: number,
+    filter_env_amount?: number,
+    filter_env_decay?: number
   ): void;
   load_kick_preset(preset_name: string): void;
   load_snare_preset(preset_name: string): void;
@@ -83,10 +106,136 @@ export interface WasmStage {
   load_tom_preset(preset_name: string): void;
   set_saturation(saturation: number): void;
   get_saturation(): number;
+  set_limiter_threshold(threshold: number): void;
+  get_limiter_threshold(): number;
+  set_limiter_mode(mode: number): void;
+  get_limiter_mode(): number;
+  set_limiter_attack(attack: number): void;
+  get_limiter_attack(): number;
+  set_limiter_release(release: number): void;
+  get_limiter_release(): number;
+  set_limiter_lookahead(lookahead: number): void;
+  get_limiter_lookahead(): number;
+  get_limiter_gain_reduction(): number;
   trigger_kick(): void;
   trigger_snare(): void;
   trigger_hihat(): void;
   trigger_tom(): void;
+  add_kick_track(name: string, preset_name: string): number;
+  add_snare_track(name: string, preset_name: string): number;
+  add_hihat_track(name: string, preset_name: string): number;
+  add_tom_track(name: string, preset_name: string): number;
+  add_fm_track(name: string, preset_name: string): number;
+  add_oscillator_track(name: string, frequency_hz: number): number;
+  remove_track(index: number): boolean;
+  move_track(from: number, to: number): boolean;
+  track_count(): number;
+  get_track_name(index: number): string | undefined;
+  set_track_name(index: number, name: string): void;
+  set_track_pan(index: number, pan: number): void;
+  get_track_pan(index: number): number;
+  set_track_retrigger_mode(index: number, mode: number): void;
+  get_track_retrigger_mode(index: number): number;
+  set_track_polyphony(index: number, voices: number): void;
+  get_track_polyphony(index: number): number;
+  set_track_voice_stealing(index: number, stealing: number): void;
+  get_track_voice_stealing(index: number): number;
+  get_eq_band_count(): number;
+  set_track_eq_band(
+    index: number,
+    band: number,
+    band_type: number,
+    frequency: number,
+    gain_db: number,
+    q: number,
+    slope: number,
+    enabled: boolean
+  ): void;
+  get_track_eq_band(index: number, band: number): Float32Array;
+  set_master_eq_band(
+    band: number,
+    band_type: number,
+    frequency: number,
+    gain_db: number,
+    q: number,
+    slope: number,
+    enabled: boolean
+  ): void;
+  get_master_eq_band(band: number): Float32Array;
+  set_track_compressor(
+    index: number,
+    threshold_db: number,
+    ratio: number,
+    knee_db: number,
+    attack: number,
+    release: number,
+    makeup_db: number,
+    detector: number,
+    enabled: boolean
+  ): void;
+  get_track_compressor(index: number): Float32Array;
+  set_track_sidechain(index: number, source?: number): void;
+  get_track_sidechain(index: number): number | undefined;
+  get_track_compressor_gain_reduction(index: number): number;
+  set_master_compressor(
+    threshold_db: number,
+    ratio: number,
+    knee_db: number,
+    attack: number,
+    release: number,
+    makeup_db: number,
+    detector: number,
+    enabled: boolean
+  ): void;
+  get_master_compressor(): Float32Array;
+  set_master_sidechain(source?: number): void;
+  get_master_sidechain(): number | undefined;
+  get_master_compressor_gain_reduction(): number;
+  set_reverb(size: number, damping: number, pre_delay: number, width: number, mix: number): void;
+  get_reverb(): Float32Array;
+  set_track_reverb_send(index: number, amount: number): void;
+  get_track_reverb_send(index: number): number;
+  set_pan_law(pan_law: number): void;
+  get_pan_law(): number;
+  set_seed(seed: number): void;
+  trigger_track(index: number): void;
+  release_track(index: number): void;
+  get_track_parameter_names(index: number): string[];
+  set_track_parameter(index: number, name: string, value: number): boolean;
+  get_track_parameter(index: number, name: string): number | undefined;
+  add_lfo(shape: number, rate: number, synced: boolean, retrigger: boolean): number;
+  remove_lfo(index: number): boolean;
+  lfo_count(): number;
+  set_lfo_shape(index: number, shape: number): void;
+  set_lfo_rate_hz(index: number, frequency: number): void;
+  set_lfo_rate_beats(index: number, beats: number): void;
+  set_lfo_retrigger(index: number, retrigger: boolean): void;
+  get_lfo_value(index: number): number;
+  add_mod_envelope(track: number, attack: number, decay: number, sustain: number, release: number): number;
+  add_mod_breakpoint_envelope(
+    track: number,
+    durations: Float32Array | number[],
+    levels: Float32Array | number[],
+    exponential: boolean,
+    loop_start?: number,
+    loop_end?: number
+  ): number;
+  remove_mod_envelope(index: number): boolean;
+  set_macro(index: number, value: number): void;
+  get_macro(index: number): number;
+  add_mod_route(
+    source: number,
+    source_index: number,
+    track: number,
+    parameter: string,
+    amount: number
+  ): number | undefined;
+  add_stage_mod_route(source: number, source_index: number, parameter: string, amount: number): number | undefined;
+  remove_mod_route(index: number): boolean;
+  set_mod_route_amount(index: number, amount: number): void;
+  mod_route_count(): number;
+  sequencer_set_step_velocity(track: number, step: number, velocity: number): void;
+  sequencer_get_step_velocity(track: number, step: number): number;
 }
 
 export interface WasmKickDrum {
@@ -137,7 +286,12 @@ export interface WasmHiHat {
     decay_time: number,
     attack_time: number,
     volume: number,
-    is_open: boolean
+    is_open: boolean,
+    filter_type?: number,
+    filter_cutoff?: number,
+    filter_resonance?: number,
+    filter_env_amount?: number,
+    filter_env_decay?: number
   ): void;
 }
 
@@ -162,7 +316,12 @@ export interface WasmSnareDrum {
     crack_amount: number,
     decay_time: number,
     pitch_drop: number,
-    volume: number
+    volume: number,
+    filter_type?: number,
+    filter_cutoff?: number,
+    filter_resonance?: number,
+    filter_env_amount?: number,
+    filter_env_decay?: number
   ): void;
 }
 
@@ -185,7 +344,34 @@ export interface WasmTomDrum {
     punch_amount: number,
     decay_time: number,
     pitch_drop: number,
-    volume: number
+    volume: number,
+    filter_type?: number,
+    filter_cutoff?: number,
+    filter_resonance?: number,
+    filter_env_amount?: number,
+    filter_env_decay?: number
+  ): void;
+}
+
+export interface WasmFmSynth {
+  free(): void;
+  new_with_preset(sample_rate: number, preset_name: string): WasmFmSynth;
+  trigger(time: number): void;
+  release(time: number): void;
+  tick(current_time: number): number;
+  is_active(): boolean;
+  set_volume(volume: number): void;
+  set_frequency(frequency: number): void;
+  set_algorithm(algorithm: number): void;
+  set_operator(
+    index: number,
+    ratio: number,
+    level: number,
+    feedback: number,
+    attack: number,
+    decay: number,
+    sustain: number,
+    release: number
   ): void;
 }
 
@@ -200,6 +386,7 @@ export interface LibGooeyWasm {
   };
   WasmSnareDrum: new (sample_rate: number) => WasmSnareDrum;
   WasmTomDrum: new (sample_rate: number) => WasmTomDrum;
+  WasmFmSynth: new (sample_rate: number) => WasmFmSynth;
   init(): Promise<void>;
 }
 